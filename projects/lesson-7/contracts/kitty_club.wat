@@ -0,0 +1,90 @@
+;; Kitty club: a contract owning kitties, which anyone can breed and only its deployer can trade.
+;;
+;; The call input is an encoded runtime call, dispatched by the contract with `ext_dispatch_call`
+;; after the contract returns, so the kitties calls run with the contract account as sender.
+;; The deployer can dispatch any call. Others can only dispatch calls starting with the 2 bytes given
+;; when deploying, the module and call indices of `Kitties::breed`.
+;;
+;; Memory layout:
+;;   0..32     storage key of the owner
+;;   32..64    storage key of the public call prefix
+;;   64..96    owner
+;;   96..98    public call prefix
+;;   128..160  caller
+;;   256..     input
+(module
+	(import "env" "ext_caller" (func $ext_caller))
+	(import "env" "ext_scratch_size" (func $ext_scratch_size (result i32)))
+	(import "env" "ext_scratch_read" (func $ext_scratch_read (param i32 i32 i32)))
+	(import "env" "ext_set_storage" (func $ext_set_storage (param i32 i32 i32 i32)))
+	(import "env" "ext_get_storage" (func $ext_get_storage (param i32) (result i32)))
+	(import "env" "ext_dispatch_call" (func $ext_dispatch_call (param i32 i32)))
+	(import "env" "memory" (memory 1 1))
+
+	(data (i32.const 32) "\01")
+
+	;; Whether the `len` bytes at `a` and `b` are equal
+	(func $eq (param $a i32) (param $b i32) (param $len i32) (result i32)
+		(block $done
+			(loop $next
+				(br_if $done (i32.eqz (get_local $len)))
+				(if (i32.ne (i32.load8_u (get_local $a)) (i32.load8_u (get_local $b)))
+					(then (return (i32.const 0)))
+				)
+				(set_local $a (i32.add (get_local $a) (i32.const 1)))
+				(set_local $b (i32.add (get_local $b) (i32.const 1)))
+				(set_local $len (i32.sub (get_local $len) (i32.const 1)))
+				(br $next)
+			)
+		)
+		(i32.const 1)
+	)
+
+	;; Input: the public call prefix
+	(func (export "deploy")
+		(if (i32.ne (call $ext_scratch_size) (i32.const 2))
+			(then unreachable)
+		)
+		(call $ext_scratch_read (i32.const 96) (i32.const 0) (i32.const 2))
+		(call $ext_set_storage (i32.const 32) (i32.const 1) (i32.const 96) (i32.const 2))
+
+		(call $ext_caller)
+		(call $ext_scratch_read (i32.const 64) (i32.const 0) (i32.const 32))
+		(call $ext_set_storage (i32.const 0) (i32.const 1) (i32.const 64) (i32.const 32))
+	)
+
+	;; Input: an encoded runtime call
+	(func (export "call")
+		(local $len i32)
+		(set_local $len (call $ext_scratch_size))
+		(if (i32.gt_u (get_local $len) (i32.const 65280))
+			(then unreachable)
+		)
+		(call $ext_scratch_read (i32.const 256) (i32.const 0) (get_local $len))
+
+		(call $ext_caller)
+		(call $ext_scratch_read (i32.const 128) (i32.const 0) (i32.const 32))
+
+		(if (call $ext_get_storage (i32.const 0))
+			(then unreachable)
+		)
+		(call $ext_scratch_read (i32.const 64) (i32.const 0) (i32.const 32))
+
+		(if (i32.eqz (call $eq (i32.const 64) (i32.const 128) (i32.const 32)))
+			(then
+				(if (i32.lt_u (get_local $len) (i32.const 2))
+					(then unreachable)
+				)
+				(if (call $ext_get_storage (i32.const 32))
+					(then unreachable)
+				)
+				(call $ext_scratch_read (i32.const 96) (i32.const 0) (i32.const 2))
+				(if (i32.eqz (call $eq (i32.const 96) (i32.const 256) (i32.const 2)))
+					(then unreachable)
+				)
+			)
+		)
+
+		(call $ext_dispatch_call (i32.const 256) (get_local $len))
+	)
+)
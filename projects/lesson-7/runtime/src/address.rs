@@ -0,0 +1,95 @@
+//! Addresses of extrinsics and calls, with account indices as an explicit variant.
+//!
+//! `MultiAddress` is encoded as a plain enum, the variant first, with the variant indices of the
+//! `MultiAddress` of later Substrate versions, so client libraries encode addresses the same way
+//! for this chain. Only the variants this chain resolves are defined. Indices are resolved through
+//! the `Indices` module.
+
+use rstd::marker::PhantomData;
+use codec::{Encode, Decode, HasCompact};
+use sr_primitives::traits::StaticLookup;
+
+/// An account address.
+#[cfg_attr(feature = "std", derive(Debug, Hash))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+pub enum MultiAddress<AccountId, AccountIndex: HasCompact> {
+	/// An account id
+	Id(AccountId),
+	/// An index assigned by the `Indices` module
+	Index(#[codec(compact)] AccountIndex),
+	/// A 32 byte account id. Index 2, `Raw` in later Substrate versions, is not defined.
+	#[codec(index = "3")]
+	Address32([u8; 32]),
+}
+
+impl<AccountId, AccountIndex: HasCompact> From<AccountId> for MultiAddress<AccountId, AccountIndex> {
+	fn from(a: AccountId) -> Self {
+		MultiAddress::Id(a)
+	}
+}
+
+/// Looks up the account of a `MultiAddress`, reading account indices from the `Indices` module.
+pub struct MultiAddressLookup<T>(PhantomData<T>);
+
+impl<T: indices::Trait> StaticLookup for MultiAddressLookup<T> {
+	type Source = MultiAddress<T::AccountId, T::AccountIndex>;
+	type Target = T::AccountId;
+
+	fn lookup(address: Self::Source) -> Result<Self::Target, &'static str> {
+		match address {
+			MultiAddress::Id(account) => Ok(account),
+			MultiAddress::Index(index) => <indices::Module<T>>::lookup_index(index).ok_or("Unknown account index"),
+			MultiAddress::Address32(bytes) => T::AccountId::decode(&mut &bytes[..]).map_err(|_| "Invalid account id"),
+		}
+	}
+
+	fn unlookup(account: Self::Target) -> Self::Source {
+		MultiAddress::Id(account)
+	}
+}
+
+/// Tests for MultiAddress
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use crate::mock::Test;
+
+	impl indices::Trait for Test {
+		type AccountIndex = u32;
+		type ResolveHint = indices::SimpleResolveHint<u64, u32>;
+		type IsDeadAccount = balances::Module<Test>;
+		type Event = ();
+	}
+	type Lookup = MultiAddressLookup<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = crate::mock::storage(vec![]);
+		indices::GenesisConfig::<Test> {
+			ids: vec![10, 20],
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	#[test]
+	fn indices_are_looked_up_explicitly() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(Lookup::lookup(MultiAddress::Id(30)), Ok(30));
+			assert_eq!(Lookup::lookup(MultiAddress::Index(1)), Ok(20));
+			assert_eq!(Lookup::lookup(MultiAddress::Index(2)), Err("Unknown account index"));
+			assert_eq!(Lookup::unlookup(10), MultiAddress::Id(10));
+		});
+	}
+
+	#[test]
+	fn encoding_starts_with_the_variant() {
+		assert_eq!(MultiAddress::<u64, u32>::Id(1).encode(), vec![0, 1, 0, 0, 0, 0, 0, 0, 0]);
+		assert_eq!(MultiAddress::<u64, u32>::Index(1).encode(), vec![1, 4]);
+		assert_eq!(MultiAddress::<u64, u32>::Address32([0; 32]).encode()[0], 3);
+		// `Raw` and `Address20` of later Substrate versions are not addresses of this chain
+		assert!(MultiAddress::<u64, u32>::decode(&mut &[2, 0][..]).is_err());
+		assert!(MultiAddress::<u64, u32>::decode(&mut &[4; 21][..]).is_err());
+	}
+}
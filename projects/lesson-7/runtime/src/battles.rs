@@ -0,0 +1,364 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageMap,
+	traits::{Currency, ReservableCurrency}
+};
+use sr_primitives::traits::Zero;
+use codec::{Encode, Decode};
+use runtime_io::blake2_128;
+use system::ensure_signed;
+use crate::kitties;
+
+/// Number of epochs between accepting a battle and fighting it.
+/// The randomness of an epoch comes from the VRF outputs of two epochs before, so the randomness a
+/// battle is fought with is unknown when it is accepted.
+pub const BATTLE_DELAY: u64 = 2;
+
+pub trait Trait: kitties::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// The randomness battles are fought with
+	type Randomness: EpochRandomness;
+}
+
+/// Randomness which changes once per epoch.
+pub trait EpochRandomness {
+	/// The index of the current epoch
+	fn epoch_index() -> u64;
+	/// The randomness of the current epoch
+	fn randomness() -> [u8; 32];
+}
+
+impl<T: babe::Trait> EpochRandomness for babe::Module<T> {
+	fn epoch_index() -> u64 {
+		Self::epoch_index()
+	}
+
+	fn randomness() -> [u8; 32] {
+		Self::randomness()
+	}
+}
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+/// Battle stats derived from the kitty DNA
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+pub struct KittyStats {
+	pub attack: u32,
+	pub defense: u32,
+	pub speed: u32,
+}
+
+impl KittyStats {
+	/// Attack is from the first 5 bytes, defense from the next 5 and speed from the last 6
+	pub fn from_dna(dna: &[u8; 16]) -> Self {
+		let sum = |bytes: &[u8]| bytes.iter().map(|b| *b as u32).sum::<u32>();
+		KittyStats {
+			attack: sum(&dna[0..5]),
+			defense: sum(&dna[5..10]),
+			speed: sum(&dna[10..16]),
+		}
+	}
+
+	pub fn power(&self) -> u32 {
+		self.attack + self.defense + self.speed
+	}
+}
+
+/// A pending challenge
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode)]
+pub struct Challenge<AccountId, Balance> {
+	/// The account which controls the challenging kitty
+	pub challenger: AccountId,
+	/// The amount both sides put at stake. Zero means a friendly battle.
+	pub stake: Balance,
+}
+
+/// An accepted battle waiting for the randomness it is fought with
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode)]
+pub struct Battle<AccountId, KittyIndex, Balance> {
+	pub challenger: AccountId,
+	pub kitty_id: KittyIndex,
+	pub opponent: AccountId,
+	pub opponent_id: KittyIndex,
+	/// The amount both sides reserved
+	pub stake: Balance,
+}
+
+/// Win / loss record of a kitty
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode, Default)]
+pub struct BattleRecord {
+	pub wins: u32,
+	pub losses: u32,
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Battles {
+		/// Pending challenges, key is (challenger kitty id, opponent kitty id)
+		pub Challenges get(pending_challenge): map (T::KittyIndex, T::KittyIndex) => Option<Challenge<T::AccountId, BalanceOf<T>>>;
+		/// Accepted battles, fought at the start of the given epoch
+		pub Battles get(battles): map u64 => Vec<Battle<T::AccountId, T::KittyIndex, BalanceOf<T>>>;
+		/// Win / loss record of each kitty
+		pub KittyRecords get(kitty_record): map T::KittyIndex => BattleRecord;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+		<T as kitties::Trait>::KittyIndex,
+		Balance = BalanceOf<T>,
+	{
+		/// A kitty challenges another kitty. (challenger, kitty_id, opponent_kitty_id, stake)
+		Challenged(AccountId, KittyIndex, KittyIndex, Balance),
+		/// A challenge is cancelled. (challenger, kitty_id, opponent_kitty_id)
+		ChallengeCancelled(AccountId, KittyIndex, KittyIndex),
+		/// A challenge is accepted and fought at the start of an epoch. (opponent, kitty_id, opponent_kitty_id, epoch)
+		ChallengeAccepted(AccountId, KittyIndex, KittyIndex, u64),
+		/// A battle is resolved. (winner, winner_kitty_id, loser_kitty_id, prize)
+		BattleWon(AccountId, KittyIndex, KittyIndex, Balance),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		fn on_initialize(_now: T::BlockNumber) {
+			// Each epoch is seen by a block, as the epoch changes at most once per block
+			for battle in <Battles<T>>::take(T::Randomness::epoch_index()) {
+				Self::fight(battle);
+			}
+		}
+
+		/// Challenge another kitty to a battle
+		/// Both sides stake `stake` and the winner takes all
+		pub fn challenge(origin, kitty_id: T::KittyIndex, opponent_id: T::KittyIndex, stake: BalanceOf<T>) {
+			let sender = ensure_signed(origin)?;
+
+			ensure!(kitty_id != opponent_id, "Cannot challenge itself");
+			ensure!(Self::is_controller(&sender, kitty_id), "Only owner can challenge with kitty");
+
+			let opponent = <kitties::Module<T>>::kitty_controller(opponent_id);
+			ensure!(opponent.is_some(), "Opponent kitty does not exist");
+			ensure!(opponent.unwrap() != sender, "Cannot challenge own kitty");
+
+			ensure!(!<Challenges<T>>::exists(&(kitty_id, opponent_id)), "Challenge already exists");
+
+			<T as Trait>::Currency::reserve(&sender, stake)?;
+
+			<Challenges<T>>::insert(&(kitty_id, opponent_id), Challenge {
+				challenger: sender.clone(),
+				stake,
+			});
+
+			Self::deposit_event(RawEvent::Challenged(sender, kitty_id, opponent_id, stake));
+		}
+
+		/// Cancel a challenge which is not yet accepted and release the stake
+		pub fn cancel_challenge(origin, kitty_id: T::KittyIndex, opponent_id: T::KittyIndex) {
+			let sender = ensure_signed(origin)?;
+
+			let challenge = Self::pending_challenge(&(kitty_id, opponent_id));
+			ensure!(challenge.is_some(), "Challenge does not exist");
+			let challenge = challenge.unwrap();
+			ensure!(challenge.challenger == sender, "Only challenger can cancel challenge");
+
+			<T as Trait>::Currency::unreserve(&sender, challenge.stake);
+			<Challenges<T>>::remove(&(kitty_id, opponent_id));
+
+			Self::deposit_event(RawEvent::ChallengeCancelled(sender, kitty_id, opponent_id));
+		}
+
+		/// Accept a challenge. The battle is fought `BATTLE_DELAY` epochs later.
+		pub fn accept_challenge(origin, kitty_id: T::KittyIndex, opponent_id: T::KittyIndex) {
+			let sender = ensure_signed(origin)?;
+
+			let challenge = Self::pending_challenge(&(kitty_id, opponent_id));
+			ensure!(challenge.is_some(), "Challenge does not exist");
+			let challenge = challenge.unwrap();
+
+			ensure!(Self::is_controller(&sender, opponent_id), "Only owner can accept challenge");
+			ensure!(Self::is_controller(&challenge.challenger, kitty_id), "Challenger no longer owns kitty");
+
+			<T as Trait>::Currency::reserve(&sender, challenge.stake)?;
+			<Challenges<T>>::remove(&(kitty_id, opponent_id));
+
+			let epoch = T::Randomness::epoch_index().saturating_add(BATTLE_DELAY);
+			<Battles<T>>::mutate(epoch, |battles| battles.push(Battle {
+				challenger: challenge.challenger,
+				kitty_id,
+				opponent: sender.clone(),
+				opponent_id,
+				stake: challenge.stake,
+			}));
+
+			Self::deposit_event(RawEvent::ChallengeAccepted(sender, kitty_id, opponent_id, epoch));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	fn is_controller(who: &T::AccountId, kitty_id: T::KittyIndex) -> bool {
+		<kitties::Module<T>>::kitty_controller(kitty_id).map(|controller| controller == *who).unwrap_or(false)
+	}
+
+	/// Battle stats of a kitty. None if the kitty does not exist.
+	pub fn kitty_stats(kitty_id: T::KittyIndex) -> Option<KittyStats> {
+		<kitties::Module<T>>::kitty(kitty_id).map(|kitty| KittyStats::from_dna(&kitty.0))
+	}
+
+	/// Fight an accepted battle and pay the stake to the winner
+	fn fight(battle: Battle<T::AccountId, T::KittyIndex, BalanceOf<T>>) {
+		let challenger_wins = Self::first_wins(battle.kitty_id, battle.opponent_id);
+
+		let (winner, winner_kitty, loser, loser_kitty) = if challenger_wins {
+			(battle.challenger, battle.kitty_id, battle.opponent, battle.opponent_id)
+		} else {
+			(battle.opponent, battle.opponent_id, battle.challenger, battle.kitty_id)
+		};
+
+		<KittyRecords<T>>::mutate(winner_kitty, |record| record.wins = record.wins.saturating_add(1));
+		<KittyRecords<T>>::mutate(loser_kitty, |record| record.losses = record.losses.saturating_add(1));
+
+		let mut prize = Zero::zero();
+		if !battle.stake.is_zero() {
+			// Both stakes are reserved since the challenge is accepted
+			let _ = <T as Trait>::Currency::repatriate_reserved(&loser, &winner, battle.stake);
+			<T as Trait>::Currency::unreserve(&winner, battle.stake);
+			prize = battle.stake;
+		}
+
+		Self::deposit_event(RawEvent::BattleWon(winner, winner_kitty, loser_kitty, prize));
+	}
+
+	/// Returns true if the first kitty wins.
+	/// Each kitty wins with a chance proportional to its power.
+	fn first_wins(kitty_id_1: T::KittyIndex, kitty_id_2: T::KittyIndex) -> bool {
+		let power1 = Self::kitty_stats(kitty_id_1).map(|s| s.power()).unwrap_or(0) + 1;
+		let power2 = Self::kitty_stats(kitty_id_2).map(|s| s.power()).unwrap_or(0) + 1;
+
+		let payload = (T::Randomness::randomness(), kitty_id_1, kitty_id_2);
+		let random = payload.using_encoded(blake2_128);
+		let roll = u32::decode(&mut &random[..]).unwrap_or(0) % (power1 + power2);
+
+		roll < power1
+	}
+}
+
+/// Tests for Battles module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::cell::Cell;
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop};
+	use sr_primitives::traits::OnInitialize;
+	use crate::mock::{Test, Origin, KittyModule, Balances};
+
+	thread_local! {
+		static EPOCH_INDEX: Cell<u64> = Cell::new(0);
+	}
+
+	/// The randomness of epoch `n` is `[n; 32]`, so battles are fought with known randomness.
+	pub struct TestRandomness;
+	impl EpochRandomness for TestRandomness {
+		fn epoch_index() -> u64 {
+			EPOCH_INDEX.with(|epoch| epoch.get())
+		}
+
+		fn randomness() -> [u8; 32] {
+			[Self::epoch_index() as u8; 32]
+		}
+	}
+
+	impl Trait for Test {
+		type Currency = balances::Module<Test>;
+		type Event = ();
+		type Randomness = TestRandomness;
+	}
+	type BattleModule = Module<Test>;
+
+	fn start_epoch(epoch: u64) {
+		EPOCH_INDEX.with(|epoch_index| epoch_index.set(epoch));
+		BattleModule::on_initialize(epoch);
+	}
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		crate::mock::new_test_ext(vec![(1, 100), (2, 100)])
+	}
+
+	/// Kitty 0 of account 1 with power 16 and kitty 1 of account 2 with power 32.
+	fn new_test_ext_with_kitties() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = crate::mock::storage(vec![(1, 100), (2, 100)]);
+		kitties::GenesisConfig::<Test> {
+			kitties: vec![(1, [1; 16], None), (2, [2; 16], None)],
+			..Default::default()
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	#[test]
+	fn stats_are_derived_from_dna() {
+		let mut dna = [0u8; 16];
+		dna[0] = 10;
+		dna[4] = 5;
+		dna[7] = 3;
+		dna[15] = 255;
+
+		assert_eq!(KittyStats::from_dna(&dna), KittyStats {
+			attack: 15,
+			defense: 3,
+			speed: 255,
+		});
+		assert_eq!(KittyStats::from_dna(&dna).power(), 273);
+	}
+
+	#[test]
+	fn battle_transfers_stake_to_winner() {
+		with_externalities(&mut new_test_ext_with_kitties(), || {
+			assert_noop!(BattleModule::challenge(Origin::signed(1), 1, 0, 10), "Only owner can challenge with kitty");
+			assert_ok!(BattleModule::challenge(Origin::signed(1), 0, 1, 10));
+			assert_eq!(Balances::reserved_balance(&1), 10);
+
+			assert_noop!(BattleModule::accept_challenge(Origin::signed(1), 0, 1), "Only owner can accept challenge");
+			assert_ok!(BattleModule::accept_challenge(Origin::signed(2), 0, 1));
+
+			assert_eq!(BattleModule::pending_challenge(&(0, 1)), None);
+			assert_eq!(Balances::reserved_balance(&2), 10);
+
+			// fought with randomness from after the battle is accepted
+			start_epoch(1);
+			assert_eq!(BattleModule::kitty_record(0), BattleRecord::default());
+			start_epoch(2);
+			assert_eq!(BattleModule::battles(2), vec![]);
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(Balances::reserved_balance(&2), 0);
+
+			// the roll of the randomness of epoch 2 is 13 of 17 + 33, below the 17 of kitty 0
+			assert_eq!(BattleModule::kitty_record(0), BattleRecord { wins: 1, losses: 0 });
+			assert_eq!(BattleModule::kitty_record(1), BattleRecord { wins: 0, losses: 1 });
+			assert_eq!(Balances::free_balance(&1), 110);
+			assert_eq!(Balances::free_balance(&2), 90);
+		});
+	}
+
+	#[test]
+	fn cancel_challenge_releases_stake() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(KittyModule::create(Origin::signed(2)));
+
+			assert_ok!(BattleModule::challenge(Origin::signed(1), 0, 1, 10));
+			assert_noop!(BattleModule::cancel_challenge(Origin::signed(2), 0, 1), "Only challenger can cancel challenge");
+			assert_ok!(BattleModule::cancel_challenge(Origin::signed(1), 0, 1));
+
+			assert_eq!(Balances::free_balance(&1), 100);
+			assert_eq!(BattleModule::pending_challenge(&(0, 1)), None);
+		});
+	}
+}
@@ -0,0 +1,76 @@
+use support::{decl_module, decl_storage, StorageValue};
+use crate::{SLOT_DURATION, EPOCH_DURATION_IN_BLOCKS, PRIMARY_PROBABILITY};
+
+/// Consensus parameters of the chain, set in the genesis so the same runtime can run chains with different
+/// block times. They are not meant to change afterwards: BABE assumes a fixed slot duration and epoch length.
+pub trait Trait: system::Trait {}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as ChainParams {
+		/// Duration of a BABE slot in milliseconds. The timestamp minimum period is half of it.
+		pub SlotDuration get(slot_duration) config(): u64 = SLOT_DURATION;
+		/// Number of slots in a BABE epoch
+		pub EpochDuration get(epoch_duration) config(): u64 = EPOCH_DURATION_IN_BLOCKS as u64;
+		/// BABE `c`, the probability of a slot having a primary block. `1 - c` is the probability of an empty slot.
+		pub PrimaryProbability get(primary_probability) config(): (u64, u64) = PRIMARY_PROBABILITY;
+	}
+	add_extra_genesis {
+		build(|config: &GenesisConfig| {
+			assert!(config.slot_duration >= 2, "Slot duration must be at least 2ms");
+			assert!(config.epoch_duration > 0, "Epoch duration must be positive");
+			let (numerator, denominator) = config.primary_probability;
+			assert!(denominator > 0 && numerator <= denominator, "Primary probability must be within [0, 1]");
+		});
+	}
+}
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {}
+}
+
+/// Tests for ChainParams module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use crate::mock::Test;
+
+	impl Trait for Test {}
+	type ChainParams = Module<Test>;
+
+	fn new_test_ext(config: GenesisConfig) -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = crate::mock::storage(vec![]);
+		config.assimilate_storage::<Test>(&mut t).unwrap();
+		t.into()
+	}
+
+	#[test]
+	fn genesis_sets_consensus_params() {
+		with_externalities(&mut new_test_ext(GenesisConfig::default()), || {
+			assert_eq!(ChainParams::slot_duration(), SLOT_DURATION);
+			assert_eq!(ChainParams::epoch_duration(), EPOCH_DURATION_IN_BLOCKS as u64);
+			assert_eq!(ChainParams::primary_probability(), PRIMARY_PROBABILITY);
+		});
+
+		with_externalities(&mut new_test_ext(GenesisConfig {
+			slot_duration: 1000,
+			epoch_duration: 20,
+			primary_probability: (1, 2),
+		}), || {
+			assert_eq!(ChainParams::slot_duration(), 1000);
+			assert_eq!(ChainParams::epoch_duration(), 20);
+			assert_eq!(ChainParams::primary_probability(), (1, 2));
+		});
+	}
+
+	#[test]
+	#[should_panic(expected = "Primary probability must be within [0, 1]")]
+	fn genesis_rejects_invalid_primary_probability() {
+		new_test_ext(GenesisConfig {
+			primary_probability: (2, 1),
+			..Default::default()
+		});
+	}
+}
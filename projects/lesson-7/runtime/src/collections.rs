@@ -0,0 +1,227 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageValue, StorageMap,
+	Parameter, traits::Currency
+};
+use sr_primitives::traits::{SimpleArithmetic, Bounded, Member};
+use codec::{Encode, Decode};
+use system::ensure_signed;
+use rstd::prelude::*;
+use rstd::result;
+use crate::kitties;
+
+/// Maximum length of a collection name in bytes
+pub const MAX_NAME_LENGTH: usize = 64;
+
+pub trait Trait: kitties::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type CollectionIndex: Parameter + Member + SimpleArithmetic + Bounded + Default + Copy;
+	type Currency: Currency<Self::AccountId>;
+}
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+/// A limited series of kitties released by an admin
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode)]
+pub struct Collection<AccountId, Balance> {
+	/// The account which manages the collection and receives the mint price
+	pub admin: AccountId,
+	pub name: Vec<u8>,
+	pub max_supply: u32,
+	/// Number of kitties minted so far
+	pub minted: u32,
+	pub mint_price: Balance,
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as KittyCollections {
+		/// Stores all the collections, key is the collection id / index
+		pub Collections get(collection): map T::CollectionIndex => Option<Collection<T::AccountId, BalanceOf<T>>>;
+		/// Stores the total number of collections. i.e. the next collection index
+		pub CollectionsCount get(collections_count): T::CollectionIndex;
+		/// Get the collection of a kitty. None means not minted in a collection.
+		pub KittyCollection get(kitty_collection): map T::KittyIndex => Option<T::CollectionIndex>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+		<T as kitties::Trait>::KittyIndex,
+		<T as Trait>::CollectionIndex,
+		Balance = BalanceOf<T>,
+	{
+		/// A collection is created. (admin, collection_id)
+		CollectionCreated(AccountId, CollectionIndex),
+		/// A kitty is minted in a collection. (owner, collection_id, kitty_id, price)
+		Minted(AccountId, CollectionIndex, KittyIndex, Balance),
+		/// The mint price of a collection is changed. (collection_id, price)
+		MintPriceChanged(CollectionIndex, Balance),
+		/// The admin of a collection is changed. (collection_id, new_admin)
+		AdminChanged(CollectionIndex, AccountId),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Create a new collection with the sender as admin
+		pub fn create_collection(origin, name: Vec<u8>, max_supply: u32, mint_price: BalanceOf<T>) {
+			let sender = ensure_signed(origin)?;
+
+			ensure!(name.len() <= MAX_NAME_LENGTH, "Collection name too long");
+			ensure!(max_supply > 0, "Max supply must be positive");
+
+			let collection_id = Self::collections_count();
+			ensure!(collection_id != T::CollectionIndex::max_value(), "Collections count overflow");
+
+			<Collections<T>>::insert(collection_id, Collection {
+				admin: sender.clone(),
+				name,
+				max_supply,
+				minted: 0,
+				mint_price,
+			});
+			<CollectionsCount<T>>::put(collection_id + 1.into());
+
+			Self::deposit_event(RawEvent::CollectionCreated(sender, collection_id));
+		}
+
+		/// Mint a new kitty in a collection and pay the mint price to the admin
+		/// Fails if the mint price is above `max_price`, e.g. raised by the admin after the call was signed
+		pub fn mint_in_collection(origin, collection_id: T::CollectionIndex, max_price: BalanceOf<T>) {
+			let sender = ensure_signed(origin)?;
+
+			let collection = Self::collection(collection_id);
+			ensure!(collection.is_some(), "Collection does not exist");
+			let mut collection = collection.unwrap();
+			ensure!(collection.minted < collection.max_supply, "Collection is sold out");
+			ensure!(max_price >= collection.mint_price, "Price is too low");
+			<kitties::Module<T>>::ensure_can_mint(&sender)?;
+
+			if sender != collection.admin {
+				<T as Trait>::Currency::transfer(&sender, &collection.admin, collection.mint_price)?;
+			}
+
+			let kitty_id = <kitties::Module<T>>::mint(&sender)?;
+
+			<KittyCollection<T>>::insert(kitty_id, collection_id);
+			collection.minted += 1;
+			let price = collection.mint_price;
+			<Collections<T>>::insert(collection_id, collection);
+
+			Self::deposit_event(RawEvent::Minted(sender, collection_id, kitty_id, price));
+		}
+
+		/// Change the mint price of a collection
+		pub fn set_mint_price(origin, collection_id: T::CollectionIndex, mint_price: BalanceOf<T>) {
+			let sender = ensure_signed(origin)?;
+
+			Self::mutate_as_admin(&sender, collection_id, |collection| collection.mint_price = mint_price)?;
+
+			Self::deposit_event(RawEvent::MintPriceChanged(collection_id, mint_price));
+		}
+
+		/// Hand over the collection to a new admin
+		pub fn set_admin(origin, collection_id: T::CollectionIndex, new_admin: T::AccountId) {
+			let sender = ensure_signed(origin)?;
+
+			Self::mutate_as_admin(&sender, collection_id, |collection| collection.admin = new_admin.clone())?;
+
+			Self::deposit_event(RawEvent::AdminChanged(collection_id, new_admin));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	fn mutate_as_admin<F>(who: &T::AccountId, collection_id: T::CollectionIndex, f: F) -> result::Result<(), &'static str> where
+		F: FnOnce(&mut Collection<T::AccountId, BalanceOf<T>>),
+	{
+		let collection = Self::collection(collection_id);
+		ensure!(collection.is_some(), "Collection does not exist");
+		let mut collection = collection.unwrap();
+		ensure!(collection.admin == *who, "Only admin can manage collection");
+
+		f(&mut collection);
+		<Collections<T>>::insert(collection_id, collection);
+
+		Ok(())
+	}
+}
+
+/// Tests for KittyCollections module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop};
+	use crate::mock::{Test, Origin, KittyModule, Balances};
+
+	impl Trait for Test {
+		type CollectionIndex = u32;
+		type Currency = balances::Module<Test>;
+		type Event = ();
+	}
+	type CollectionModule = Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		crate::mock::new_test_ext(vec![(1, 100), (2, 100)])
+	}
+
+	#[test]
+	fn mint_in_collection_charges_price_and_enforces_supply() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(CollectionModule::create_collection(Origin::signed(1), b"Genesis".to_vec(), 2, 10));
+			assert_eq!(CollectionModule::collections_count(), 1);
+
+			assert_noop!(CollectionModule::mint_in_collection(Origin::signed(2), 0, 9), "Price is too low");
+			assert_ok!(CollectionModule::mint_in_collection(Origin::signed(2), 0, 10));
+			assert_ok!(CollectionModule::mint_in_collection(Origin::signed(2), 0, 20));
+			assert_noop!(CollectionModule::mint_in_collection(Origin::signed(2), 0, 10), "Collection is sold out");
+
+			assert_eq!(Balances::free_balance(&1), 120);
+			assert_eq!(Balances::free_balance(&2), 80);
+			assert_eq!(KittyModule::kitty_owner(1), Some(2));
+			assert_eq!(CollectionModule::kitty_collection(1), Some(0));
+			assert_eq!(CollectionModule::collection(0).unwrap().minted, 2);
+		});
+	}
+
+	#[test]
+	fn mint_in_collection_is_not_charged_when_minting_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(CollectionModule::create_collection(Origin::signed(1), b"Genesis".to_vec(), 2, 10));
+			assert_ok!(KittyModule::create(Origin::signed(2)));
+			assert_ok!(KittyModule::set_max_owned(Origin::ROOT, 1));
+
+			assert_noop!(CollectionModule::mint_in_collection(Origin::signed(2), 0, 10), "Account owns too many kitties");
+			assert_eq!(Balances::free_balance(&2), 100);
+		});
+	}
+
+	#[test]
+	fn only_admin_can_manage_collection() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(
+				CollectionModule::create_collection(Origin::signed(1), vec![0; MAX_NAME_LENGTH + 1], 2, 10),
+				"Collection name too long"
+			);
+			assert_ok!(CollectionModule::create_collection(Origin::signed(1), b"Genesis".to_vec(), 2, 10));
+
+			assert_noop!(CollectionModule::set_mint_price(Origin::signed(2), 0, 0), "Only admin can manage collection");
+			assert_ok!(CollectionModule::set_admin(Origin::signed(1), 0, 2));
+			assert_ok!(CollectionModule::set_mint_price(Origin::signed(2), 0, 0));
+
+			assert_eq!(CollectionModule::collection(0), Some(Collection {
+				admin: 2,
+				name: b"Genesis".to_vec(),
+				max_supply: 2,
+				minted: 0,
+				mint_price: 0,
+			}));
+		});
+	}
+}
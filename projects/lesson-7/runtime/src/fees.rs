@@ -0,0 +1,164 @@
+//! Transaction fees: the weight to fee polynomial, the congestion multiplier and the fee estimate API.
+//!
+//! `balances::TakeFees` charges `base + byte * len + weight_to_fee(multiplier * weight) + tip`,
+//! and the fee including the tip is the priority of the transaction in the pool.
+
+use codec::{Encode, Decode};
+use client::decl_runtime_apis;
+use sr_primitives::{Fixed64, Perbill};
+use sr_primitives::traits::{Block as BlockT, Convert, Saturating};
+use sr_primitives::weights::{Weight, WeightMultiplier, DispatchInfo, GetDispatchInfo};
+use support::{parameter_types, traits::Get};
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use crate::{
+	Balance, System, UncheckedExtrinsic, MaximumBlockWeight, AvailableBlockRatio, TransactionBaseFee, TransactionByteFee,
+};
+
+/// Coefficients of the weight to fee polynomial, lowest degree first.
+pub const WEIGHT_FEE_COEFFICIENTS: [Balance; 3] = [0, 100_000, 1];
+
+parameter_types! {
+	/// Share of the weight available to normal transactions the fees are adjusted towards.
+	pub const TargetBlockFullness: Perbill = Perbill::from_percent(25);
+}
+
+/// Converts a weight to a fee with the `WEIGHT_FEE_COEFFICIENTS` polynomial.
+pub struct WeightToFee;
+
+impl Convert<Weight, Balance> for WeightToFee {
+	fn convert(weight: Weight) -> Balance {
+		let weight = Balance::from(weight);
+		WEIGHT_FEE_COEFFICIENTS.iter().rev()
+			.fold(0, |fee: Balance, coefficient| fee.saturating_mul(weight).saturating_add(*coefficient))
+	}
+}
+
+/// Updates the weight multiplier after each block, raising the fees while blocks are fuller than
+/// `TargetBlockFullness` of the normal transaction weight and lowering them otherwise.
+///
+/// The multiplier changes by `v * diff + v^2 * diff^2 / 2` with `v = 0.00004` and `diff` the
+/// distance to the target as a share of the normal transaction weight. It does not go below -1,
+/// where the weight fee is zero.
+pub struct FeeMultiplierUpdate;
+
+impl Convert<(Weight, WeightMultiplier), WeightMultiplier> for FeeMultiplierUpdate {
+	fn convert((block_weight, multiplier): (Weight, WeightMultiplier)) -> WeightMultiplier {
+		let normal_weight = (AvailableBlockRatio::get() * MaximumBlockWeight::get()).max(1);
+		let target_weight = TargetBlockFullness::get() * normal_weight;
+
+		let positive = block_weight >= target_weight;
+		let diff_abs = block_weight.max(target_weight) - block_weight.min(target_weight);
+		let diff = Fixed64::from_rational(diff_abs as i64, normal_weight as u64);
+		let diff_squared = diff.saturating_mul(diff);
+
+		let v = Fixed64::from_rational(4, 100_000);
+		// v^2 / 2 rounded to a billionth
+		let v_squared_2 = Fixed64::from_rational(1, 1_000_000_000);
+
+		let first_term = v.saturating_mul(diff);
+		let second_term = v_squared_2.saturating_mul(diff_squared);
+
+		if positive {
+			multiplier.saturating_add(first_term.saturating_add(second_term))
+		} else {
+			// diff is at most 1, so the first term is larger
+			multiplier.saturating_sub(first_term - second_term)
+				.max(Fixed64::from_rational(-1, 1))
+		}
+	}
+}
+
+/// The fee charged for an extrinsic of `len` bytes before the tip, as `balances::TakeFees` computes it.
+///
+/// `TakeFees` doesn't expose its computation, so it is repeated here. `tests/fees.rs` checks that
+/// both agree on the fee of an applied extrinsic.
+pub fn compute_fee(len: u32, info: DispatchInfo) -> Balance {
+	let len_fee = if info.pay_length_fee() {
+		TransactionBaseFee::get().saturating_add(TransactionByteFee::get().saturating_mul(Balance::from(len)))
+	} else {
+		0
+	};
+
+	let capped_weight = info.weight.min(MaximumBlockWeight::get());
+	let adjusted_weight = System::next_weight_multiplier().apply_to(capped_weight);
+
+	len_fee.saturating_add(WeightToFee::convert(adjusted_weight))
+}
+
+/// The weight and fee of an extrinsic.
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
+#[cfg_attr(feature = "std", serde(rename_all = "camelCase"))]
+pub struct FeeInfo {
+	/// Weight of the call
+	pub weight: Weight,
+	/// Fee without the tip
+	pub partial_fee: Balance,
+}
+
+/// `FeesApi::query_info`
+pub fn query_info(uxt: &UncheckedExtrinsic, len: u32) -> FeeInfo {
+	let info = uxt.get_dispatch_info();
+	FeeInfo {
+		weight: info.weight,
+		partial_fee: compute_fee(len, info),
+	}
+}
+
+decl_runtime_apis! {
+	/// Fee estimates of extrinsics.
+	pub trait FeesApi {
+		/// The weight and fee of `uxt`, whose encoding is `len` bytes long.
+		fn query_info(uxt: <Block as BlockT>::Extrinsic, len: u32) -> FeeInfo;
+	}
+}
+
+/// Tests for fees
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::{with_externalities, TestExternalities};
+	use primitives::Blake2Hasher;
+	use sr_primitives::weights::DispatchClass;
+
+	fn normal_weight() -> Weight {
+		AvailableBlockRatio::get() * MaximumBlockWeight::get()
+	}
+
+	#[test]
+	fn weight_fee_follows_the_polynomial() {
+		assert_eq!(WeightToFee::convert(0), 0);
+		assert_eq!(WeightToFee::convert(10), 1_000_100);
+		assert_eq!(WeightToFee::convert(10_000), 1_100_000_000);
+	}
+
+	#[test]
+	fn multiplier_follows_block_fullness() {
+		let target = TargetBlockFullness::get() * normal_weight();
+		let zero = WeightMultiplier::default();
+
+		assert_eq!(FeeMultiplierUpdate::convert((target, zero)), zero);
+		assert!(FeeMultiplierUpdate::convert((normal_weight(), zero)) > zero);
+		assert!(FeeMultiplierUpdate::convert((0, zero)) < zero);
+
+		// an empty chain stops lowering the fees at -1
+		let minimum = Fixed64::from_rational(-1, 1);
+		assert_eq!(FeeMultiplierUpdate::convert((0, minimum)), minimum);
+	}
+
+	#[test]
+	fn fee_adds_length_and_weight_fees() {
+		with_externalities(&mut TestExternalities::<Blake2Hasher>::default(), || {
+			let info = DispatchInfo { weight: 10_000, class: DispatchClass::Normal };
+			assert_eq!(
+				compute_fee(100, info),
+				TransactionBaseFee::get() + 100 * TransactionByteFee::get() + WeightToFee::convert(10_000),
+			);
+
+			let operational = DispatchInfo { weight: 10_000, class: DispatchClass::Operational };
+			assert_eq!(compute_fee(100, operational), WeightToFee::convert(10_000));
+		});
+	}
+}
@@ -0,0 +1,363 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageMap,
+	traits::{Currency, ReservableCurrency}
+};
+use sr_primitives::{ModuleId, traits::{AccountIdConversion, Zero, CheckedMul}};
+use codec::{Encode, Decode};
+use system::ensure_signed;
+use rstd::result;
+use crate::kitties;
+
+/// The account which holds the fractionalized kitties
+const MODULE_ID: ModuleId = ModuleId(*b"kty/frac");
+
+pub trait Trait: kitties::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: ReservableCurrency<Self::AccountId>;
+}
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+/// An offer to buy all the shares of a kitty
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode)]
+pub struct Buyout<AccountId, Balance> {
+	pub buyer: AccountId,
+	pub price_per_share: Balance,
+	/// The part of the buyer's funds which is still reserved to pay for shares
+	pub reserved: Balance,
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as KittyFractions {
+		/// Total number of shares of a fractionalized kitty. None means not fractionalized.
+		pub TotalShares get(total_shares): map T::KittyIndex => Option<u32>;
+		/// Share balance of an account for a kitty
+		pub ShareBalances get(share_balance): map (T::KittyIndex, T::AccountId) => u32;
+		/// Active buyout offer for a kitty
+		pub Buyouts get(buyout): map T::KittyIndex => Option<Buyout<T::AccountId, BalanceOf<T>>>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+		<T as kitties::Trait>::KittyIndex,
+		Balance = BalanceOf<T>,
+	{
+		/// A kitty is fractionalized. (owner, kitty_id, shares)
+		Fractionalized(AccountId, KittyIndex, u32),
+		/// Shares are transferred. (from, to, kitty_id, shares)
+		SharesTransferred(AccountId, AccountId, KittyIndex, u32),
+		/// A kitty is redeemed by the holder of all shares. (owner, kitty_id)
+		Redeemed(AccountId, KittyIndex),
+		/// A buyout is offered. (buyer, kitty_id, price_per_share)
+		BuyoutOffered(AccountId, KittyIndex, Balance),
+		/// A buyout is cancelled. (buyer, kitty_id)
+		BuyoutCancelled(AccountId, KittyIndex),
+		/// Shares are sold to the buyer. (seller, buyer, kitty_id, shares, price)
+		SharesSold(AccountId, AccountId, KittyIndex, u32, Balance),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Lock a kitty in the module and mint `shares` shares to the owner
+		pub fn fractionalize(origin, kitty_id: T::KittyIndex, shares: u32) {
+			let sender = ensure_signed(origin)?;
+
+			ensure!(shares > 0, "Shares must be positive");
+
+			<kitties::Module<T>>::transfer_kitty_to_module(&sender, &Self::account_id(), kitty_id)?;
+
+			<TotalShares<T>>::insert(kitty_id, shares);
+			<ShareBalances<T>>::insert(&(kitty_id, sender.clone()), shares);
+
+			Self::deposit_event(RawEvent::Fractionalized(sender, kitty_id, shares));
+		}
+
+		/// Transfer shares of a kitty
+		pub fn transfer_shares(origin, kitty_id: T::KittyIndex, to: T::AccountId, shares: u32) {
+			let sender = ensure_signed(origin)?;
+
+			Self::move_shares(&sender, &to, kitty_id, shares)?;
+
+			Self::deposit_event(RawEvent::SharesTransferred(sender, to, kitty_id, shares));
+		}
+
+		/// Burn all the shares of a kitty and take the kitty out of the module
+		pub fn redeem(origin, kitty_id: T::KittyIndex) {
+			let sender = ensure_signed(origin)?;
+
+			let total = Self::total_shares(kitty_id);
+			ensure!(total.is_some(), "Kitty is not fractionalized");
+			ensure!(Self::share_balance(&(kitty_id, sender.clone())) == total.unwrap(), "Only holder of all shares can redeem kitty");
+
+			Self::do_redeem(&sender, kitty_id)?;
+		}
+
+		/// Offer to buy all the shares of a kitty at `price_per_share`
+		/// The price of the shares not owned by the buyer is reserved
+		/// A higher offer replaces the current one, whose remaining funds are released
+		pub fn offer_buyout(origin, kitty_id: T::KittyIndex, price_per_share: BalanceOf<T>) {
+			let sender = ensure_signed(origin)?;
+
+			let total = Self::total_shares(kitty_id);
+			ensure!(total.is_some(), "Kitty is not fractionalized");
+			ensure!(!price_per_share.is_zero(), "Buyout price must not be zero");
+			let current = Self::buyout(kitty_id);
+			if let Some(ref current) = current {
+				ensure!(price_per_share > current.price_per_share, "Buyout offer is too low");
+			}
+
+			let missing = total.unwrap() - Self::share_balance(&(kitty_id, sender.clone()));
+			ensure!(missing > 0, "Already holding all shares");
+
+			let reserved = price_per_share.checked_mul(&missing.into()).ok_or("Buyout price overflow")?;
+			<T as Trait>::Currency::reserve(&sender, reserved)?;
+
+			if let Some(current) = current {
+				Self::close_buyout(kitty_id);
+				Self::deposit_event(RawEvent::BuyoutCancelled(current.buyer, kitty_id));
+			}
+
+			<Buyouts<T>>::insert(kitty_id, Buyout {
+				buyer: sender.clone(),
+				price_per_share,
+				reserved,
+			});
+
+			Self::deposit_event(RawEvent::BuyoutOffered(sender, kitty_id, price_per_share));
+		}
+
+		/// Cancel a buyout offer and release the remaining reserved funds
+		pub fn cancel_buyout(origin, kitty_id: T::KittyIndex) {
+			let sender = ensure_signed(origin)?;
+
+			let buyout = Self::buyout(kitty_id);
+			ensure!(buyout.is_some(), "No buyout for kitty");
+			ensure!(buyout.unwrap().buyer == sender, "Only buyer can cancel buyout");
+
+			Self::close_buyout(kitty_id);
+
+			Self::deposit_event(RawEvent::BuyoutCancelled(sender, kitty_id));
+		}
+
+		/// Sell all the shares of the sender to the buyer at the offered price
+		pub fn accept_buyout(origin, kitty_id: T::KittyIndex) {
+			let sender = ensure_signed(origin)?;
+
+			let buyout = Self::buyout(kitty_id);
+			ensure!(buyout.is_some(), "No buyout for kitty");
+			let mut buyout = buyout.unwrap();
+			ensure!(buyout.buyer != sender, "Buyer cannot accept own buyout");
+
+			let shares = Self::share_balance(&(kitty_id, sender.clone()));
+			ensure!(shares > 0, "No shares to sell");
+
+			let price = buyout.price_per_share.checked_mul(&shares.into()).ok_or("Buyout price overflow")?;
+			ensure!(price <= buyout.reserved, "Not enough funds reserved by buyer");
+			// Other modules may have slashed the reserve since the offer
+			ensure!(<T as Trait>::Currency::reserved_balance(&buyout.buyer) >= price, "Not enough funds reserved by buyer");
+
+			// The buyer redeems the kitty with the last shares, checked before anything moves
+			let buyer_shares = Self::share_balance(&(kitty_id, buyout.buyer.clone()));
+			let redeemed = Self::total_shares(kitty_id) == Some(buyer_shares + shares);
+			if redeemed {
+				<kitties::Module<T>>::ensure_can_transfer(&Self::account_id(), &buyout.buyer, kitty_id)?;
+			}
+
+			// The reserve covers the whole price, checked above, so nothing is left unpaid
+			<T as Trait>::Currency::repatriate_reserved(&buyout.buyer, &sender, price)?;
+			// Can't fail: the sender sells all its shares
+			Self::move_shares(&sender, &buyout.buyer, kitty_id, shares)?;
+			buyout.reserved -= price;
+
+			let buyer = buyout.buyer.clone();
+			<Buyouts<T>>::insert(kitty_id, buyout);
+
+			Self::deposit_event(RawEvent::SharesSold(sender, buyer.clone(), kitty_id, shares, price));
+
+			if redeemed {
+				Self::do_redeem(&buyer, kitty_id)?;
+			}
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The account which owns the fractionalized kitties
+	pub fn account_id() -> T::AccountId {
+		MODULE_ID.into_account()
+	}
+
+	fn move_shares(from: &T::AccountId, to: &T::AccountId, kitty_id: T::KittyIndex, shares: u32) -> result::Result<(), &'static str> {
+		let from_balance = Self::share_balance(&(kitty_id, from.clone()));
+		ensure!(from_balance >= shares, "Not enough shares");
+
+		if from_balance == shares {
+			<ShareBalances<T>>::remove(&(kitty_id, from.clone()));
+		} else {
+			<ShareBalances<T>>::insert(&(kitty_id, from.clone()), from_balance - shares);
+		}
+		<ShareBalances<T>>::mutate(&(kitty_id, to.clone()), |balance| *balance += shares);
+
+		Ok(())
+	}
+
+	fn close_buyout(kitty_id: T::KittyIndex) {
+		if let Some(buyout) = <Buyouts<T>>::take(kitty_id) {
+			if !buyout.reserved.is_zero() {
+				<T as Trait>::Currency::unreserve(&buyout.buyer, buyout.reserved);
+			}
+		}
+	}
+
+	fn do_redeem(owner: &T::AccountId, kitty_id: T::KittyIndex) -> result::Result<(), &'static str> {
+		<kitties::Module<T>>::transfer_kitty(&Self::account_id(), owner, kitty_id)?;
+
+		Self::close_buyout(kitty_id);
+		<TotalShares<T>>::remove(kitty_id);
+		<ShareBalances<T>>::remove(&(kitty_id, owner.clone()));
+
+		Self::deposit_event(RawEvent::Redeemed(owner.clone(), kitty_id));
+
+		Ok(())
+	}
+}
+
+/// Tests for KittyFractions module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop};
+	use crate::mock::{Test, Origin, KittyModule, Balances};
+
+	impl Trait for Test {
+		type Currency = balances::Module<Test>;
+		type Event = ();
+	}
+	type FractionModule = Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		crate::mock::new_test_ext(vec![(1, 100), (2, 100), (3, 100)])
+	}
+
+	#[test]
+	fn fractionalize_and_redeem() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+
+			assert_noop!(FractionModule::fractionalize(Origin::signed(2), 0, 10), "Only owner can transfer kitty");
+			assert_ok!(FractionModule::fractionalize(Origin::signed(1), 0, 10));
+
+			assert_eq!(KittyModule::kitty_owner(0), Some(FractionModule::account_id()));
+			assert_eq!(FractionModule::share_balance(&(0, 1)), 10);
+
+			assert_ok!(FractionModule::transfer_shares(Origin::signed(1), 0, 2, 4));
+			assert_noop!(FractionModule::redeem(Origin::signed(1), 0), "Only holder of all shares can redeem kitty");
+
+			assert_ok!(FractionModule::transfer_shares(Origin::signed(2), 0, 1, 4));
+			assert_ok!(FractionModule::redeem(Origin::signed(1), 0));
+
+			assert_eq!(KittyModule::kitty_owner(0), Some(1));
+			assert_eq!(FractionModule::total_shares(0), None);
+			assert_eq!(FractionModule::share_balance(&(0, 1)), 0);
+		});
+	}
+
+	#[test]
+	fn module_account_is_not_capped() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::set_max_owned(Origin::ROOT, 2));
+			// the fractions account holds more kitties than the cap
+			for kitty_id in 0..3 {
+				assert_ok!(KittyModule::create(Origin::signed(1)));
+				assert_ok!(FractionModule::fractionalize(Origin::signed(1), kitty_id, 10));
+			}
+
+			assert_eq!(KittyModule::kitties_of(&FractionModule::account_id()), vec![0, 1, 2]);
+		});
+	}
+
+	#[test]
+	fn buyout_transfers_kitty_to_buyer() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(FractionModule::fractionalize(Origin::signed(1), 0, 10));
+			assert_ok!(FractionModule::transfer_shares(Origin::signed(1), 0, 2, 4));
+
+			assert_ok!(FractionModule::offer_buyout(Origin::signed(3), 0, 5));
+			assert_eq!(Balances::reserved_balance(&3), 50);
+
+			assert_ok!(FractionModule::accept_buyout(Origin::signed(1), 0));
+			assert_eq!(Balances::free_balance(&1), 130);
+			assert_eq!(KittyModule::kitty_owner(0), Some(FractionModule::account_id()));
+
+			assert_ok!(FractionModule::accept_buyout(Origin::signed(2), 0));
+			assert_eq!(Balances::free_balance(&2), 120);
+			assert_eq!(Balances::free_balance(&3), 50);
+			assert_eq!(Balances::reserved_balance(&3), 0);
+
+			assert_eq!(KittyModule::kitty_owner(0), Some(3));
+			assert_eq!(FractionModule::buyout(0), None);
+			assert_eq!(FractionModule::total_shares(0), None);
+		});
+	}
+
+	#[test]
+	fn buyout_checks_redeeming_before_selling() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(KittyModule::create(Origin::signed(3)));
+			assert_ok!(FractionModule::fractionalize(Origin::signed(1), 0, 10));
+
+			assert_noop!(FractionModule::offer_buyout(Origin::signed(3), 0, u64::max_value()), "Buyout price overflow");
+			assert_ok!(FractionModule::offer_buyout(Origin::signed(3), 0, 5));
+
+			// the buyer can't own the kitty it would redeem, so no shares are sold
+			assert_ok!(KittyModule::set_max_owned(Origin::ROOT, 1));
+			assert_noop!(FractionModule::accept_buyout(Origin::signed(1), 0), "Account owns too many kitties");
+			assert_eq!(FractionModule::share_balance(&(0, 1)), 10);
+			assert_eq!(Balances::reserved_balance(&3), 50);
+		});
+	}
+
+	#[test]
+	fn higher_buyout_replaces_offer() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(FractionModule::fractionalize(Origin::signed(1), 0, 10));
+
+			assert_noop!(FractionModule::offer_buyout(Origin::signed(3), 0, 0), "Buyout price must not be zero");
+			assert_ok!(FractionModule::offer_buyout(Origin::signed(3), 0, 5));
+			assert_noop!(FractionModule::offer_buyout(Origin::signed(2), 0, 5), "Buyout offer is too low");
+
+			assert_ok!(FractionModule::offer_buyout(Origin::signed(2), 0, 6));
+			assert_eq!(Balances::reserved_balance(&2), 60);
+			assert_eq!(Balances::reserved_balance(&3), 0);
+			assert_eq!(Balances::free_balance(&3), 100);
+			assert_eq!(FractionModule::buyout(0).map(|buyout| buyout.buyer), Some(2));
+		});
+	}
+
+	#[test]
+	fn cancel_buyout_releases_funds() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(FractionModule::fractionalize(Origin::signed(1), 0, 10));
+
+			assert_ok!(FractionModule::offer_buyout(Origin::signed(3), 0, 5));
+			assert_noop!(FractionModule::cancel_buyout(Origin::signed(1), 0), "Only buyer can cancel buyout");
+			assert_ok!(FractionModule::cancel_buyout(Origin::signed(3), 0));
+
+			assert_eq!(Balances::free_balance(&3), 100);
+			assert_eq!(Balances::reserved_balance(&3), 0);
+		});
+	}
+}
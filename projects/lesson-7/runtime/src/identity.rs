@@ -0,0 +1,311 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageValue, StorageMap,
+	traits::{Currency, ReservableCurrency, Get}
+};
+use sr_primitives::traits::EnsureOrigin;
+use rstd::prelude::*;
+use codec::{Encode, Decode};
+use system::ensure_signed;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+/// Maximum length in bytes of the display name and of the website
+pub const MAX_FIELD_LENGTH: usize = 32;
+
+/// Index of a registrar in `Registrars`
+pub type RegistrarIndex = u32;
+
+/// Human readable names of accounts, judged by registrars.
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// Amount reserved while an account has an identity
+	type IdentityDeposit: Get<BalanceOf<Self>>;
+	/// The origin allowed to add and remove registrars
+	type RegistrarOrigin: EnsureOrigin<Self::Origin>;
+}
+
+/// The information an account gives about itself. Fields are UTF-8, empty when not given.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Default, Clone, PartialEq, Eq)]
+pub struct IdentityInfo {
+	pub display: Vec<u8>,
+	pub web: Vec<u8>,
+}
+
+/// A registrar's judgement of an identity.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub enum Judgement {
+	/// The information looks right but was not checked thoroughly
+	Reasonable,
+	/// The information was checked and is right
+	KnownGood,
+	/// The information is wrong
+	Erroneous,
+}
+
+/// An identity with its deposit and judgements.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+pub struct Registration<Balance> {
+	pub info: IdentityInfo,
+	pub deposit: Balance,
+	/// Judgements of the current information, one per registrar
+	pub judgements: Vec<(RegistrarIndex, Judgement)>,
+}
+
+impl<Balance> Registration<Balance> {
+	/// Whether a registrar judged the information right and none judged it wrong
+	/// Only the judgements of the current `registrars` count
+	pub fn is_verified<AccountId>(&self, registrars: &[Option<AccountId>]) -> bool {
+		let is_registrar = |index: RegistrarIndex| registrars.get(index as usize).map_or(false, Option::is_some);
+		let judged = |j: Judgement| self.judgements.iter()
+			.any(|(index, judgement)| *judgement == j && is_registrar(*index));
+		!judged(Judgement::Erroneous) && (judged(Judgement::Reasonable) || judged(Judgement::KnownGood))
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Identity {
+		/// The identities of accounts
+		pub IdentityOf get(identity): map T::AccountId => Option<Registration<BalanceOf<T>>>;
+		/// The registrars, indexed by `RegistrarIndex`. None for a removed registrar, so indexes don't change.
+		pub Registrars get(registrars): Vec<Option<T::AccountId>>;
+		/// The `Erroneous` judgements of a cleared identity, given back when the account sets an identity again
+		pub ErroneousJudgements get(erroneous_judgements): map T::AccountId => Vec<RegistrarIndex>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+	{
+		/// An account set its identity. (who)
+		IdentitySet(AccountId),
+		/// An account cleared its identity. (who)
+		IdentityCleared(AccountId),
+		/// A registrar is added. (index, registrar)
+		RegistrarAdded(RegistrarIndex, AccountId),
+		/// A registrar is removed. (index, registrar)
+		RegistrarRemoved(RegistrarIndex, AccountId),
+		/// A registrar judged an identity. (index, target, judgement)
+		JudgementGiven(RegistrarIndex, AccountId, Judgement),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Set the identity of the sender, reserving the deposit the first time
+		/// Changing the information removes its judgements, except `Erroneous` ones, which a new identity
+		/// gets back from the identity the account cleared
+		pub fn set_identity(origin, info: IdentityInfo) {
+			let who = ensure_signed(origin)?;
+
+			ensure!(info.display.len() <= MAX_FIELD_LENGTH, "Display name too long");
+			ensure!(info.web.len() <= MAX_FIELD_LENGTH, "Website too long");
+
+			let registration = match Self::identity(&who) {
+				Some(mut registration) => {
+					if registration.info != info {
+						registration.info = info;
+						// a registrar judging the identity wrong judges it again to lift it
+						registration.judgements.retain(|(_, judgement)| *judgement == Judgement::Erroneous);
+					}
+					registration
+				},
+				None => {
+					let deposit = T::IdentityDeposit::get();
+					T::Currency::reserve(&who, deposit)?;
+					let judgements = <ErroneousJudgements<T>>::take(&who).into_iter()
+						.map(|index| (index, Judgement::Erroneous))
+						.collect();
+					Registration { info, deposit, judgements }
+				},
+			};
+
+			<IdentityOf<T>>::insert(&who, registration);
+
+			Self::deposit_event(RawEvent::IdentitySet(who));
+		}
+
+		/// Clear the identity of the sender, unreserving the deposit
+		/// `Erroneous` judgements are kept in `ErroneousJudgements` until the account sets an identity again
+		pub fn clear_identity(origin) {
+			let who = ensure_signed(origin)?;
+
+			let registration = Self::identity(&who);
+			ensure!(registration.is_some(), "No identity");
+			let registration = registration.unwrap();
+
+			let erroneous: Vec<RegistrarIndex> = registration.judgements.iter()
+				.filter(|(_, judgement)| *judgement == Judgement::Erroneous)
+				.map(|(index, _)| *index)
+				.collect();
+			if !erroneous.is_empty() {
+				<ErroneousJudgements<T>>::insert(&who, erroneous);
+			}
+
+			T::Currency::unreserve(&who, registration.deposit);
+			<IdentityOf<T>>::remove(&who);
+
+			Self::deposit_event(RawEvent::IdentityCleared(who));
+		}
+
+		/// Add a registrar
+		pub fn add_registrar(origin, registrar: T::AccountId) {
+			T::RegistrarOrigin::ensure_origin(origin)?;
+
+			let mut registrars = Self::registrars();
+			ensure!(!registrars.contains(&Some(registrar.clone())), "Already a registrar");
+
+			let index = registrars.len() as RegistrarIndex;
+			registrars.push(Some(registrar.clone()));
+			<Registrars<T>>::put(registrars);
+
+			Self::deposit_event(RawEvent::RegistrarAdded(index, registrar));
+		}
+
+		/// Remove the registrar at `index`. Its judgements stop counting, and the index isn't reused.
+		pub fn remove_registrar(origin, index: RegistrarIndex) {
+			T::RegistrarOrigin::ensure_origin(origin)?;
+
+			let mut registrars = Self::registrars();
+			let registrar = registrars.get_mut(index as usize).and_then(Option::take);
+			ensure!(registrar.is_some(), "No registrar at index");
+			<Registrars<T>>::put(registrars);
+
+			Self::deposit_event(RawEvent::RegistrarRemoved(index, registrar.unwrap()));
+		}
+
+		/// Judge the identity of `target` as the registrar at `index`, replacing its previous judgement
+		pub fn provide_judgement(origin, index: RegistrarIndex, target: T::AccountId, judgement: Judgement) {
+			let who = ensure_signed(origin)?;
+
+			ensure!(Self::registrars().get(index as usize) == Some(&Some(who)), "Not the registrar at index");
+
+			let registration = Self::identity(&target);
+			ensure!(registration.is_some(), "No identity");
+			let mut registration = registration.unwrap();
+
+			match registration.judgements.iter_mut().find(|(i, _)| *i == index) {
+				Some(previous) => previous.1 = judgement,
+				None => registration.judgements.push((index, judgement)),
+			}
+			<IdentityOf<T>>::insert(&target, registration);
+
+			Self::deposit_event(RawEvent::JudgementGiven(index, target, judgement));
+		}
+	}
+}
+
+/// Tests for Identity module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop, parameter_types};
+	use crate::mock::{Test, Origin, Balances};
+
+	parameter_types! {
+		pub const IdentityDeposit: u64 = 10;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Currency = balances::Module<Test>;
+		type IdentityDeposit = IdentityDeposit;
+		type RegistrarOrigin = system::EnsureRoot<u64>;
+	}
+	type IdentityModule = Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		crate::mock::new_test_ext(vec![(1, 100), (2, 100)])
+	}
+
+	fn info(display: &str) -> IdentityInfo {
+		IdentityInfo { display: display.as_bytes().to_vec(), web: b"https://kitties.example".to_vec() }
+	}
+
+	#[test]
+	fn identity_reserves_deposit_until_cleared() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(IdentityModule::set_identity(Origin::signed(1), info("Alice")));
+			assert_eq!(Balances::reserved_balance(&1), 10);
+			assert_eq!(IdentityModule::identity(1).unwrap().info, info("Alice"));
+
+			assert_ok!(IdentityModule::set_identity(Origin::signed(1), info("Alice's kitties")));
+			assert_eq!(Balances::reserved_balance(&1), 10);
+
+			assert_noop!(IdentityModule::set_identity(Origin::signed(1), info(&"a".repeat(33))), "Display name too long");
+
+			assert_ok!(IdentityModule::clear_identity(Origin::signed(1)));
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(IdentityModule::identity(1), None);
+			assert_noop!(IdentityModule::clear_identity(Origin::signed(1)), "No identity");
+		});
+	}
+
+	#[test]
+	fn registrar_judgements_are_reset_by_changes() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(IdentityModule::add_registrar(Origin::signed(1), 2), "bad origin: expected to be a root origin");
+			assert_ok!(IdentityModule::add_registrar(Origin::ROOT, 2));
+			let registrars = IdentityModule::registrars();
+
+			assert_ok!(IdentityModule::set_identity(Origin::signed(1), info("Alice")));
+			assert_noop!(IdentityModule::provide_judgement(Origin::signed(1), 0, 1, Judgement::KnownGood), "Not the registrar at index");
+			assert_ok!(IdentityModule::provide_judgement(Origin::signed(2), 0, 1, Judgement::KnownGood));
+			assert!(IdentityModule::identity(1).unwrap().is_verified(&registrars));
+
+			assert_ok!(IdentityModule::provide_judgement(Origin::signed(2), 0, 1, Judgement::Erroneous));
+			assert_eq!(IdentityModule::identity(1).unwrap().judgements, vec![(0, Judgement::Erroneous)]);
+			assert!(!IdentityModule::identity(1).unwrap().is_verified(&registrars));
+
+			// a name judged wrong stays flagged when changed, or cleared and set again
+			assert_ok!(IdentityModule::set_identity(Origin::signed(1), info("Bob")));
+			assert_eq!(IdentityModule::identity(1).unwrap().judgements, vec![(0, Judgement::Erroneous)]);
+			assert_ok!(IdentityModule::clear_identity(Origin::signed(1)));
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(IdentityModule::erroneous_judgements(1), vec![0]);
+			assert_ok!(IdentityModule::set_identity(Origin::signed(1), info("Bob")));
+			assert_eq!(IdentityModule::identity(1).unwrap().judgements, vec![(0, Judgement::Erroneous)]);
+			assert_eq!(IdentityModule::erroneous_judgements(1), vec![]);
+
+			assert_ok!(IdentityModule::provide_judgement(Origin::signed(2), 0, 1, Judgement::Reasonable));
+			assert_ok!(IdentityModule::set_identity(Origin::signed(1), info("Carol")));
+			assert_eq!(IdentityModule::identity(1).unwrap().judgements, vec![]);
+			assert_ok!(IdentityModule::clear_identity(Origin::signed(1)));
+		});
+	}
+
+	#[test]
+	fn removed_registrar_judgements_stop_counting() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(IdentityModule::add_registrar(Origin::ROOT, 2));
+			assert_noop!(IdentityModule::add_registrar(Origin::ROOT, 2), "Already a registrar");
+			assert_ok!(IdentityModule::set_identity(Origin::signed(1), info("Alice")));
+			assert_ok!(IdentityModule::provide_judgement(Origin::signed(2), 0, 1, Judgement::KnownGood));
+
+			assert_noop!(IdentityModule::remove_registrar(Origin::signed(1), 0), "bad origin: expected to be a root origin");
+			assert_noop!(IdentityModule::remove_registrar(Origin::ROOT, 1), "No registrar at index");
+			assert_ok!(IdentityModule::remove_registrar(Origin::ROOT, 0));
+			assert_noop!(IdentityModule::remove_registrar(Origin::ROOT, 0), "No registrar at index");
+			assert_eq!(IdentityModule::registrars(), vec![None]);
+
+			assert!(!IdentityModule::identity(1).unwrap().is_verified(&IdentityModule::registrars()));
+			assert_noop!(
+				IdentityModule::provide_judgement(Origin::signed(2), 0, 1, Judgement::KnownGood),
+				"Not the registrar at index"
+			);
+
+			// added again, the registrar gets a new index
+			assert_ok!(IdentityModule::add_registrar(Origin::ROOT, 2));
+			assert_ok!(IdentityModule::provide_judgement(Origin::signed(2), 1, 1, Judgement::Reasonable));
+			assert!(IdentityModule::identity(1).unwrap().is_verified(&IdentityModule::registrars()));
+		});
+	}
+}
@@ -1,78 +1,143 @@
 use support::{
 	decl_module, decl_storage, decl_event, ensure, StorageValue, StorageMap,
-	Parameter, traits::Currency
+	Parameter, traits::{Currency, WithdrawReason, ExistenceRequirement, OnUnbalanced, Imbalance}
 };
-use sr_primitives::traits::{SimpleArithmetic, Bounded, Member};
+use sr_primitives::traits::{SimpleArithmetic, Bounded, Member, EnsureOrigin};
+use sr_primitives::Permill;
+use rstd::prelude::*;
 use codec::{Encode, Decode};
 use runtime_io::blake2_128;
 use system::ensure_signed;
 use rstd::result;
 use crate::linked_item::{LinkedList, LinkedItem};
+use crate::scheduler::{Named, ScheduledOrigin};
 
-pub trait Trait: system::Trait {
-	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+pub trait Trait<I: Instance = DefaultInstance>: system::Trait {
+	type Event: From<Event<Self, I>> + Into<<Self as system::Trait>::Event>;
 	type KittyIndex: Parameter + Member + SimpleArithmetic + Bounded + Default + Copy;
 	type Currency: Currency<Self::AccountId>;
+	/// The origin allowed to change the sale fee and the ownership cap
+	type ParamsOrigin: EnsureOrigin<Self::Origin>;
+	/// Handler for the sale fees
+	type OnSaleFee: OnUnbalanced<NegativeImbalanceOf<Self, I>>;
+	/// The call the scheduler dispatches, which kitties calls are converted into
+	type ScheduledCall: From<Call<Self, I>>;
+	/// Scheduler of follow-up calls, such as the end of a listing
+	type Scheduler: Named<Self::AccountId, Self::BlockNumber, Self::ScheduledCall>;
 }
 
-type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+type BalanceOf<T, I> = <<T as Trait<I>>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+type NegativeImbalanceOf<T, I> = <<T as Trait<I>>::Currency as Currency<<T as system::Trait>::AccountId>>::NegativeImbalance;
 
 #[derive(Encode, Decode)]
 pub struct Kitty(pub [u8; 16]);
 
-type KittyLinkedItem<T> = LinkedItem<<T as Trait>::KittyIndex>;
-type OwnedKittiesList<T> = LinkedList<OwnedKitties<T>, <T as system::Trait>::AccountId, <T as Trait>::KittyIndex>;
+/// The terms of a kitty lease.
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode)]
+pub struct KittyLease<AccountId, BlockNumber, Balance> {
+	/// The account that receives the usage rights
+	pub lessee: AccountId,
+	/// The block at which control returns to the owner
+	pub until: BlockNumber,
+	/// The fee paid by the lessee to the owner
+	pub fee: Balance,
+}
+
+type KittyLeaseOf<T, I> = KittyLease<<T as system::Trait>::AccountId, <T as system::Trait>::BlockNumber, BalanceOf<T, I>>;
+
+type KittyLinkedItem<T, I = DefaultInstance> = LinkedItem<<T as Trait<I>>::KittyIndex>;
+type OwnedKittiesList<T, I = DefaultInstance> = LinkedList<OwnedKitties<T, I>, <T as system::Trait>::AccountId, <T as Trait<I>>::KittyIndex>;
 
 decl_storage! {
-	trait Store for Module<T: Trait> as Kitties {
+	trait Store for Module<T: Trait<I>, I: Instance = DefaultInstance> as Kitties {
 		/// Stores all the kitties, key is the kitty id / index
 		pub Kitties get(kitty): map T::KittyIndex => Option<Kitty>;
 		/// Stores the total number of kitties. i.e. the next kitty index
 		pub KittiesCount get(kitties_count): T::KittyIndex;
 
-		pub OwnedKitties get(owned_kitties): map (T::AccountId, Option<T::KittyIndex>) => Option<KittyLinkedItem<T>>;
+		pub OwnedKitties get(owned_kitties): map (T::AccountId, Option<T::KittyIndex>) => Option<KittyLinkedItem<T, I>>;
 
 		/// Get kitty owner
 		pub KittyOwners get(kitty_owner): map T::KittyIndex => Option<T::AccountId>;
 		/// Get kitty price. None means not for sale.
-		pub KittyPrices get(kitty_price): map T::KittyIndex => Option<BalanceOf<T>>
+		pub KittyPrices get(kitty_price): map T::KittyIndex => Option<BalanceOf<T, I>>;
+		/// Number of kitties with a price, i.e. for sale
+		pub ListedCount get(listed_count): T::KittyIndex;
+
+		/// Lease offers waiting to be accepted by the lessee
+		pub LeaseOffers get(lease_offer): map T::KittyIndex => Option<KittyLeaseOf<T, I>>;
+		/// Active leases. The lessee has the usage rights until the lease ends.
+		pub KittyLeases get(kitty_lease): map T::KittyIndex => Option<KittyLeaseOf<T, I>>;
+		/// Kitties whose lease ends at the given block
+		pub LeaseExpiries get(lease_expiries): map T::BlockNumber => Vec<T::KittyIndex>;
+
+		/// Share of the sale price paid by the seller on each sale
+		pub SaleFee get(sale_fee) config(): Permill;
+		/// Maximum number of kitties an account can own. Zero means no limit.
+		pub MaxOwned get(max_owned) config(): u32;
+	}
+	add_extra_genesis {
+		// Kitties of the genesis block, indexed in order. (owner, dna, price)
+		config(kitties): Vec<(T::AccountId, [u8; 16], Option<BalanceOf<T, I>>)>;
+		build(|config: &GenesisConfig<T, I>| {
+			for (owner, dna, price) in &config.kitties {
+				let kitty_id = <Module<T, I>>::next_kitty_id()
+					.expect("Genesis kitties fit in the kitty index; qed");
+				<Module<T, I>>::insert_kitty(owner, kitty_id, Kitty(*dna));
+				<Module<T, I>>::set_price(kitty_id, price.clone());
+			}
+		});
 	}
 }
 
 decl_event!(
-	pub enum Event<T> where
+	pub enum Event<T, I: Instance = DefaultInstance> where
 		<T as system::Trait>::AccountId,
-		<T as Trait>::KittyIndex,
-		Balance = BalanceOf<T>,
+		<T as Trait<I>>::KittyIndex,
+		<T as system::Trait>::BlockNumber,
+		Balance = BalanceOf<T, I>,
 	{
 		/// A kitty is created. (owner, kitty_id)
 		Created(AccountId, KittyIndex),
+		/// A kitty is bred, following its Created event. (owner, kitty_id, parent_1, parent_2)
+		Bred(AccountId, KittyIndex, KittyIndex, KittyIndex),
 		/// A kitty is transferred. (from, to, kitty_id)
 		Transferred(AccountId, AccountId, KittyIndex),
 		/// A kitty is available for sale. (owner, kitty_id, price)
 		Ask(AccountId, KittyIndex, Option<Balance>),
 		/// A kitty is sold. (from, to, kitty_id, price)
 		Sold(AccountId, AccountId, KittyIndex, Balance),
+		/// A lease is offered. (owner, lessee, kitty_id, until, fee)
+		LeaseOffered(AccountId, AccountId, KittyIndex, BlockNumber, Balance),
+		/// A lease offer is cancelled. (owner, lessee, kitty_id)
+		LeaseOfferCancelled(AccountId, AccountId, KittyIndex),
+		/// A lease is accepted and started. (owner, lessee, kitty_id, until)
+		LeaseStarted(AccountId, AccountId, KittyIndex, BlockNumber),
+		/// A lease is ended and control returned to the owner. (owner, lessee, kitty_id)
+		LeaseEnded(AccountId, AccountId, KittyIndex),
+		/// The sale fee is changed. (fee)
+		SaleFeeChanged(Permill),
+		/// The ownership cap is changed. (max_owned)
+		MaxOwnedChanged(u32),
 	}
 );
 
 decl_module! {
-	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+	pub struct Module<T: Trait<I>, I: Instance = DefaultInstance> for enum Call where origin: T::Origin {
 		fn deposit_event() = default;
 
+		fn on_initialize(n: T::BlockNumber) {
+			for kitty_id in <LeaseExpiries<T, I>>::take(n) {
+				Self::end_lease(kitty_id);
+			}
+		}
+
 		/// Create a new kitty
 		pub fn create(origin) {
 			let sender = ensure_signed(origin)?;
-			let kitty_id = Self::next_kitty_id()?;
 
-			// Generate a random 128bit value
-			let dna = Self::random_value(&sender);
-
-			// Create and store kitty
-			let kitty = Kitty(dna);
-			Self::insert_kitty(&sender, kitty_id, kitty);
-
-			Self::deposit_event(RawEvent::Created(sender, kitty_id));
+			Self::mint(&sender)?;
 		}
 
 		/// Breed kitties
@@ -81,14 +146,17 @@ decl_module! {
 
 			let new_kitty_id = Self::do_breed(&sender, kitty_id_1, kitty_id_2)?;
 
-			Self::deposit_event(RawEvent::Created(sender, new_kitty_id));
+			Self::deposit_event(RawEvent::Created(sender.clone(), new_kitty_id));
+			Self::deposit_event(RawEvent::Bred(sender, new_kitty_id, kitty_id_1, kitty_id_2));
 		}
 
 		/// Transfer a kitty to new owner
  		pub fn transfer(origin, to: T::AccountId, kitty_id: T::KittyIndex) {
  			let sender = ensure_signed(origin)?;
 
-  			ensure!(<OwnedKitties<T>>::exists(&(sender.clone(), Some(kitty_id))), "Only owner can transfer kitty");
+  			ensure!(<OwnedKitties<T, I>>::exists(&(sender.clone(), Some(kitty_id))), "Only owner can transfer kitty");
+			ensure!(!<KittyLeases<T, I>>::exists(kitty_id), "Kitty is leased");
+			Self::ensure_can_own(&to)?;
 
 			Self::do_transfer(&sender, &to, kitty_id);
 
@@ -97,21 +165,57 @@ decl_module! {
 
 		/// Set a price for a kitty for sale
 		/// None to delist the kitty
-		pub fn ask(origin, kitty_id: T::KittyIndex, price: Option<BalanceOf<T>>) {
+		pub fn ask(origin, kitty_id: T::KittyIndex, price: Option<BalanceOf<T, I>>) {
 			let sender = ensure_signed(origin)?;
 
-			ensure!(<OwnedKitties<T>>::exists(&(sender.clone(), Some(kitty_id))), "Only owner can set price for kitty");
+			ensure!(<OwnedKitties<T, I>>::exists(&(sender.clone(), Some(kitty_id))), "Only owner can set price for kitty");
+			ensure!(!<KittyLeases<T, I>>::exists(kitty_id), "Kitty is leased");
+			ensure!(price.is_none() || !<LeaseOffers<T, I>>::exists(kitty_id), "Kitty has a lease offer");
 
-			if let Some(ref price) = price {
-				<KittyPrices<T>>::insert(kitty_id, price);
-			} else {
-				<KittyPrices<T>>::remove(kitty_id);
-			}
+			let _ = T::Scheduler::cancel_named(ScheduledOrigin::Signed(sender.clone()), Self::ask_expiry_id(kitty_id));
+			Self::set_price(kitty_id, price);
 
 			Self::deposit_event(RawEvent::Ask(sender, kitty_id, price));
 		}
 
-		pub fn buy(origin, kitty_id: T::KittyIndex, price: BalanceOf<T>) {
+		/// Set a price for a kitty for sale until block `until`, when it is delisted
+		pub fn ask_until(origin, kitty_id: T::KittyIndex, price: BalanceOf<T, I>, until: T::BlockNumber) {
+			let sender = ensure_signed(origin)?;
+
+			ensure!(<OwnedKitties<T, I>>::exists(&(sender.clone(), Some(kitty_id))), "Only owner can set price for kitty");
+			ensure!(!<KittyLeases<T, I>>::exists(kitty_id), "Kitty is leased");
+			ensure!(!<LeaseOffers<T, I>>::exists(kitty_id), "Kitty has a lease offer");
+			ensure!(until > <system::Module<T>>::block_number(), "Listing must end in the future");
+
+			// replaces the end of a previous listing. The owner schedules the expiry and reserves its deposit.
+			let _ = T::Scheduler::cancel_named(ScheduledOrigin::Signed(sender.clone()), Self::ask_expiry_id(kitty_id));
+			T::Scheduler::schedule_named(
+				Self::ask_expiry_id(kitty_id),
+				until,
+				None,
+				ScheduledOrigin::Signed(sender.clone()),
+				<Call<T, I>>::expire_ask(kitty_id).into(),
+			)?;
+
+			Self::set_price(kitty_id, Some(price));
+
+			Self::deposit_event(RawEvent::Ask(sender, kitty_id, Some(price)));
+		}
+
+		/// Delist a kitty at the end of its listing, dispatched by the scheduler as the owner
+		pub fn expire_ask(origin, kitty_id: T::KittyIndex) {
+			let sender = ensure_signed(origin)?;
+
+			ensure!(<OwnedKitties<T, I>>::exists(&(sender.clone(), Some(kitty_id))), "Only owner can set price for kitty");
+
+			if <KittyPrices<T, I>>::exists(kitty_id) {
+				Self::set_price(kitty_id, None);
+
+				Self::deposit_event(RawEvent::Ask(sender, kitty_id, None));
+			}
+		}
+
+		pub fn buy(origin, kitty_id: T::KittyIndex, price: BalanceOf<T, I>) {
 			let sender = ensure_signed(origin)?;
 
 			let owner = Self::kitty_owner(kitty_id);
@@ -120,18 +224,101 @@ decl_module! {
 
 			let kitty_price = Self::kitty_price(kitty_id);
 			ensure!(kitty_price.is_some(), "Kitty not for sale");
+			ensure!(!<KittyLeases<T, I>>::exists(kitty_id), "Kitty is leased");
 
 			let kitty_price = kitty_price.unwrap();
 			ensure!(price >= kitty_price, "Price is too low");
+			Self::ensure_can_own(&sender)?;
 
-			T::Currency::transfer(&sender, &owner, kitty_price)?;
+			// The only fallible write: the price is paid in full before anything else changes
+			let payment = T::Currency::withdraw(&sender, kitty_price, WithdrawReason::Transfer, ExistenceRequirement::AllowDeath)?;
+			// The seller pays the fee out of the price
+			let (fee, proceeds) = payment.split(Self::sale_fee() * kitty_price);
+			T::Currency::resolve_creating(&owner, proceeds);
+			T::OnSaleFee::on_unbalanced(fee);
 
-			<KittyPrices<T>>::remove(kitty_id);
+			Self::set_price(kitty_id, None);
 
 			Self::do_transfer(&owner, &sender, kitty_id);
 
 			Self::deposit_event(RawEvent::Sold(owner, sender, kitty_id, kitty_price));
 		}
+
+		/// Offer to lease a kitty to `lessee` until block `until_block` for `fee`
+		/// The lease starts when the lessee accepts it
+		pub fn lease(origin, kitty_id: T::KittyIndex, lessee: T::AccountId, until_block: T::BlockNumber, fee: BalanceOf<T, I>) {
+			let sender = ensure_signed(origin)?;
+
+			ensure!(<OwnedKitties<T, I>>::exists(&(sender.clone(), Some(kitty_id))), "Only owner can lease kitty");
+			ensure!(!<KittyLeases<T, I>>::exists(kitty_id), "Kitty is leased");
+			ensure!(!<KittyPrices<T, I>>::exists(kitty_id), "Kitty is for sale");
+			ensure!(lessee != sender, "Cannot lease kitty to owner");
+			ensure!(until_block > <system::Module<T>>::block_number(), "Lease must end in the future");
+
+			<LeaseOffers<T, I>>::insert(kitty_id, KittyLease {
+				lessee: lessee.clone(),
+				until: until_block,
+				fee,
+			});
+
+			Self::deposit_event(RawEvent::LeaseOffered(sender, lessee, kitty_id, until_block, fee));
+		}
+
+		/// Cancel a lease offer which is not yet accepted
+		pub fn cancel_lease_offer(origin, kitty_id: T::KittyIndex) {
+			let sender = ensure_signed(origin)?;
+
+			ensure!(<OwnedKitties<T, I>>::exists(&(sender.clone(), Some(kitty_id))), "Only owner can cancel lease offer");
+
+			let offer = <LeaseOffers<T, I>>::take(kitty_id);
+			ensure!(offer.is_some(), "No lease offer for kitty");
+
+			Self::deposit_event(RawEvent::LeaseOfferCancelled(sender, offer.unwrap().lessee, kitty_id));
+		}
+
+		/// Accept a lease offer and pay the fee to the owner
+		pub fn accept_lease(origin, kitty_id: T::KittyIndex) {
+			let sender = ensure_signed(origin)?;
+
+			let offer = Self::lease_offer(kitty_id);
+			ensure!(offer.is_some(), "No lease offer for kitty");
+			let offer = offer.unwrap();
+
+			ensure!(offer.lessee == sender, "Only lessee can accept lease");
+			ensure!(offer.until > <system::Module<T>>::block_number(), "Lease offer is expired");
+
+			let owner = Self::kitty_owner(kitty_id);
+			ensure!(owner.is_some(), "Kitty does not exist");
+			let owner = owner.unwrap();
+
+			T::Currency::transfer(&sender, &owner, offer.fee)?;
+
+			<LeaseOffers<T, I>>::remove(kitty_id);
+			<LeaseExpiries<T, I>>::mutate(offer.until, |kitties| kitties.push(kitty_id));
+			let until = offer.until;
+			<KittyLeases<T, I>>::insert(kitty_id, offer);
+
+			Self::deposit_event(RawEvent::LeaseStarted(owner, sender, kitty_id, until));
+		}
+
+		/// Set the share of the sale price paid by the seller
+		pub fn set_sale_fee(origin, fee: Permill) {
+			T::ParamsOrigin::ensure_origin(origin)?;
+
+			<SaleFee<T, I>>::put(fee);
+
+			Self::deposit_event(RawEvent::SaleFeeChanged(fee));
+		}
+
+		/// Set the maximum number of kitties an account can own, zero for no limit
+		/// Accounts already above the limit keep their kitties
+		pub fn set_max_owned(origin, max_owned: u32) {
+			T::ParamsOrigin::ensure_origin(origin)?;
+
+			<MaxOwned<T, I>>::put(max_owned);
+
+			Self::deposit_event(RawEvent::MaxOwnedChanged(max_owned));
+		}
 	}
 }
 
@@ -139,12 +326,122 @@ fn combine_dna(dna1: u8, dna2: u8, selector: u8) -> u8 {
 	((selector & dna1) | (!selector & dna2))
 }
 
-impl<T: Trait> Module<T> {
+impl<T: Trait<I>, I: Instance> Module<T, I> {
+	/// The scheduler id of the end of a kitty listing, distinct per instance
+	fn ask_expiry_id(kitty_id: T::KittyIndex) -> Vec<u8> {
+		(I::PREFIX, b"ask", kitty_id).encode()
+	}
+
+	/// The kitties of an account, in the order of its `OwnedKitties` list.
+	pub fn kitties_of(owner: &T::AccountId) -> Vec<T::KittyIndex> {
+		<OwnedKittiesList<T, I>>::values(owner)
+	}
+
+	/// The account which has the usage rights of a kitty.
+	/// This is the lessee while the kitty is leased, otherwise the owner.
+	pub fn kitty_controller(kitty_id: T::KittyIndex) -> Option<T::AccountId> {
+		Self::kitty_lease(kitty_id)
+			.map(|lease| lease.lessee)
+			.or_else(|| Self::kitty_owner(kitty_id))
+	}
+
+	/// Create a new kitty with random DNA for `owner`
+	pub fn mint(owner: &T::AccountId) -> result::Result<T::KittyIndex, &'static str> {
+		let kitty_id = Self::next_kitty_id()?;
+		Self::ensure_can_own(owner)?;
+
+		// Generate a random 128bit value
+		let dna = Self::random_value(owner);
+
+		// Create and store kitty
+		let kitty = Kitty(dna);
+		Self::insert_kitty(owner, kitty_id, kitty);
+
+		Self::deposit_event(RawEvent::Created(owner.clone(), kitty_id));
+
+		Ok(kitty_id)
+	}
+
+	/// Transfer a kitty on behalf of another module. The kitty is delisted.
+	pub fn transfer_kitty(from: &T::AccountId, to: &T::AccountId, kitty_id: T::KittyIndex) -> result::Result<(), &'static str> {
+		Self::ensure_can_transfer(from, to, kitty_id)?;
+
+		Self::module_transfer(from, to, kitty_id);
+
+		Ok(())
+	}
+
+	/// Transfer a kitty to the account of another module, which holds it for its users.
+	/// Module accounts are not limited by `MaxOwned`. The kitty is delisted.
+	pub fn transfer_kitty_to_module(from: &T::AccountId, module: &T::AccountId, kitty_id: T::KittyIndex) -> result::Result<(), &'static str> {
+		Self::ensure_can_move(from, kitty_id)?;
+
+		Self::module_transfer(from, module, kitty_id);
+
+		Ok(())
+	}
+
+	/// Check that `transfer_kitty` would succeed, for modules making other changes first
+	pub fn ensure_can_transfer(from: &T::AccountId, to: &T::AccountId, kitty_id: T::KittyIndex) -> result::Result<(), &'static str> {
+		Self::ensure_can_move(from, kitty_id)?;
+		Self::ensure_can_own(to)
+	}
+
+	/// Check that `mint` would succeed, for modules charging for the kitty first
+	pub fn ensure_can_mint(owner: &T::AccountId) -> result::Result<(), &'static str> {
+		Self::next_kitty_id()?;
+		Self::ensure_can_own(owner)
+	}
+
 	fn random_value(sender: &T::AccountId) -> [u8; 16] {
 		let payload = (<system::Module<T>>::random_seed(), sender, <system::Module<T>>::extrinsic_index(), <system::Module<T>>::block_number());
 		payload.using_encoded(blake2_128)
 	}
 
+	/// Number of kitties owned by an account, walking its whole `OwnedKitties` list
+	#[cfg(test)]
+	pub fn owned_kitties_count(who: &T::AccountId) -> u32 {
+		<OwnedKittiesList<T, I>>::count_up_to(who, u32::max_value())
+	}
+
+	fn ensure_can_move(from: &T::AccountId, kitty_id: T::KittyIndex) -> result::Result<(), &'static str> {
+		ensure!(<OwnedKitties<T, I>>::exists(&(from.clone(), Some(kitty_id))), "Only owner can transfer kitty");
+		ensure!(!<KittyLeases<T, I>>::exists(kitty_id), "Kitty is leased");
+		Ok(())
+	}
+
+	fn module_transfer(from: &T::AccountId, to: &T::AccountId, kitty_id: T::KittyIndex) {
+		Self::set_price(kitty_id, None);
+		Self::do_transfer(from, to, kitty_id);
+
+		Self::deposit_event(RawEvent::Transferred(from.clone(), to.clone(), kitty_id));
+	}
+
+	/// Check that `who` is below the ownership cap
+	/// Only the kitties up to the cap are counted, so kitties owned before the cap are included
+	fn ensure_can_own(who: &T::AccountId) -> result::Result<(), &'static str> {
+		let max_owned = Self::max_owned();
+		ensure!(max_owned == 0 || <OwnedKittiesList<T, I>>::count_up_to(who, max_owned) < max_owned, "Account owns too many kitties");
+		Ok(())
+	}
+
+	/// Set or remove the price of a kitty, keeping `ListedCount` in step
+	fn set_price(kitty_id: T::KittyIndex, price: Option<BalanceOf<T, I>>) {
+		let listed = <KittyPrices<T, I>>::exists(kitty_id);
+		match price {
+			Some(price) => {
+				<KittyPrices<T, I>>::insert(kitty_id, price);
+				if !listed {
+					<ListedCount<T, I>>::mutate(|count| *count = *count + 1.into());
+				}
+			},
+			None => if listed {
+				<KittyPrices<T, I>>::remove(kitty_id);
+				<ListedCount<T, I>>::mutate(|count| *count = *count - 1.into());
+			},
+		}
+	}
+
 	fn next_kitty_id() -> result::Result<T::KittyIndex, &'static str> {
 		let kitty_id = Self::kitties_count();
 		if kitty_id == T::KittyIndex::max_value() {
@@ -154,14 +451,14 @@ impl<T: Trait> Module<T> {
 	}
 
 	fn insert_owned_kitty(owner: &T::AccountId, kitty_id: T::KittyIndex) {
-		<OwnedKittiesList<T>>::append(owner, kitty_id);
+		<OwnedKittiesList<T, I>>::append(owner, kitty_id);
 	}
 
 	fn insert_kitty(owner: &T::AccountId, kitty_id: T::KittyIndex, kitty: Kitty) {
 		// Create and store kitty
-		<Kitties<T>>::insert(kitty_id, kitty);
-		<KittiesCount<T>>::put(kitty_id + 1.into());
-		<KittyOwners<T>>::insert(kitty_id, owner.clone());
+		<Kitties<T, I>>::insert(kitty_id, kitty);
+		<KittiesCount<T, I>>::put(kitty_id + 1.into());
+		<KittyOwners<T, I>>::insert(kitty_id, owner.clone());
 
 		Self::insert_owned_kitty(owner, kitty_id);
 	}
@@ -173,10 +470,11 @@ impl<T: Trait> Module<T> {
 		ensure!(kitty1.is_some(), "Invalid kitty_id_1");
 		ensure!(kitty2.is_some(), "Invalid kitty_id_2");
 		ensure!(kitty_id_1 != kitty_id_2, "Needs different parent");
-		ensure!(Self::kitty_owner(&kitty_id_1).map(|owner| owner == *sender).unwrap_or(false), "Not onwer of kitty1");
- 		ensure!(Self::kitty_owner(&kitty_id_2).map(|owner| owner == *sender).unwrap_or(false), "Not owner of kitty2");
+		ensure!(Self::kitty_controller(kitty_id_1).map(|controller| controller == *sender).unwrap_or(false), "Not onwer of kitty1");
+ 		ensure!(Self::kitty_controller(kitty_id_2).map(|controller| controller == *sender).unwrap_or(false), "Not owner of kitty2");
 
 		let kitty_id = Self::next_kitty_id()?;
+		Self::ensure_can_own(sender)?;
 
 		let kitty1_dna = kitty1.unwrap().0;
 		let kitty2_dna = kitty2.unwrap().0;
@@ -196,10 +494,20 @@ impl<T: Trait> Module<T> {
 	}
 
 	fn do_transfer(from: &T::AccountId, to: &T::AccountId, kitty_id: T::KittyIndex)  {
- 		<OwnedKittiesList<T>>::remove(&from, kitty_id);
- 		<OwnedKittiesList<T>>::append(&to, kitty_id);
- 		<KittyOwners<T>>::insert(kitty_id, to);
+ 		<OwnedKittiesList<T, I>>::remove(&from, kitty_id);
+ 		Self::insert_owned_kitty(to, kitty_id);
+ 		<KittyOwners<T, I>>::insert(kitty_id, to);
+		<LeaseOffers<T, I>>::remove(kitty_id);
+		let _ = T::Scheduler::cancel_named(ScheduledOrigin::Signed(from.clone()), Self::ask_expiry_id(kitty_id));
  	}
+
+	fn end_lease(kitty_id: T::KittyIndex) {
+		if let Some(lease) = <KittyLeases<T, I>>::take(kitty_id) {
+			if let Some(owner) = Self::kitty_owner(kitty_id) {
+				Self::deposit_event(RawEvent::LeaseEnded(owner, lease.lessee, kitty_id));
+			}
+		}
+	}
 }
 
 /// Tests for Kitties module
@@ -208,78 +516,48 @@ mod tests {
 	use super::*;
 
 	use runtime_io::with_externalities;
-	use primitives::{H256, Blake2Hasher};
-	use support::{impl_outer_origin, parameter_types};
-	use sr_primitives::{traits::{BlakeTwo256, IdentityLookup}, testing::Header};
-	use sr_primitives::weights::Weight;
-	use sr_primitives::Perbill;
-
-	impl_outer_origin! {
-		pub enum Origin for Test {}
-	}
-
-	// For testing the module, we construct most of a mock runtime. This means
-	// first constructing a configuration type (`Test`) which `impl`s each of the
-	// configuration traits of modules we want to use.
-	#[derive(Clone, Eq, PartialEq, Debug)]
-	pub struct Test;
-	parameter_types! {
-		pub const BlockHashCount: u64 = 250;
-		pub const MaximumBlockWeight: Weight = 1024;
-		pub const MaximumBlockLength: u32 = 2 * 1024;
-		pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
-	}
-	impl system::Trait for Test {
-		type Origin = Origin;
-		type Call = ();
-		type Index = u64;
-		type BlockNumber = u64;
-		type Hash = H256;
-		type Hashing = BlakeTwo256;
-		type AccountId = u64;
-		type Lookup = IdentityLookup<Self::AccountId>;
-		type Header = Header;
-		type WeightMultiplierUpdate = ();
-		type Event = ();
-		type BlockHashCount = BlockHashCount;
-		type MaximumBlockWeight = MaximumBlockWeight;
-		type MaximumBlockLength = MaximumBlockLength;
-		type AvailableBlockRatio = AvailableBlockRatio;
-		type Version = ();
-	}
-	parameter_types! {
-		pub const ExistentialDeposit: u64 = 0;
-		pub const TransferFee: u64 = 0;
-		pub const CreationFee: u64 = 0;
-		pub const TransactionBaseFee: u64 = 0;
-		pub const TransactionByteFee: u64 = 0;
-	}
-	impl balances::Trait for Test {
-		type Balance = u64;
-		type OnFreeBalanceZero = ();
-		type OnNewAccount = ();
-		type Event = ();
-		type TransactionPayment = ();
-		type TransferPayment = ();
-		type DustRemoval = ();
-		type ExistentialDeposit = ExistentialDeposit;
-		type TransferFee = TransferFee;
-		type CreationFee = CreationFee;
-		type TransactionBaseFee = TransactionBaseFee;
-		type TransactionByteFee = TransactionByteFee;
-		type WeightToFee = ();
-	}
-	impl Trait for Test {
-		type KittyIndex = u32;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop};
+	use sr_primitives::traits::OnInitialize;
+	use crate::mock::{Test, Origin, KittyModule, System, Balances};
+
+	// The mock runtime implements the default instance. The second instance only exists in these
+	// tests, with no scheduler.
+	impl Trait<Instance2> for Test {
+		type KittyIndex = u64;
 		type Currency = balances::Module<Test>;
 		type Event = ();
+		type ParamsOrigin = system::EnsureRoot<u64>;
+		type OnSaleFee = ();
+		type ScheduledCall = Call<Test, Instance2>;
+		type Scheduler = ();
 	}
 	type OwnedKittiesTest = OwnedKitties<Test>;
+	type PuppyModule = Module<Test, Instance2>;
 
-	// This function basically just builds a genesis storage key/value store according to
-	// our desired mockup.
 	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
-		system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
+		crate::mock::new_test_ext(vec![(1, 100), (2, 100), (3, 100)])
+	}
+
+	#[test]
+	fn genesis_kitties_are_inserted_in_order() {
+		let mut t = crate::mock::storage(vec![]);
+		GenesisConfig::<Test> {
+			kitties: vec![(1, [1; 16], None), (2, [2; 16], Some(10))],
+			..Default::default()
+		}.assimilate_storage(&mut t).unwrap();
+		let mut ext: runtime_io::TestExternalities<Blake2Hasher> = t.into();
+
+		with_externalities(&mut ext, || {
+			assert_eq!(KittyModule::kitties_count(), 2);
+			assert_eq!(KittyModule::kitty(1).map(|kitty| kitty.0), Some([2; 16]));
+			assert_eq!(KittyModule::kitty_owner(0), Some(1));
+			assert_eq!(KittyModule::kitty_price(1), Some(10));
+			assert_eq!(OwnedKittiesTest::get(&(2, Some(1))), Some(KittyLinkedItem::<Test> {
+				prev: None,
+				next: None,
+			}));
+		});
 	}
 
 	#[test]
@@ -394,4 +672,157 @@ mod tests {
 			assert_eq!(OwnedKittiesTest::get(&(0, Some(2))), None);
 		});
 	}
+
+	#[test]
+	fn lease_gives_usage_rights_until_expiry() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+
+			assert_ok!(KittyModule::lease(Origin::signed(1), 0, 2, 5, 10));
+			assert_ok!(KittyModule::lease(Origin::signed(1), 1, 2, 5, 10));
+			// a kitty is not listed while its lease offer is open
+			assert_noop!(KittyModule::ask(Origin::signed(1), 0, Some(10)), "Kitty has a lease offer");
+			assert_noop!(KittyModule::ask_until(Origin::signed(1), 0, 10, 3), "Kitty has a lease offer");
+			assert_noop!(KittyModule::accept_lease(Origin::signed(3), 0), "Only lessee can accept lease");
+			assert_ok!(KittyModule::accept_lease(Origin::signed(2), 0));
+			assert_ok!(KittyModule::accept_lease(Origin::signed(2), 1));
+
+			assert_eq!(Balances::free_balance(&1), 120);
+			assert_eq!(Balances::free_balance(&2), 80);
+			assert_eq!(KittyModule::kitty_owner(0), Some(1));
+			assert_eq!(KittyModule::kitty_controller(0), Some(2));
+
+			assert_noop!(KittyModule::transfer(Origin::signed(1), 3, 0), "Kitty is leased");
+			assert_noop!(KittyModule::ask(Origin::signed(1), 0, Some(10)), "Kitty is leased");
+			assert_noop!(KittyModule::breed(Origin::signed(1), 0, 1), "Not onwer of kitty1");
+			assert_ok!(KittyModule::breed(Origin::signed(2), 0, 1));
+			assert_eq!(KittyModule::kitty_owner(2), Some(2));
+
+			KittyModule::on_initialize(5);
+
+			assert_eq!(KittyModule::kitty_lease(0), None);
+			assert_eq!(KittyModule::kitty_controller(0), Some(1));
+			assert_ok!(KittyModule::transfer(Origin::signed(1), 3, 0));
+		});
+	}
+
+	#[test]
+	fn lease_offer_is_cleared_on_transfer() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_noop!(KittyModule::lease(Origin::signed(1), 0, 2, 1, 10), "Lease must end in the future");
+			assert_ok!(KittyModule::lease(Origin::signed(1), 0, 2, 5, 10));
+			assert_ok!(KittyModule::transfer(Origin::signed(1), 3, 0));
+
+			assert_noop!(KittyModule::accept_lease(Origin::signed(2), 0), "No lease offer for kitty");
+		});
+	}
+
+	#[test]
+	fn sale_fee_and_ownership_cap_are_set_by_params_origin() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(KittyModule::set_sale_fee(Origin::signed(1), Permill::from_percent(10)), "bad origin: expected to be a root origin");
+			assert_ok!(KittyModule::set_sale_fee(Origin::ROOT, Permill::from_percent(10)));
+			assert_ok!(KittyModule::set_max_owned(Origin::ROOT, 2));
+
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_noop!(KittyModule::create(Origin::signed(1)), "Account owns too many kitties");
+			assert_noop!(KittyModule::breed(Origin::signed(1), 0, 1), "Account owns too many kitties");
+
+			assert_ok!(KittyModule::ask(Origin::signed(1), 0, Some(50)));
+			assert_ok!(KittyModule::ask(Origin::signed(1), 1, Some(150)));
+			// a failed payment changes nothing
+			assert_noop!(KittyModule::buy(Origin::signed(2), 1, 150), "too few free funds in account");
+			assert_ok!(KittyModule::ask(Origin::signed(1), 1, None));
+			assert_ok!(KittyModule::buy(Origin::signed(2), 0, 50));
+
+			assert_eq!(Balances::free_balance(&1), 145);
+			assert_eq!(Balances::free_balance(&2), 50);
+			assert_eq!(KittyModule::owned_kitties_count(&1), 1);
+			assert_eq!(KittyModule::owned_kitties_count(&2), 1);
+
+			assert_ok!(KittyModule::transfer(Origin::signed(1), 2, 1));
+			assert_noop!(KittyModule::create(Origin::signed(2)), "Account owns too many kitties");
+
+			assert_ok!(KittyModule::set_max_owned(Origin::ROOT, 0));
+			assert_ok!(KittyModule::create(Origin::signed(2)));
+			assert_eq!(KittyModule::owned_kitties_count(&2), 3);
+		});
+	}
+
+	#[test]
+	fn kitties_owned_before_the_cap_are_counted() {
+		with_externalities(&mut new_test_ext(), || {
+			// owned before the ownership cap was introduced
+			OwnedKittiesList::<Test>::append(&1, 10);
+			OwnedKittiesList::<Test>::append(&1, 11);
+			assert_eq!(KittyModule::owned_kitties_count(&1), 2);
+
+			assert_ok!(KittyModule::set_max_owned(Origin::ROOT, 2));
+			assert_noop!(KittyModule::create(Origin::signed(1)), "Account owns too many kitties");
+			assert_ok!(KittyModule::create(Origin::signed(2)));
+		});
+	}
+
+	#[test]
+	fn listed_kitties_are_counted() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+
+			assert_ok!(KittyModule::ask(Origin::signed(1), 0, Some(10)));
+			// a new price doesn't list the kitty again
+			assert_ok!(KittyModule::ask(Origin::signed(1), 0, Some(20)));
+			assert_ok!(KittyModule::ask(Origin::signed(1), 1, Some(10)));
+			assert_eq!(KittyModule::listed_count(), 2);
+
+			assert_ok!(KittyModule::buy(Origin::signed(2), 0, 20));
+			assert_ok!(KittyModule::ask(Origin::signed(1), 1, None));
+			assert_ok!(KittyModule::ask(Origin::signed(1), 1, None));
+			assert_eq!(KittyModule::listed_count(), 0);
+		});
+	}
+
+	#[test]
+	fn expired_listing_is_delisted_by_the_owner() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_noop!(KittyModule::ask_until(Origin::signed(1), 0, 10, 0), "Listing must end in the future");
+			assert_noop!(KittyModule::ask_until(Origin::signed(2), 0, 10, 5), "Only owner can set price for kitty");
+			assert_ok!(KittyModule::ask_until(Origin::signed(1), 0, 10, 5));
+			assert_eq!(KittyModule::kitty_price(0), Some(10));
+
+			assert_noop!(KittyModule::expire_ask(Origin::signed(2), 0), "Only owner can set price for kitty");
+			assert_ok!(KittyModule::expire_ask(Origin::signed(1), 0));
+			assert_eq!(KittyModule::kitty_price(0), None);
+		});
+	}
+
+	#[test]
+	fn instances_have_separate_storage() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(PuppyModule::create(Origin::signed(2)));
+
+			assert_eq!(KittyModule::kitties_count(), 2);
+			assert_eq!(PuppyModule::kitties_count(), 1);
+			assert_eq!(KittyModule::kitty_owner(0), Some(1));
+			assert_eq!(PuppyModule::kitty_owner(0), Some(2));
+
+			assert_ok!(PuppyModule::transfer(Origin::signed(2), 3, 0));
+			assert_eq!(PuppyModule::kitty_owner(0), Some(3));
+			assert_eq!(KittyModule::kitty_owner(0), Some(1));
+			assert_eq!(OwnedKitties::<Test, Instance2>::get(&(3, None)), Some(KittyLinkedItem::<Test, Instance2> {
+				prev: Some(0),
+				next: Some(0),
+			}));
+		});
+	}
 }
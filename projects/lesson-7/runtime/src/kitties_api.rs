@@ -0,0 +1,96 @@
+//! Runtime API reading the kitties registry.
+//!
+//! Light clients call it like full clients: the call is executed by a full node, which returns the
+//! storage proof of the call, and the light client checks it against the state root of the block.
+
+use rstd::prelude::*;
+use codec::{Encode, Decode};
+use client::decl_runtime_apis;
+use crate::{AccountId, Balance, Event, Identity, Kitties, Runtime, System, kitties};
+
+/// The kitty index of the `Kitties` module.
+pub type KittyIndex = <Runtime as kitties::Trait>::KittyIndex;
+
+/// An event of the `Kitties` module.
+pub type KittyEvent = kitties::Event<Runtime>;
+
+/// A kitty with its owner and price.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct KittyInfo {
+	pub dna: [u8; 16],
+	pub owner: AccountId,
+	/// None means not for sale
+	pub price: Option<Balance>,
+}
+
+/// The owner of a kitty and its identity.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct OwnerInfo {
+	pub account: AccountId,
+	/// The display name of the identity, empty without identity
+	pub display: Vec<u8>,
+	/// Whether a registrar judged the identity right and none judged it wrong
+	pub verified: bool,
+}
+
+decl_runtime_apis! {
+	/// Reads of the kitties registry.
+	///
+	/// The calls read the state of the block they are made at, without initializing the next block:
+	/// the events of the block are still there and the calls scheduled at the next block are not
+	/// dispatched.
+	pub trait KittiesApi {
+		/// Number of kitties, i.e. the next kitty index.
+		#[skip_initialize_block]
+		fn kitties_count() -> KittyIndex;
+		/// A kitty, its owner and its price.
+		#[skip_initialize_block]
+		fn kitty(kitty_id: KittyIndex) -> Option<KittyInfo>;
+		/// The owner of a kitty with its identity.
+		#[skip_initialize_block]
+		fn kitty_owner(kitty_id: KittyIndex) -> Option<OwnerInfo>;
+		/// The kitties owned by an account, in the order of its `OwnedKitties` list.
+		#[skip_initialize_block]
+		fn owned_kitties(owner: AccountId) -> Vec<KittyIndex>;
+		/// The `Kitties` events of the block with their index among the events of the block.
+		#[skip_initialize_block]
+		fn kitty_events() -> Vec<(u32, KittyEvent)>;
+		/// Number of kitties for sale.
+		#[skip_initialize_block]
+		fn listed_count() -> KittyIndex;
+	}
+}
+
+/// `KittiesApi::kitty`
+pub fn kitty(kitty_id: KittyIndex) -> Option<KittyInfo> {
+	let kitty = Kitties::kitty(kitty_id)?;
+	Some(KittyInfo {
+		dna: kitty.0,
+		owner: Kitties::kitty_owner(kitty_id)?,
+		price: Kitties::kitty_price(kitty_id),
+	})
+}
+
+/// `KittiesApi::kitty_owner`
+pub fn kitty_owner(kitty_id: KittyIndex) -> Option<OwnerInfo> {
+	let account = Kitties::kitty_owner(kitty_id)?;
+	let registration = Identity::identity(&account);
+	Some(OwnerInfo {
+		display: registration.as_ref().map(|registration| registration.info.display.clone()).unwrap_or_default(),
+		verified: registration.map_or(false, |registration| registration.is_verified(&Identity::registrars())),
+		account,
+	})
+}
+
+/// `KittiesApi::kitty_events`
+pub fn kitty_events() -> Vec<(u32, KittyEvent)> {
+	System::events().into_iter()
+		.enumerate()
+		.filter_map(|(index, record)| match record.event {
+			Event::kitties(event) => Some((index as u32, event)),
+			_ => None,
+		})
+		.collect()
+}
@@ -9,12 +9,12 @@
 include!(concat!(env!("OUT_DIR"), "/wasm_binary.rs"));
 
 use rstd::prelude::*;
-use primitives::{OpaqueMetadata, crypto::key_types};
+use primitives::{OpaqueMetadata, crypto::key_types, u32_trait::{_1, _2, _3}};
 use sr_primitives::{
 	ApplyResult, transaction_validity::TransactionValidity, generic, create_runtime_str,
 	impl_opaque_keys, AnySignature
 };
-use sr_primitives::traits::{NumberFor, BlakeTwo256, Block as BlockT, DigestFor, StaticLookup, Verify, ConvertInto};
+use sr_primitives::traits::{NumberFor, BlakeTwo256, Block as BlockT, DigestFor, Verify, ConvertInto};
 use sr_primitives::weights::Weight;
 use babe::{AuthorityId as BabeId};
 use grandpa::{AuthorityId as GrandpaId, AuthorityWeight as GrandpaWeight};
@@ -24,6 +24,8 @@ use client::{
 	runtime_api as client_api, impl_runtime_apis
 };
 use version::RuntimeVersion;
+use codec::{Encode, Decode};
+use support::traits::Get;
 #[cfg(feature = "std")]
 use version::NativeVersion;
 
@@ -32,6 +34,9 @@ use version::NativeVersion;
 pub use sr_primitives::BuildStorage;
 pub use timestamp::Call as TimestampCall;
 pub use balances::Call as BalancesCall;
+pub use sudo::Call as SudoCall;
+pub use upgrade::Call as RuntimeUpgradeCall;
+pub use democracy::Call as DemocracyCall;
 pub use sr_primitives::{Permill, Perbill};
 pub use support::{StorageValue, construct_runtime, parameter_types};
 
@@ -39,6 +44,8 @@ pub use support::{StorageValue, construct_runtime, parameter_types};
 pub type BlockNumber = u32;
 
 /// Alias to 512-bit hash when used in the context of a transaction signature on the chain.
+///
+/// sr25519 or ed25519 only: this Substrate version has no `MultiSignature` nor ECDSA keys.
 pub type Signature = AnySignature;
 
 /// Some way of identifying an account on the chain. We intentionally make it equivalent
@@ -65,9 +72,54 @@ pub type DigestItem = generic::DigestItem<Hash>;
 mod template;
 
 /// Used for the module kitties in `./kitties.rs`
-mod kitties;
+pub mod kitties;
 
-mod linked_item;
+pub mod linked_item;
+
+/// Runtime API reading the kitties registry in `./kitties_api.rs`
+pub mod kitties_api;
+
+/// Used for the module battles in `./battles.rs`
+mod battles;
+
+/// Used for the module fractions in `./fractions.rs`
+mod fractions;
+
+/// Used for the module collections in `./collections.rs`
+mod collections;
+
+/// Used for the module chain_params in `./chain_params.rs`
+mod chain_params;
+
+/// Used for the module upgrade in `./upgrade.rs`
+pub mod upgrade;
+
+/// Used for the module validator_set in `./validator_set.rs`
+mod validator_set;
+
+/// Transaction fee model and fee estimates in `./fees.rs`
+pub mod fees;
+
+/// Used for the module multisig in `./multisig.rs`
+mod multisig;
+
+/// Used for the module proxy in `./proxy.rs`
+mod proxy;
+
+/// Used for the module identity in `./identity.rs`
+pub mod identity;
+
+/// Used for the module vesting in `./vesting.rs`
+pub mod vesting;
+
+/// Used for the module scheduler in `./scheduler.rs`
+mod scheduler;
+
+/// Addresses of extrinsics and calls in `./address.rs`
+pub mod address;
+
+/// Mock runtime for the tests of the modules in `./mock.rs`
+mod mock;
 
 /// Opaque types. These are used by the CLI to instantiate machinery that don't need to know
 /// the specifics of the runtime. They can then be made to be agnostic over specific formats
@@ -102,12 +154,15 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
 	spec_name: create_runtime_str!("substrate-kitties"),
 	impl_name: create_runtime_str!("substrate-kitties"),
 	authoring_version: 3,
-	spec_version: 4,
-	impl_version: 4,
+	spec_version: 18,
+	impl_version: 18,
 	apis: RUNTIME_API_VERSIONS,
 };
 
 /// Constants for Babe.
+///
+/// These are the defaults of the `ChainParams` genesis, which sets the
+/// actual slot duration, epoch length and `c` of a chain.
 
 /// Since BABE is probabilistic this is the average expected block time that
 /// we are targetting. Blocks will be produced at a minimum duration defined
@@ -158,7 +213,7 @@ impl system::Trait for Runtime {
 	/// The aggregated dispatch type that is available for extrinsics.
 	type Call = Call;
 	/// The lookup mechanism to get account ID from whatever is passed in dispatchers.
-	type Lookup = Indices;
+	type Lookup = address::MultiAddressLookup<Runtime>;
 	/// The index type for storing how many extrinsics an account has signed.
 	type Index = Index;
 	/// The index type for blocks.
@@ -171,8 +226,8 @@ impl system::Trait for Runtime {
 	type Header = generic::Header<BlockNumber, BlakeTwo256>;
 	/// The ubiquitous event type.
 	type Event = Event;
-	/// Update weight (to fee) multiplier per-block.
-	type WeightMultiplierUpdate = ();
+	/// Update weight (to fee) multiplier per-block, following how full blocks are.
+	type WeightMultiplierUpdate = fees::FeeMultiplierUpdate;
 	/// The ubiquitous origin type.
 	type Origin = Origin;
 	/// Maximum number of block number to block hash mappings to keep (oldest pruned first).
@@ -186,9 +241,20 @@ impl system::Trait for Runtime {
 	type Version = Version;
 }
 
-parameter_types! {
-	pub const EpochDuration: u64 = EPOCH_DURATION_IN_BLOCKS as u64;
-	pub const ExpectedBlockTime: u64 = MILLISECS_PER_BLOCK;
+/// The epoch length set in the `ChainParams` genesis.
+pub struct EpochDuration;
+impl Get<u64> for EpochDuration {
+	fn get() -> u64 {
+		ChainParams::epoch_duration()
+	}
+}
+
+/// The slot duration set in the `ChainParams` genesis.
+pub struct ExpectedBlockTime;
+impl Get<u64> for ExpectedBlockTime {
+	fn get() -> u64 {
+		ChainParams::slot_duration()
+	}
 }
 
 impl babe::Trait for Runtime {
@@ -212,8 +278,17 @@ impl indices::Trait for Runtime {
 	type Event = Event;
 }
 
-parameter_types! {
-	pub const MinimumPeriod: u64 = 1000;
+/// Half the slot duration set in the `ChainParams` genesis, BABE slots last two minimum periods.
+///
+/// This used to be a fixed 1000, which is above half the slot on chains with slots under 2
+/// seconds, so a block could not be sealed at each slot. With the default 6 second slots the
+/// minimum period is now 3 seconds, still below the block time; a chain wanting the old 1 second
+/// sets a `slotDuration` of 2000.
+pub struct MinimumPeriod;
+impl Get<u64> for MinimumPeriod {
+	fn get() -> u64 {
+		ChainParams::slot_duration() / 2
+	}
 }
 
 impl timestamp::Trait for Runtime {
@@ -227,8 +302,8 @@ parameter_types! {
 	pub const ExistentialDeposit: u128 = 500;
 	pub const TransferFee: u128 = 0;
 	pub const CreationFee: u128 = 0;
-	pub const TransactionBaseFee: u128 = 0;
-	pub const TransactionByteFee: u128 = 1;
+	pub const TransactionBaseFee: u128 = 1_000_000_000;
+	pub const TransactionByteFee: u128 = 10_000_000;
 }
 
 impl balances::Trait for Runtime {
@@ -240,15 +315,16 @@ impl balances::Trait for Runtime {
 	type OnNewAccount = Indices;
 	/// The ubiquitous event type.
 	type Event = Event;
-	type TransactionPayment = ();
-	type DustRemoval = ();
+	/// Transaction fees and dust go to the treasury.
+	type TransactionPayment = Treasury;
+	type DustRemoval = Treasury;
 	type TransferPayment = ();
 	type ExistentialDeposit = ExistentialDeposit;
 	type TransferFee = TransferFee;
 	type CreationFee = CreationFee;
 	type TransactionBaseFee = TransactionBaseFee;
 	type TransactionByteFee = TransactionByteFee;
-	type WeightToFee = ConvertInto;
+	type WeightToFee = fees::WeightToFee;
 }
 
 impl sudo::Trait for Runtime {
@@ -265,6 +341,281 @@ impl kitties::Trait for Runtime {
 	type Event = Event;
 	type KittyIndex = u32;
 	type Currency = Balances;
+	/// The sale fee and ownership cap are changed by referendum
+	type ParamsOrigin = system::EnsureRoot<AccountId>;
+	type OnSaleFee = Treasury;
+	type ScheduledCall = Call;
+	type Scheduler = Scheduler;
+}
+
+/// A second, independent registry sharing the kitties module
+impl kitties::Trait<kitties::Instance2> for Runtime {
+	type Event = Event;
+	type KittyIndex = u64;
+	type Currency = Balances;
+	type ParamsOrigin = system::EnsureRoot<AccountId>;
+	type OnSaleFee = Treasury;
+	type ScheduledCall = Call;
+	type Scheduler = Scheduler;
+}
+
+impl battles::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type Randomness = Babe;
+}
+
+impl fractions::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+}
+
+impl collections::Trait for Runtime {
+	type Event = Event;
+	type CollectionIndex = u32;
+	type Currency = Balances;
+}
+
+impl chain_params::Trait for Runtime {}
+
+impl upgrade::Trait for Runtime {
+	type Event = Event;
+}
+
+parameter_types! {
+	pub const ValidatorBond: Balance = 1 << 50;
+	pub const MaxValidators: u32 = 100;
+}
+
+impl validator_set::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type ValidatorBond = ValidatorBond;
+	type MaxValidators = MaxValidators;
+	/// A council majority approves validators.
+	type ApproveOrigin = collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>;
+	type ExistingAuthorities = FixedAuthorities;
+}
+
+/// The BABE and GRANDPA authorities of a chain from before the validator set, paired in genesis
+/// order. The account of each is its BABE key, which its node holds.
+pub struct FixedAuthorities;
+
+impl validator_set::ExistingAuthorities<AccountId, opaque::SessionKeys> for FixedAuthorities {
+	fn existing_authorities() -> Vec<(AccountId, opaque::SessionKeys)> {
+		Babe::authorities().into_iter()
+			.zip(Grandpa::grandpa_authorities())
+			.map(|((babe, _), (grandpa, _))| {
+				let account = AccountId::decode(&mut &babe.encode()[..]).unwrap_or_default();
+				(account, opaque::SessionKeys { grandpa, babe })
+			})
+			.collect()
+	}
+}
+
+parameter_types! {
+	pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(17);
+}
+
+impl session::Trait for Runtime {
+	type Event = Event;
+	/// Validators are identified by their stash account.
+	type ValidatorId = AccountId;
+	/// Session keys are set by the controller of a stash.
+	type ValidatorIdOf = validator_set::StashOf<Self>;
+	/// Sessions follow BABE epochs.
+	type ShouldEndSession = Babe;
+	type OnSessionEnding = ValidatorSet;
+	type SessionHandler = opaque::SessionHandlers;
+	type Keys = opaque::SessionKeys;
+	type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
+	type SelectInitialValidators = ValidatorSet;
+}
+
+type CouncilCollective = collective::Instance1;
+impl collective::Trait<CouncilCollective> for Runtime {
+	type Origin = Origin;
+	type Proposal = Call;
+	type Event = Event;
+}
+
+parameter_types! {
+	pub const LaunchPeriod: BlockNumber = 7 * DAYS;
+	pub const VotingPeriod: BlockNumber = 7 * DAYS;
+	pub const EmergencyVotingPeriod: BlockNumber = 3 * HOURS;
+	pub const MinimumDeposit: Balance = 1 << 40;
+	pub const EnactmentPeriod: BlockNumber = DAYS;
+	pub const CooloffPeriod: BlockNumber = 7 * DAYS;
+}
+
+impl democracy::Trait for Runtime {
+	type Proposal = Call;
+	type Event = Event;
+	type Currency = Balances;
+	type EnactmentPeriod = EnactmentPeriod;
+	type LaunchPeriod = LaunchPeriod;
+	type VotingPeriod = VotingPeriod;
+	type EmergencyVotingPeriod = EmergencyVotingPeriod;
+	type MinimumDeposit = MinimumDeposit;
+	/// A majority of the council can table the next external referendum.
+	type ExternalOrigin = collective::EnsureProportionAtLeast<_1, _2, AccountId, CouncilCollective>;
+	/// A majority of the council can have the next external referendum pass by simple majority.
+	type ExternalMajorityOrigin = collective::EnsureProportionAtLeast<_1, _2, AccountId, CouncilCollective>;
+	/// A unanimous council can have the next external referendum pass unless voted down.
+	type ExternalDefaultOrigin = collective::EnsureProportionAtLeast<_1, _1, AccountId, CouncilCollective>;
+	/// Two thirds of the council can start an external referendum right away with the emergency voting period.
+	type FastTrackOrigin = collective::EnsureProportionAtLeast<_2, _3, AccountId, CouncilCollective>;
+	/// Two thirds of the council can cancel a passed referendum before it is enacted.
+	type CancellationOrigin = collective::EnsureProportionAtLeast<_2, _3, AccountId, CouncilCollective>;
+	/// Any council member can veto an external proposal once, for the cooloff period.
+	type VetoOrigin = collective::EnsureMember<AccountId, CouncilCollective>;
+	type CooloffPeriod = CooloffPeriod;
+}
+
+parameter_types! {
+	pub const ProposalBond: Permill = Permill::from_percent(5);
+	pub const ProposalBondMinimum: Balance = 1 << 40;
+	pub const SpendPeriod: BlockNumber = DAYS;
+	pub const Burn: Permill = Permill::from_percent(0);
+}
+
+impl treasury::Trait for Runtime {
+	type Currency = Balances;
+	/// Two thirds of the council approve a spend proposal.
+	type ApproveOrigin = collective::EnsureProportionAtLeast<_2, _3, AccountId, CouncilCollective>;
+	/// A council majority rejects a spend proposal, slashing its bond.
+	type RejectOrigin = collective::EnsureProportionMoreThan<_1, _2, AccountId, CouncilCollective>;
+	type Event = Event;
+	type MintedForSpending = ();
+	/// Slashed bonds stay in the treasury.
+	type ProposalRejection = Treasury;
+	type ProposalBond = ProposalBond;
+	type ProposalBondMinimum = ProposalBondMinimum;
+	type SpendPeriod = SpendPeriod;
+	/// Funds left after a spend period are kept for later grants.
+	type Burn = Burn;
+}
+
+parameter_types! {
+	pub const MultisigDeposit: Balance = 1 << 40;
+}
+
+impl multisig::Trait for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type Currency = Balances;
+	type MultisigDeposit = MultisigDeposit;
+}
+
+/// The calls a proxy account can dispatch for the account which added it.
+#[derive(codec::Encode, codec::Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum ProxyType {
+	/// Any call
+	Any,
+	/// Only asking prices for and buying kitties
+	KittiesMarket,
+}
+
+impl proxy::CallFilter<Call> for ProxyType {
+	fn allows(&self, call: &Call) -> bool {
+		match self {
+			ProxyType::Any => true,
+			ProxyType::KittiesMarket => match call {
+				Call::Kitties(kitties::Call::ask(..)) | Call::Kitties(kitties::Call::buy(..)) => true,
+				_ => false,
+			},
+		}
+	}
+}
+
+parameter_types! {
+	pub const ProxyDeposit: Balance = 1 << 40;
+}
+
+impl proxy::Trait for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type ProxyType = ProxyType;
+	type Currency = Balances;
+	type ProxyDeposit = ProxyDeposit;
+}
+
+parameter_types! {
+	pub const IdentityDeposit: Balance = 1 << 40;
+}
+
+impl identity::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type IdentityDeposit = IdentityDeposit;
+	type RegistrarOrigin = system::EnsureRoot<AccountId>;
+}
+
+parameter_types! {
+	pub const MinVestedTransfer: Balance = 1 << 40;
+}
+
+impl vesting::Trait for Runtime {
+	type Event = Event;
+	type Currency = Balances;
+	type BlockNumberToBalance = ConvertInto;
+	type MinVestedTransfer = MinVestedTransfer;
+}
+
+parameter_types! {
+	pub const ScheduleDeposit: Balance = 1 << 40;
+	pub const MaxScheduledPerBlock: u32 = 50;
+	pub const MaxRootScheduledPerBlock: u32 = 50;
+}
+
+impl scheduler::Trait for Runtime {
+	type Event = Event;
+	type Call = Call;
+	type ScheduleOrigin = system::EnsureRoot<AccountId>;
+	type Currency = Balances;
+	type ScheduleDeposit = ScheduleDeposit;
+	type MaxScheduledPerBlock = MaxScheduledPerBlock;
+	type MaxRootScheduledPerBlock = MaxRootScheduledPerBlock;
+}
+
+parameter_types! {
+	pub const ContractTransferFee: Balance = 0;
+	pub const ContractCreationFee: Balance = 0;
+	pub const ContractFee: Balance = 1_000_000_000;
+	pub const TombstoneDeposit: Balance = 1 << 40;
+	pub const RentByteFee: Balance = 1 << 30;
+	pub const RentDepositOffset: Balance = 1 << 40;
+	pub const SurchargeReward: Balance = 1 << 40;
+}
+
+impl contracts::Trait for Runtime {
+	type Currency = Balances;
+	/// Contracts use kitties through the calls they dispatch with `ext_dispatch_call`.
+	type Call = Call;
+	type Event = Event;
+	type DetermineContractAddress = contracts::SimpleAddressDeterminator<Runtime>;
+	type ComputeDispatchFee = contracts::DefaultDispatchFeeComputor<Runtime>;
+	type TrieIdGenerator = contracts::TrieIdFromParentCounter<Runtime>;
+	/// Gas and rent are paid into the treasury, like transaction fees.
+	type GasPayment = Treasury;
+	type RentPayment = Treasury;
+	type SignedClaimHandicap = contracts::DefaultSignedClaimHandicap;
+	type TombstoneDeposit = TombstoneDeposit;
+	type StorageSizeOffset = contracts::DefaultStorageSizeOffset;
+	type RentByteFee = RentByteFee;
+	type RentDepositOffset = RentDepositOffset;
+	type SurchargeReward = SurchargeReward;
+	type TransferFee = ContractTransferFee;
+	type CreationFee = ContractCreationFee;
+	type TransactionBaseFee = TransactionBaseFee;
+	type TransactionByteFee = TransactionByteFee;
+	type ContractFee = ContractFee;
+	type CallBaseFee = contracts::DefaultCallBaseFee;
+	type InstantiateBaseFee = contracts::DefaultInstantiateBaseFee;
+	type MaxDepth = contracts::DefaultMaxDepth;
+	type MaxValueSize = contracts::DefaultMaxValueSize;
+	type BlockGasLimit = contracts::DefaultBlockGasLimit;
 }
 
 construct_runtime!(
@@ -274,6 +625,8 @@ construct_runtime!(
 		UncheckedExtrinsic = UncheckedExtrinsic
 	{
 		System: system::{Module, Call, Storage, Config, Event},
+		// Slot duration, epoch length and BABE `c` of the chain
+		ChainParams: chain_params::{Module, Storage, Config},
 		Timestamp: timestamp::{Module, Call, Storage, Inherent},
 		Babe: babe::{Module, Call, Storage, Config, Inherent(Timestamp)},
 		Grandpa: grandpa::{Module, Call, Storage, Config, Event},
@@ -283,12 +636,41 @@ construct_runtime!(
 		// Used for the module template in `./template.rs`
 		TemplateModule: template::{Module, Call, Storage, Event<T>},
 		// Substrate Kitties module
-		Kitties: kitties::{Module, Storage, Call, Event<T>},
+		Kitties: kitties::{Module, Storage, Call, Event<T>, Config<T>},
+		Puppies: kitties::<Instance2>::{Module, Storage, Call, Event<T>},
+		// Kitty battles built on the kitties DNA
+		Battles: battles::{Module, Storage, Call, Event<T>},
+		// Fractional ownership of kitties
+		KittyFractions: fractions::{Module, Storage, Call, Event<T>},
+		// Curated kitty collections
+		KittyCollections: collections::{Module, Storage, Call, Event<T>},
+		// Runtime upgrades declaring their spec name and version
+		RuntimeUpgrade: upgrade::{Module, Call, Event},
+		// Governance: public and council proposals voted in referenda, enacted as Root
+		Democracy: democracy::{Module, Call, Storage, Config, Event<T>},
+		Council: collective::<Instance1>::{Module, Call, Storage, Origin<T>, Event<T>, Config<T>},
+		// Transaction fees, dust and kitties sale fees, spent on council approved grants
+		Treasury: treasury::{Module, Call, Storage, Event<T>},
+		// Bonded validators, handed to the session module at the end of each session
+		ValidatorSet: validator_set::{Module, Call, Storage, Event<T>, Config<T>},
+		// Session keys of the validators, rotated with BABE epochs and set with `ValidatorSet::set_keys`
+		Session: session::{Module, Storage, Event, Config<T>},
+		// Shared kitty ownership: M-of-N multisig accounts and proxies limited by call type
+		Multisig: multisig::{Module, Call, Storage, Event<T>},
+		Proxy: proxy::{Module, Call, Storage, Event<T>},
+		// Display names and websites of accounts, judged by registrars
+		Identity: identity::{Module, Call, Storage, Event<T>},
+		// Balances unlocked per block, for team and investor allocations
+		Vesting: vesting::{Module, Call, Storage, Event<T>, Config<T>},
+		// Calls dispatched at a later block, once or periodically
+		Scheduler: scheduler::{Module, Call, Storage, Event<T>},
+		// Wasm smart contracts, metered with gas
+		Contracts: contracts::{Module, Call, Storage, Config<T>, Event<T>},
 	}
 );
 
 /// The address format for describing accounts.
-pub type Address = <Indices as StaticLookup>::Source;
+pub type Address = address::MultiAddress<AccountId, AccountIndex>;
 /// Block header type as expected by this runtime.
 pub type Header = generic::Header<BlockNumber, BlakeTwo256>;
 /// Block type as expected by this runtime.
@@ -304,7 +686,8 @@ pub type SignedExtra = (
 	system::CheckEra<Runtime>,
 	system::CheckNonce<Runtime>,
 	system::CheckWeight<Runtime>,
-	balances::TakeFees<Runtime>
+	balances::TakeFees<Runtime>,
+	upgrade::CheckCodeUpgrade,
 );
 /// Unchecked extrinsic type as expected by this runtime.
 pub type UncheckedExtrinsic = generic::UncheckedExtrinsic<Address, Call, Signature, SignedExtra>;
@@ -396,7 +779,7 @@ impl_runtime_apis! {
 			babe_primitives::BabeConfiguration {
 				median_required_blocks: 1000,
 				slot_duration: Babe::slot_duration(),
-				c: PRIMARY_PROBABILITY,
+				c: ChainParams::primary_probability(),
 			}
 		}
 
@@ -412,6 +795,38 @@ impl_runtime_apis! {
 		}
 	}
 
+	impl kitties_api::KittiesApi<Block> for Runtime {
+		fn kitties_count() -> kitties_api::KittyIndex {
+			Kitties::kitties_count()
+		}
+
+		fn kitty(kitty_id: kitties_api::KittyIndex) -> Option<kitties_api::KittyInfo> {
+			kitties_api::kitty(kitty_id)
+		}
+
+		fn kitty_owner(kitty_id: kitties_api::KittyIndex) -> Option<kitties_api::OwnerInfo> {
+			kitties_api::kitty_owner(kitty_id)
+		}
+
+		fn owned_kitties(owner: AccountId) -> Vec<kitties_api::KittyIndex> {
+			Kitties::kitties_of(&owner)
+		}
+
+		fn kitty_events() -> Vec<(u32, kitties_api::KittyEvent)> {
+			kitties_api::kitty_events()
+		}
+
+		fn listed_count() -> kitties_api::KittyIndex {
+			Kitties::listed_count()
+		}
+	}
+
+	impl fees::FeesApi<Block> for Runtime {
+		fn query_info(uxt: <Block as BlockT>::Extrinsic, len: u32) -> fees::FeeInfo {
+			fees::query_info(&uxt, len)
+		}
+	}
+
 	impl substrate_session::SessionKeys<Block> for Runtime {
 		fn generate_session_keys(seed: Option<Vec<u8>>) -> Vec<u8> {
 			let seed = seed.as_ref().map(|s| rstd::str::from_utf8(&s).expect("Seed is an utf8 string"));
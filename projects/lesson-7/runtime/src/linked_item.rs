@@ -1,3 +1,4 @@
+use rstd::prelude::*;
 use support::{StorageMap, Parameter};
 use sr_primitives::traits::Member;
 use codec::{Encode, Decode};
@@ -77,4 +78,29 @@ impl<Storage, Key, Value> LinkedList<Storage, Key, Value> where
 			Self::write(key, item.next, new_next);
 		}
 	}
-}
\ No newline at end of file
+
+	/// The values in the list, from the head
+	pub fn values(key: &Key) -> Vec<Value> {
+		let mut values = Vec::new();
+		let mut next = Self::read_head(key).next;
+		while let Some(value) = next {
+			values.push(value);
+			next = Self::read(key, Some(value)).next;
+		}
+		values
+	}
+
+	/// The number of values in the list, counting at most `max` of them
+	pub fn count_up_to(key: &Key, max: u32) -> u32 {
+		let mut count = 0;
+		let mut next = Self::read_head(key).next;
+		while let Some(value) = next {
+			if count == max {
+				break;
+			}
+			count += 1;
+			next = Self::read(key, Some(value)).next;
+		}
+		count
+	}
+}
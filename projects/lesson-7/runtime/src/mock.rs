@@ -0,0 +1,120 @@
+//! The mock runtime shared by the tests of the modules, with `System`, `Balances`, `Kitties` and
+//! `Scheduler`. Each module implements its own trait for `Test` in its tests.
+
+#![cfg(test)]
+
+use primitives::{H256, Blake2Hasher};
+use support::{impl_outer_origin, impl_outer_dispatch, parameter_types};
+use sr_primitives::{traits::{BlakeTwo256, IdentityLookup}, testing::Header};
+use sr_primitives::weights::Weight;
+use sr_primitives::{Perbill, StorageOverlay, ChildrenStorageOverlay, create_runtime_str};
+use version::RuntimeVersion;
+use crate::{kitties, scheduler};
+
+impl_outer_origin! {
+	pub enum Origin for Test {}
+}
+
+impl_outer_dispatch! {
+	pub enum Call for Test where origin: Origin {
+		kitties::KittyModule,
+	}
+}
+
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct Test;
+parameter_types! {
+	pub const BlockHashCount: u64 = 250;
+	pub const MaximumBlockWeight: Weight = 1024;
+	pub const MaximumBlockLength: u32 = 2 * 1024;
+	pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+	pub const Version: RuntimeVersion = RuntimeVersion {
+		spec_name: create_runtime_str!("substrate-kitties"),
+		impl_name: create_runtime_str!("substrate-kitties"),
+		authoring_version: 1,
+		spec_version: 4,
+		impl_version: 4,
+		apis: version::create_apis_vec!([]),
+	};
+}
+impl system::Trait for Test {
+	type Origin = Origin;
+	type Call = ();
+	type Index = u64;
+	type BlockNumber = u64;
+	type Hash = H256;
+	type Hashing = BlakeTwo256;
+	type AccountId = u64;
+	type Lookup = IdentityLookup<Self::AccountId>;
+	type Header = Header;
+	type WeightMultiplierUpdate = ();
+	type Event = ();
+	type BlockHashCount = BlockHashCount;
+	type MaximumBlockWeight = MaximumBlockWeight;
+	type MaximumBlockLength = MaximumBlockLength;
+	type AvailableBlockRatio = AvailableBlockRatio;
+	type Version = Version;
+}
+parameter_types! {
+	pub const ExistentialDeposit: u64 = 0;
+	pub const TransferFee: u64 = 0;
+	pub const CreationFee: u64 = 0;
+	pub const TransactionBaseFee: u64 = 0;
+	pub const TransactionByteFee: u64 = 0;
+}
+impl balances::Trait for Test {
+	type Balance = u64;
+	type OnFreeBalanceZero = ();
+	type OnNewAccount = ();
+	type Event = ();
+	type TransactionPayment = ();
+	type TransferPayment = ();
+	type DustRemoval = ();
+	type ExistentialDeposit = ExistentialDeposit;
+	type TransferFee = TransferFee;
+	type CreationFee = CreationFee;
+	type TransactionBaseFee = TransactionBaseFee;
+	type TransactionByteFee = TransactionByteFee;
+	type WeightToFee = ();
+}
+impl kitties::Trait for Test {
+	type KittyIndex = u32;
+	type Currency = balances::Module<Test>;
+	type Event = ();
+	type ParamsOrigin = system::EnsureRoot<u64>;
+	type OnSaleFee = ();
+	type ScheduledCall = Call;
+	type Scheduler = SchedulerModule;
+}
+parameter_types! {
+	pub const ScheduleDeposit: u64 = 10;
+	pub const MaxScheduledPerBlock: u32 = 2;
+	pub const MaxRootScheduledPerBlock: u32 = 2;
+}
+impl scheduler::Trait for Test {
+	type Event = ();
+	type Call = Call;
+	type ScheduleOrigin = system::EnsureRoot<u64>;
+	type Currency = balances::Module<Test>;
+	type ScheduleDeposit = ScheduleDeposit;
+	type MaxScheduledPerBlock = MaxScheduledPerBlock;
+	type MaxRootScheduledPerBlock = MaxRootScheduledPerBlock;
+}
+pub type KittyModule = kitties::Module<Test>;
+pub type SchedulerModule = scheduler::Module<Test>;
+pub type System = system::Module<Test>;
+pub type Balances = balances::Module<Test>;
+
+/// The genesis storage of `System` and `Balances`, for modules adding their own genesis config.
+pub fn storage(balances: Vec<(u64, u64)>) -> (StorageOverlay, ChildrenStorageOverlay) {
+	let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+	balances::GenesisConfig::<Test> {
+		balances,
+		vesting: vec![],
+	}.assimilate_storage(&mut t).unwrap();
+	t
+}
+
+pub fn new_test_ext(balances: Vec<(u64, u64)>) -> runtime_io::TestExternalities<Blake2Hasher> {
+	storage(balances).into()
+}
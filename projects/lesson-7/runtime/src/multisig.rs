@@ -0,0 +1,258 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageMap, Parameter,
+	traits::{Currency, ReservableCurrency, Get}, dispatch::Dispatchable
+};
+use sr_primitives::traits::Member;
+use sr_primitives::weights::{Weight, GetDispatchInfo, WeighData, ClassifyDispatch, DispatchClass};
+use rstd::prelude::*;
+use rstd::result;
+use codec::{Encode, Decode};
+use runtime_io::blake2_256;
+use system::ensure_signed;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+/// Maximum number of signatories of a multisig account
+pub const MAX_SIGNATORIES: usize = 100;
+
+/// Weight of an approval, the default weight of a call
+pub const APPROVAL_WEIGHT: Weight = 10_000;
+
+/// M-of-N multisig accounts dispatching a call once enough signatories approve it.
+///
+/// The account of a set of signatories and a threshold is derived from them, so it exists without
+/// being created and is funded like any other account.
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Call: Parameter + Member + Dispatchable<Origin = Self::Origin> + GetDispatchInfo;
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// Amount reserved from the first approver of a call until it is dispatched or cancelled
+	type MultisigDeposit: Get<BalanceOf<Self>>;
+}
+
+/// A call of a multisig account waiting for approvals.
+#[cfg_attr(feature = "std", derive(Debug, PartialEq, Eq))]
+#[derive(Encode, Decode)]
+pub struct PendingCall<AccountId, Balance> {
+	/// The account which reserved the deposit
+	pub depositor: AccountId,
+	pub deposit: Balance,
+	/// The signatories which approved the call
+	pub approvals: Vec<AccountId>,
+}
+
+/// The weight of an approval and of the call it may dispatch, which any approval can be.
+pub struct MultiWeight;
+
+impl<'a, AccountId, Call: GetDispatchInfo> WeighData<(&'a u16, &'a Vec<AccountId>, &'a Box<Call>)> for MultiWeight {
+	fn weigh_data(&self, (_, _, call): (&'a u16, &'a Vec<AccountId>, &'a Box<Call>)) -> Weight {
+		APPROVAL_WEIGHT.saturating_add(call.get_dispatch_info().weight)
+	}
+}
+
+impl<T> ClassifyDispatch<T> for MultiWeight {
+	fn classify_dispatch(&self, _: T) -> DispatchClass {
+		DispatchClass::Normal
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Multisig {
+		/// Calls waiting for approvals, by multisig account and call hash
+		pub PendingCalls get(pending_call): map (T::AccountId, [u8; 32]) => Option<PendingCall<T::AccountId, BalanceOf<T>>>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+	{
+		/// A call of a multisig account is approved. (approver, multisig, call_hash)
+		Approved(AccountId, AccountId, [u8; 32]),
+		/// A call of a multisig account is dispatched, with whether it succeeded. (multisig, call_hash, result)
+		Executed(AccountId, [u8; 32], bool),
+		/// A pending call is cancelled. (depositor, multisig, call_hash)
+		Cancelled(AccountId, AccountId, [u8; 32]),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Approve `call` as the multisig account of the sender and `other_signatories` with
+		/// `threshold`, dispatching it with the multisig account as signed origin on the last
+		/// approval needed
+		/// `other_signatories` must be sorted
+		#[weight = MultiWeight]
+		pub fn as_multi(origin, threshold: u16, other_signatories: Vec<T::AccountId>, call: Box<<T as Trait>::Call>) {
+			let who = ensure_signed(origin)?;
+
+			let signatories = Self::signatories(&who, other_signatories)?;
+			ensure!(threshold >= 1, "Threshold must be at least 1");
+			ensure!(threshold as usize <= signatories.len(), "Threshold is above the number of signatories");
+
+			let multisig = Self::multi_account_id(&signatories, threshold);
+			let call_hash = call.using_encoded(blake2_256);
+			let key = (multisig.clone(), call_hash);
+
+			let pending = match Self::pending_call(&key) {
+				Some(mut pending) => {
+					ensure!(!pending.approvals.contains(&who), "Already approved");
+					pending.approvals.push(who.clone());
+					pending
+				},
+				None => {
+					let deposit = T::MultisigDeposit::get();
+					T::Currency::reserve(&who, deposit)?;
+					PendingCall { depositor: who.clone(), deposit, approvals: vec![who.clone()] }
+				},
+			};
+
+			Self::deposit_event(RawEvent::Approved(who, multisig.clone(), call_hash));
+
+			if pending.approvals.len() < threshold as usize {
+				<PendingCalls<T>>::insert(&key, pending);
+				return Ok(());
+			}
+
+			<PendingCalls<T>>::remove(&key);
+			T::Currency::unreserve(&pending.depositor, pending.deposit);
+
+			let ok = match call.dispatch(system::RawOrigin::Signed(multisig.clone()).into()) {
+				Ok(_) => true,
+				Err(e) => {
+					runtime_io::print(e);
+					false
+				},
+			};
+
+			Self::deposit_event(RawEvent::Executed(multisig, call_hash, ok));
+		}
+
+		/// Cancel a pending call, unreserving the deposit. Only the depositor can cancel it
+		pub fn cancel_as_multi(origin, threshold: u16, other_signatories: Vec<T::AccountId>, call_hash: [u8; 32]) {
+			let who = ensure_signed(origin)?;
+
+			let signatories = Self::signatories(&who, other_signatories)?;
+			let multisig = Self::multi_account_id(&signatories, threshold);
+			let key = (multisig.clone(), call_hash);
+
+			let pending = Self::pending_call(&key);
+			ensure!(pending.is_some(), "No pending call");
+			let pending = pending.unwrap();
+			ensure!(pending.depositor == who, "Only depositor can cancel");
+
+			T::Currency::unreserve(&who, pending.deposit);
+			<PendingCalls<T>>::remove(&key);
+
+			Self::deposit_event(RawEvent::Cancelled(who, multisig, call_hash));
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// The account of sorted `signatories` with `threshold`
+	pub fn multi_account_id(signatories: &[T::AccountId], threshold: u16) -> T::AccountId {
+		let entropy = (b"kitties/multisig", signatories, threshold).using_encoded(blake2_256);
+		T::AccountId::decode(&mut &entropy[..]).unwrap_or_default()
+	}
+
+	/// `other_signatories` and `who` in order, checking the others are sorted and distinct
+	fn signatories(who: &T::AccountId, other_signatories: Vec<T::AccountId>) -> result::Result<Vec<T::AccountId>, &'static str> {
+		ensure!(!other_signatories.is_empty(), "Too few signatories");
+		ensure!(other_signatories.len() < MAX_SIGNATORIES, "Too many signatories");
+		ensure!(other_signatories.windows(2).all(|pair| pair[0] < pair[1]), "Signatories must be sorted and distinct");
+
+		let mut signatories = other_signatories;
+		match signatories.binary_search(who) {
+			Ok(_) => return Err("Sender is in the other signatories"),
+			Err(index) => signatories.insert(index, who.clone()),
+		}
+
+		Ok(signatories)
+	}
+}
+
+/// Tests for Multisig module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop, parameter_types};
+	use crate::kitties;
+	use crate::mock::{Test, Origin, Call, KittyModule, Balances};
+
+	parameter_types! {
+		pub const MultisigDeposit: u64 = 10;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Call = Call;
+		type Currency = balances::Module<Test>;
+		type MultisigDeposit = MultisigDeposit;
+	}
+	type MultisigModule = Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		crate::mock::new_test_ext(vec![(1, 100), (2, 100), (3, 100)])
+	}
+
+	fn guild() -> u64 {
+		MultisigModule::multi_account_id(&[1, 2, 3], 2)
+	}
+
+	#[test]
+	fn guild_kitty_needs_two_approvals() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(Balances::transfer(Origin::signed(1), guild(), 50));
+			assert_ok!(KittyModule::create(Origin::signed(guild())));
+
+			let call = Box::new(Call::KittyModule(kitties::Call::transfer(3, 0)));
+			assert_ok!(MultisigModule::as_multi(Origin::signed(1), 2, vec![2, 3], call.clone()));
+			assert_eq!(KittyModule::kitty_owner(0), Some(guild()));
+			assert_eq!(Balances::reserved_balance(&1), 10);
+			assert_noop!(MultisigModule::as_multi(Origin::signed(1), 2, vec![2, 3], call.clone()), "Already approved");
+
+			assert_ok!(MultisigModule::as_multi(Origin::signed(3), 2, vec![1, 2], call.clone()));
+			assert_eq!(KittyModule::kitty_owner(0), Some(3));
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(MultisigModule::pending_call(&(guild(), call.using_encoded(blake2_256))), None);
+		});
+	}
+
+	#[test]
+	fn signatories_must_be_sorted_and_threshold_reachable() {
+		with_externalities(&mut new_test_ext(), || {
+			let call = Box::new(Call::KittyModule(kitties::Call::create()));
+			assert_noop!(MultisigModule::as_multi(Origin::signed(1), 2, vec![3, 2], call.clone()), "Signatories must be sorted and distinct");
+			assert_noop!(MultisigModule::as_multi(Origin::signed(1), 2, vec![1, 2], call.clone()), "Sender is in the other signatories");
+			assert_noop!(MultisigModule::as_multi(Origin::signed(1), 4, vec![2, 3], call.clone()), "Threshold is above the number of signatories");
+			assert_noop!(MultisigModule::as_multi(Origin::signed(1), 2, vec![], call), "Too few signatories");
+		});
+	}
+
+	#[test]
+	fn approval_is_charged_the_weight_of_the_call() {
+		let create = Box::new(Call::KittyModule(kitties::Call::create()));
+		let weight = create.get_dispatch_info().weight;
+
+		assert_eq!(super::Call::<Test>::as_multi(2, vec![2, 3], create).get_dispatch_info().weight, APPROVAL_WEIGHT + weight);
+	}
+
+	#[test]
+	fn depositor_cancels_pending_call() {
+		with_externalities(&mut new_test_ext(), || {
+			let call = Box::new(Call::KittyModule(kitties::Call::create()));
+			let call_hash = call.using_encoded(blake2_256);
+			assert_ok!(MultisigModule::as_multi(Origin::signed(1), 2, vec![2, 3], call));
+
+			assert_noop!(MultisigModule::cancel_as_multi(Origin::signed(2), 2, vec![1, 3], call_hash), "Only depositor can cancel");
+			assert_ok!(MultisigModule::cancel_as_multi(Origin::signed(1), 2, vec![2, 3], call_hash));
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(KittyModule::kitties_count(), 0);
+		});
+	}
+}
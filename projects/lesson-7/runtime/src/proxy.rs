@@ -0,0 +1,211 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageMap, Parameter,
+	traits::{Currency, ReservableCurrency, Get}, dispatch::Dispatchable
+};
+use sr_primitives::traits::Member;
+use rstd::prelude::*;
+use system::ensure_signed;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+/// Maximum number of proxies of an account
+pub const MAX_PROXIES: usize = 32;
+
+/// The calls a proxy type allows.
+pub trait CallFilter<Call> {
+	/// Whether a proxy of this type can dispatch `call`
+	fn allows(&self, call: &Call) -> bool;
+}
+
+/// Proxy accounts dispatching calls for another account, limited by the type of the proxy.
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Call: Parameter + Member + Dispatchable<Origin = Self::Origin>;
+	/// The kinds of proxies and the calls each allows
+	type ProxyType: Parameter + Member + CallFilter<<Self as Trait>::Call>;
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// Amount reserved for each proxy until it is removed
+	type ProxyDeposit: Get<BalanceOf<Self>>;
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Proxy {
+		/// The proxies of an account and their types
+		pub Proxies get(proxies): map T::AccountId => Vec<(T::AccountId, T::ProxyType)>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+		<T as Trait>::ProxyType,
+	{
+		/// A proxy is added. (real, proxy, proxy_type)
+		ProxyAdded(AccountId, AccountId, ProxyType),
+		/// A proxy is removed. (real, proxy, proxy_type)
+		ProxyRemoved(AccountId, AccountId, ProxyType),
+		/// A proxy dispatched a call, with whether it succeeded. (real, proxy, result)
+		ProxyExecuted(AccountId, AccountId, bool),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Allow `proxy` to dispatch the calls `proxy_type` allows for the sender
+		pub fn add_proxy(origin, proxy: T::AccountId, proxy_type: T::ProxyType) {
+			let who = ensure_signed(origin)?;
+
+			let mut proxies = Self::proxies(&who);
+			ensure!(!proxies.iter().any(|p| p.0 == proxy && p.1 == proxy_type), "Proxy already added");
+			ensure!(proxies.len() < MAX_PROXIES, "Too many proxies");
+
+			T::Currency::reserve(&who, T::ProxyDeposit::get())?;
+
+			proxies.push((proxy.clone(), proxy_type.clone()));
+			<Proxies<T>>::insert(&who, proxies);
+
+			Self::deposit_event(RawEvent::ProxyAdded(who, proxy, proxy_type));
+		}
+
+		/// Remove a proxy of the sender
+		pub fn remove_proxy(origin, proxy: T::AccountId, proxy_type: T::ProxyType) {
+			let who = ensure_signed(origin)?;
+
+			let mut proxies = Self::proxies(&who);
+			let index = proxies.iter().position(|p| p.0 == proxy && p.1 == proxy_type);
+			ensure!(index.is_some(), "Proxy not found");
+			proxies.remove(index.unwrap());
+
+			T::Currency::unreserve(&who, T::ProxyDeposit::get());
+
+			if proxies.is_empty() {
+				<Proxies<T>>::remove(&who);
+			} else {
+				<Proxies<T>>::insert(&who, proxies);
+			}
+
+			Self::deposit_event(RawEvent::ProxyRemoved(who, proxy, proxy_type));
+		}
+
+		/// Dispatch `call` with `real` as signed origin, if the sender is a proxy of `real` allowed
+		/// to dispatch it
+		pub fn proxy(origin, real: T::AccountId, call: Box<<T as Trait>::Call>) {
+			let who = ensure_signed(origin)?;
+
+			let proxies = Self::proxies(&real);
+			ensure!(proxies.iter().any(|p| p.0 == who), "Not a proxy");
+			ensure!(proxies.iter().any(|p| p.0 == who && p.1.allows(&call)), "Call not allowed for proxy");
+
+			let ok = match call.dispatch(system::RawOrigin::Signed(real.clone()).into()) {
+				Ok(_) => true,
+				Err(e) => {
+					runtime_io::print(e);
+					false
+				},
+			};
+
+			Self::deposit_event(RawEvent::ProxyExecuted(real, who, ok));
+		}
+	}
+}
+
+/// Tests for Proxy module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use codec::{Encode, Decode};
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop, parameter_types};
+	use crate::kitties;
+	use crate::mock::{Test, Origin, Call, KittyModule, Balances};
+
+	#[derive(Encode, Decode, Clone, Eq, PartialEq, Debug)]
+	pub enum ProxyType {
+		Any,
+		Market,
+	}
+	impl CallFilter<Call> for ProxyType {
+		fn allows(&self, call: &Call) -> bool {
+			match self {
+				ProxyType::Any => true,
+				ProxyType::Market => match call {
+					Call::KittyModule(kitties::Call::ask(..)) | Call::KittyModule(kitties::Call::buy(..)) => true,
+					_ => false,
+				},
+			}
+		}
+	}
+
+	parameter_types! {
+		pub const ProxyDeposit: u64 = 10;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Call = Call;
+		type ProxyType = ProxyType;
+		type Currency = balances::Module<Test>;
+		type ProxyDeposit = ProxyDeposit;
+	}
+	type ProxyModule = Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		crate::mock::new_test_ext(vec![(1, 100), (2, 100), (3, 100)])
+	}
+
+	#[test]
+	fn market_proxy_only_asks_and_buys() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(ProxyModule::add_proxy(Origin::signed(1), 2, ProxyType::Market));
+
+			let ask = Box::new(Call::KittyModule(kitties::Call::ask(0, Some(10))));
+			assert_ok!(ProxyModule::proxy(Origin::signed(2), 1, ask));
+			assert_eq!(KittyModule::kitty_price(0), Some(10));
+
+			let transfer = Box::new(Call::KittyModule(kitties::Call::transfer(2, 0)));
+			assert_noop!(ProxyModule::proxy(Origin::signed(2), 1, transfer.clone()), "Call not allowed for proxy");
+			assert_noop!(ProxyModule::proxy(Origin::signed(3), 1, transfer), "Not a proxy");
+
+			assert_ok!(KittyModule::create(Origin::signed(3)));
+			assert_ok!(KittyModule::ask(Origin::signed(3), 1, Some(20)));
+			let buy = Box::new(Call::KittyModule(kitties::Call::buy(1, 20)));
+			assert_ok!(ProxyModule::proxy(Origin::signed(2), 1, buy));
+			assert_eq!(KittyModule::kitty_owner(1), Some(1));
+		});
+	}
+
+	#[test]
+	fn removed_proxy_cannot_dispatch() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			assert_ok!(ProxyModule::add_proxy(Origin::signed(1), 2, ProxyType::Any));
+			assert_noop!(ProxyModule::add_proxy(Origin::signed(1), 2, ProxyType::Any), "Proxy already added");
+
+			let transfer = Box::new(Call::KittyModule(kitties::Call::transfer(3, 0)));
+			assert_ok!(ProxyModule::remove_proxy(Origin::signed(1), 2, ProxyType::Any));
+			assert_noop!(ProxyModule::proxy(Origin::signed(2), 1, transfer.clone()), "Not a proxy");
+
+			assert_ok!(ProxyModule::add_proxy(Origin::signed(1), 2, ProxyType::Any));
+			assert_ok!(ProxyModule::proxy(Origin::signed(2), 1, transfer));
+			assert_eq!(KittyModule::kitty_owner(0), Some(3));
+		});
+	}
+
+	#[test]
+	fn proxies_reserve_a_deposit() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(ProxyModule::add_proxy(Origin::signed(1), 2, ProxyType::Any));
+			assert_ok!(ProxyModule::add_proxy(Origin::signed(1), 3, ProxyType::Market));
+			assert_eq!(Balances::reserved_balance(&1), 20);
+
+			assert_ok!(ProxyModule::remove_proxy(Origin::signed(1), 2, ProxyType::Any));
+			assert_eq!(Balances::reserved_balance(&1), 10);
+			assert_ok!(ProxyModule::remove_proxy(Origin::signed(1), 3, ProxyType::Market));
+			assert_eq!(Balances::reserved_balance(&1), 0);
+		});
+	}
+}
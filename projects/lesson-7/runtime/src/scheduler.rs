@@ -0,0 +1,501 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageMap, Parameter,
+	traits::{Currency, ReservableCurrency, Get}, dispatch::Dispatchable
+};
+use sr_primitives::traits::{Member, EnsureOrigin, Saturating, Zero, One};
+use sr_primitives::weights::{Weight, GetDispatchInfo, WeighData, ClassifyDispatch, DispatchClass};
+use rstd::prelude::*;
+use rstd::result;
+use codec::{Encode, Decode};
+use system::ensure_signed;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+/// Maximum length in bytes of the id of a scheduled call
+pub const MAX_ID_LENGTH: usize = 32;
+
+/// Number of blocks after its period a periodic call is moved to when the blocks before are full
+pub const MAX_RESCHEDULE_DELAY: u32 = 16;
+
+/// The number of blocks between two dispatches of a periodic call and the number of dispatches.
+pub type Period<BlockNumber> = (BlockNumber, u32);
+
+/// Calls dispatched at a later block, once or periodically.
+///
+/// Signed origins schedule calls dispatched with their own signed origin, `ScheduleOrigin` schedules
+/// calls dispatched as Root. The two have separate slots at each block, so signed accounts can't fill
+/// a block Root schedules at. Other modules schedule calls with either origin through `Named`.
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Call: Parameter + Member + Dispatchable<Origin = Self::Origin> + GetDispatchInfo;
+	/// The origin allowed to schedule calls dispatched as Root
+	type ScheduleOrigin: EnsureOrigin<Self::Origin>;
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// Amount reserved from a signed account for each call it schedules until its last dispatch
+	type ScheduleDeposit: Get<BalanceOf<Self>>;
+	/// Maximum number of calls of signed origins scheduled at a block
+	type MaxScheduledPerBlock: Get<u32>;
+	/// Maximum number of calls dispatched as Root scheduled at a block
+	type MaxRootScheduledPerBlock: Get<u32>;
+}
+
+/// The origin a scheduled call is dispatched with.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+pub enum ScheduledOrigin<AccountId> {
+	Root,
+	Signed(AccountId),
+}
+
+/// A call scheduled at a block.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+pub struct Scheduled<Call, BlockNumber, AccountId, Balance> {
+	/// The id to cancel the call with, unique per origin
+	pub id: Option<Vec<u8>>,
+	pub origin: ScheduledOrigin<AccountId>,
+	pub call: Call,
+	/// The period and the number of dispatches left, this one included
+	pub periodic: Option<Period<BlockNumber>>,
+	/// The amount reserved from the signed origin, zero for Root
+	pub deposit: Balance,
+}
+
+/// Scheduling of calls by other modules.
+pub trait Named<AccountId, BlockNumber, Call> {
+	/// Schedule `call` at block `when` under `id`, repeated with `periodic` and dispatched with `origin`.
+	/// A signed origin reserves the deposit and uses the slots of signed origins, as if it scheduled the
+	/// call itself.
+	fn schedule_named(
+		id: Vec<u8>,
+		when: BlockNumber,
+		periodic: Option<Period<BlockNumber>>,
+		origin: ScheduledOrigin<AccountId>,
+		call: Call,
+	) -> result::Result<(), &'static str>;
+	/// Cancel the call `origin` scheduled under `id`
+	fn cancel_named(origin: ScheduledOrigin<AccountId>, id: Vec<u8>) -> result::Result<(), &'static str>;
+}
+
+impl<AccountId, BlockNumber, Call> Named<AccountId, BlockNumber, Call> for () {
+	fn schedule_named(
+		_: Vec<u8>,
+		_: BlockNumber,
+		_: Option<Period<BlockNumber>>,
+		_: ScheduledOrigin<AccountId>,
+		_: Call,
+	) -> result::Result<(), &'static str> {
+		Ok(())
+	}
+
+	fn cancel_named(_: ScheduledOrigin<AccountId>, _: Vec<u8>) -> result::Result<(), &'static str> {
+		Ok(())
+	}
+}
+
+/// The weight of scheduling a call: the weight of all its dispatches, charged to the extrinsic
+/// scheduling it. `on_initialize` dispatches the call without checking the weight of its block.
+pub struct ScheduleWeight;
+
+impl ScheduleWeight {
+	fn weight<BlockNumber, Call: GetDispatchInfo>(periodic: &Option<Period<BlockNumber>>, call: &Call) -> Weight {
+		let dispatches = periodic.as_ref().map_or(1, |&(_, count)| count);
+		call.get_dispatch_info().weight.saturating_mul(dispatches)
+	}
+}
+
+impl<'a, BlockNumber, Call: GetDispatchInfo> WeighData<(&'a BlockNumber, &'a Option<Period<BlockNumber>>, &'a Box<Call>)> for ScheduleWeight {
+	fn weigh_data(&self, (_, periodic, call): (&'a BlockNumber, &'a Option<Period<BlockNumber>>, &'a Box<Call>)) -> Weight {
+		Self::weight(periodic, call.as_ref())
+	}
+}
+
+impl<'a, BlockNumber, Call: GetDispatchInfo> WeighData<(&'a Vec<u8>, &'a BlockNumber, &'a Option<Period<BlockNumber>>, &'a Box<Call>)> for ScheduleWeight {
+	fn weigh_data(&self, (_, _, periodic, call): (&'a Vec<u8>, &'a BlockNumber, &'a Option<Period<BlockNumber>>, &'a Box<Call>)) -> Weight {
+		Self::weight(periodic, call.as_ref())
+	}
+}
+
+impl<T> ClassifyDispatch<T> for ScheduleWeight {
+	fn classify_dispatch(&self, _: T) -> DispatchClass {
+		DispatchClass::Normal
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Scheduler {
+		/// The calls scheduled at a block, in the order they are dispatched
+		pub Agenda get(agenda): map T::BlockNumber => Vec<Scheduled<<T as Trait>::Call, T::BlockNumber, T::AccountId, BalanceOf<T>>>;
+		/// The block of the next dispatch of a call scheduled with an id
+		pub ScheduledIds get(scheduled_at): map (ScheduledOrigin<T::AccountId>, Vec<u8>) => Option<T::BlockNumber>;
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::BlockNumber,
+	{
+		/// A call is scheduled. (when, id)
+		Scheduled(BlockNumber, Option<Vec<u8>>),
+		/// A scheduled call is cancelled. (when, id)
+		Cancelled(BlockNumber, Vec<u8>),
+		/// A scheduled call is dispatched, with whether it succeeded. (when, id, result)
+		Dispatched(BlockNumber, Option<Vec<u8>>, bool),
+		/// A periodic call is dropped because the blocks it could move to are full. (when, id)
+		Dropped(BlockNumber, Option<Vec<u8>>),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		fn on_initialize(now: T::BlockNumber) {
+			for scheduled in <Agenda<T>>::take(now) {
+				let origin: T::Origin = match scheduled.origin.clone() {
+					ScheduledOrigin::Root => system::RawOrigin::Root.into(),
+					ScheduledOrigin::Signed(who) => system::RawOrigin::Signed(who).into(),
+				};
+
+				let ok = match scheduled.call.clone().dispatch(origin) {
+					Ok(_) => true,
+					Err(e) => {
+						runtime_io::print(e);
+						false
+					},
+				};
+				Self::deposit_event(RawEvent::Dispatched(now, scheduled.id.clone(), ok));
+
+				match scheduled.periodic {
+					Some((period, count)) if count > 1 => {
+						match Self::free_block(now.saturating_add(period), &scheduled.origin) {
+							Some(next) => {
+								if let Some(ref id) = scheduled.id {
+									<ScheduledIds<T>>::insert(&(scheduled.origin.clone(), id.clone()), next);
+								}
+								<Agenda<T>>::mutate(next, |agenda| agenda.push(Scheduled {
+									periodic: Some((period, count - 1)),
+									..scheduled
+								}));
+							},
+							None => {
+								Self::deposit_event(RawEvent::Dropped(now, scheduled.id.clone()));
+								Self::remove(scheduled);
+							},
+						}
+					},
+					_ => Self::remove(scheduled),
+				}
+			}
+		}
+
+		/// Schedule `call` at block `when`, repeated with `periodic`
+		/// The call is dispatched as Root if the origin is `ScheduleOrigin`, otherwise as the sender
+		#[weight = ScheduleWeight]
+		pub fn schedule(origin, when: T::BlockNumber, periodic: Option<Period<T::BlockNumber>>, call: Box<<T as Trait>::Call>) {
+			let origin = Self::scheduled_origin(origin)?;
+
+			Self::do_schedule(None, when, periodic, origin, *call)?;
+		}
+
+		/// Schedule `call` under `id`, which cancels it with `cancel_named`
+		#[weight = ScheduleWeight]
+		pub fn schedule_named(origin, id: Vec<u8>, when: T::BlockNumber, periodic: Option<Period<T::BlockNumber>>, call: Box<<T as Trait>::Call>) {
+			let origin = Self::scheduled_origin(origin)?;
+
+			Self::do_schedule(Some(id), when, periodic, origin, *call)?;
+		}
+
+		/// Cancel the call the origin scheduled under `id`
+		pub fn cancel_named(origin, id: Vec<u8>) {
+			let origin = Self::scheduled_origin(origin)?;
+
+			Self::do_cancel(origin, id)?;
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	fn scheduled_origin(origin: T::Origin) -> result::Result<ScheduledOrigin<T::AccountId>, &'static str> {
+		match T::ScheduleOrigin::try_origin(origin) {
+			Ok(_) => Ok(ScheduledOrigin::Root),
+			Err(origin) => ensure_signed(origin).map(ScheduledOrigin::Signed),
+		}
+	}
+
+	fn do_schedule(
+		id: Option<Vec<u8>>,
+		when: T::BlockNumber,
+		periodic: Option<Period<T::BlockNumber>>,
+		origin: ScheduledOrigin<T::AccountId>,
+		call: <T as Trait>::Call,
+	) -> result::Result<(), &'static str> {
+		ensure!(when > <system::Module<T>>::block_number(), "Call must be scheduled in the future");
+		if let Some((period, count)) = periodic {
+			ensure!(!period.is_zero() && count > 0, "Invalid period");
+		}
+		if let Some(ref id) = id {
+			ensure!(id.len() <= MAX_ID_LENGTH, "Id too long");
+			ensure!(!<ScheduledIds<T>>::exists(&(origin.clone(), id.clone())), "Id already scheduled");
+		}
+		ensure!(Self::has_free_slot(when, &origin), "Too many calls scheduled at block");
+
+		let deposit = match origin {
+			ScheduledOrigin::Root => Zero::zero(),
+			ScheduledOrigin::Signed(ref who) => {
+				let deposit = T::ScheduleDeposit::get();
+				T::Currency::reserve(who, deposit)?;
+				deposit
+			},
+		};
+
+		if let Some(ref id) = id {
+			<ScheduledIds<T>>::insert(&(origin.clone(), id.clone()), when);
+		}
+		<Agenda<T>>::mutate(when, |agenda| agenda.push(Scheduled {
+			id: id.clone(),
+			origin,
+			call,
+			periodic,
+			deposit,
+		}));
+
+		Self::deposit_event(RawEvent::Scheduled(when, id));
+
+		Ok(())
+	}
+
+	fn do_cancel(origin: ScheduledOrigin<T::AccountId>, id: Vec<u8>) -> result::Result<(), &'static str> {
+		let key = (origin, id);
+		let when = Self::scheduled_at(&key);
+		ensure!(when.is_some(), "No call scheduled with id");
+		let when = when.unwrap();
+
+		<ScheduledIds<T>>::remove(&key);
+		let mut agenda = Self::agenda(when);
+		if let Some(index) = agenda.iter().position(|scheduled| scheduled.origin == key.0 && scheduled.id.as_ref() == Some(&key.1)) {
+			Self::remove(agenda.remove(index));
+		}
+		<Agenda<T>>::insert(when, agenda);
+
+		Self::deposit_event(RawEvent::Cancelled(when, key.1));
+
+		Ok(())
+	}
+
+	/// Whether a call of `origin` can be scheduled at block `when`
+	fn has_free_slot(when: T::BlockNumber, origin: &ScheduledOrigin<T::AccountId>) -> bool {
+		let is_root = *origin == ScheduledOrigin::Root;
+		let max = if is_root { T::MaxRootScheduledPerBlock::get() } else { T::MaxScheduledPerBlock::get() };
+		let scheduled = Self::agenda(when).iter()
+			.filter(|scheduled| (scheduled.origin == ScheduledOrigin::Root) == is_root)
+			.count();
+		scheduled < max as usize
+	}
+
+	/// The first block from `when` a call of `origin` can be scheduled at, at most
+	/// `MAX_RESCHEDULE_DELAY` blocks later
+	fn free_block(when: T::BlockNumber, origin: &ScheduledOrigin<T::AccountId>) -> Option<T::BlockNumber> {
+		let mut block = when;
+		for _ in 0..=MAX_RESCHEDULE_DELAY {
+			if Self::has_free_slot(block, origin) {
+				return Some(block);
+			}
+			block = block.saturating_add(One::one());
+		}
+		None
+	}
+
+	/// Forget a call which won't be dispatched again, returning its deposit
+	fn remove(scheduled: Scheduled<<T as Trait>::Call, T::BlockNumber, T::AccountId, BalanceOf<T>>) {
+		if let ScheduledOrigin::Signed(ref who) = scheduled.origin {
+			T::Currency::unreserve(who, scheduled.deposit);
+		}
+		if let Some(id) = scheduled.id {
+			<ScheduledIds<T>>::remove(&(scheduled.origin, id));
+		}
+	}
+}
+
+impl<T: Trait> Named<T::AccountId, T::BlockNumber, <T as Trait>::Call> for Module<T> {
+	fn schedule_named(
+		id: Vec<u8>,
+		when: T::BlockNumber,
+		periodic: Option<Period<T::BlockNumber>>,
+		origin: ScheduledOrigin<T::AccountId>,
+		call: <T as Trait>::Call,
+	) -> result::Result<(), &'static str> {
+		Self::do_schedule(Some(id), when, periodic, origin, call)
+	}
+
+	fn cancel_named(origin: ScheduledOrigin<T::AccountId>, id: Vec<u8>) -> result::Result<(), &'static str> {
+		Self::do_cancel(origin, id)
+	}
+}
+
+/// Tests for Scheduler module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop};
+	use sr_primitives::traits::OnInitialize;
+	use crate::kitties;
+	use crate::mock::{Test, Origin, Call, KittyModule, SchedulerModule, System, Balances};
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		crate::mock::new_test_ext(vec![(1, 100), (2, 100)])
+	}
+
+	fn run_to_block(n: u64) {
+		for block in System::block_number() + 1..=n {
+			System::set_block_number(block);
+			SchedulerModule::on_initialize(block);
+		}
+	}
+
+	#[test]
+	fn calls_are_dispatched_with_their_origin() {
+		with_externalities(&mut new_test_ext(), || {
+			let create = Box::new(Call::KittyModule(kitties::Call::create()));
+			let set_max_owned = Box::new(Call::KittyModule(kitties::Call::set_max_owned(3)));
+			assert_noop!(SchedulerModule::schedule(Origin::signed(1), 0, None, create.clone()), "Call must be scheduled in the future");
+
+			assert_ok!(SchedulerModule::schedule(Origin::signed(1), 2, None, create.clone()));
+			assert_eq!(Balances::reserved_balance(&1), 10);
+			// a signed origin can't schedule Root calls
+			assert_ok!(SchedulerModule::schedule(Origin::signed(2), 2, None, set_max_owned.clone()));
+			assert_noop!(SchedulerModule::schedule(Origin::signed(1), 2, None, create), "Too many calls scheduled at block");
+			// Root calls have their own slots
+			assert_ok!(SchedulerModule::schedule(Origin::ROOT, 2, None, Box::new(Call::KittyModule(kitties::Call::set_max_owned(0)))));
+			assert_ok!(SchedulerModule::schedule(Origin::ROOT, 3, None, set_max_owned));
+
+			run_to_block(2);
+			assert_eq!(KittyModule::kitty_owner(0), Some(1));
+			assert_eq!(KittyModule::max_owned(), 0);
+			assert_eq!(SchedulerModule::agenda(2), vec![]);
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_eq!(Balances::reserved_balance(&2), 0);
+
+			run_to_block(3);
+			assert_eq!(KittyModule::max_owned(), 3);
+		});
+	}
+
+	#[test]
+	fn periodic_calls_repeat_until_cancelled() {
+		with_externalities(&mut new_test_ext(), || {
+			let create = Box::new(Call::KittyModule(kitties::Call::create()));
+			assert_noop!(SchedulerModule::schedule(Origin::signed(1), 1, Some((0, 3)), create.clone()), "Invalid period");
+			assert_ok!(SchedulerModule::schedule_named(Origin::signed(1), b"daily".to_vec(), 1, Some((2, 3)), create.clone()));
+			assert_noop!(
+				SchedulerModule::schedule_named(Origin::signed(1), b"daily".to_vec(), 2, None, create.clone()),
+				"Id already scheduled"
+			);
+			// ids are per origin
+			assert_ok!(SchedulerModule::schedule_named(Origin::signed(2), b"daily".to_vec(), 1, Some((2, 3)), create));
+
+			run_to_block(3);
+			assert_eq!(KittyModule::owned_kitties_count(&1), 2);
+			assert_eq!(SchedulerModule::scheduled_at(&(ScheduledOrigin::Signed(1), b"daily".to_vec())), Some(5));
+
+			assert_noop!(SchedulerModule::cancel_named(Origin::signed(1), b"weekly".to_vec()), "No call scheduled with id");
+			assert_eq!(Balances::reserved_balance(&1), 10);
+			assert_ok!(SchedulerModule::cancel_named(Origin::signed(1), b"daily".to_vec()));
+			assert_eq!(Balances::reserved_balance(&1), 0);
+
+			run_to_block(7);
+			assert_eq!(KittyModule::owned_kitties_count(&1), 2);
+			// the last of its 3 dispatches is at block 5
+			assert_eq!(KittyModule::owned_kitties_count(&2), 3);
+			assert_eq!(SchedulerModule::scheduled_at(&(ScheduledOrigin::Signed(2), b"daily".to_vec())), None);
+			assert_eq!(Balances::reserved_balance(&2), 0);
+		});
+	}
+
+	#[test]
+	fn periodic_calls_move_to_the_next_free_block() {
+		with_externalities(&mut new_test_ext(), || {
+			let create = Box::new(Call::KittyModule(kitties::Call::create()));
+			assert_ok!(SchedulerModule::schedule_named(Origin::signed(1), b"daily".to_vec(), 2, Some((1, 2)), create.clone()));
+			assert_ok!(SchedulerModule::schedule(Origin::signed(2), 3, None, create.clone()));
+			assert_ok!(SchedulerModule::schedule(Origin::signed(2), 3, None, create));
+
+			run_to_block(2);
+			assert_eq!(SchedulerModule::agenda(3).len(), 2);
+			assert_eq!(SchedulerModule::scheduled_at(&(ScheduledOrigin::Signed(1), b"daily".to_vec())), Some(4));
+
+			run_to_block(4);
+			assert_eq!(KittyModule::owned_kitties_count(&1), 2);
+			assert_eq!(KittyModule::owned_kitties_count(&2), 2);
+			assert_eq!(Balances::reserved_balance(&1), 0);
+		});
+	}
+
+	#[test]
+	fn scheduling_is_charged_the_weight_of_the_dispatches() {
+		let create = Box::new(Call::KittyModule(kitties::Call::create()));
+		let weight = create.get_dispatch_info().weight;
+
+		assert_eq!(super::Call::<Test>::schedule(2, None, create.clone()).get_dispatch_info().weight, weight);
+		assert_eq!(
+			super::Call::<Test>::schedule_named(b"daily".to_vec(), 2, Some((1, 3)), create).get_dispatch_info().weight,
+			3 * weight
+		);
+	}
+
+	#[test]
+	fn periodic_calls_are_dropped_when_no_block_is_free() {
+		with_externalities(&mut new_test_ext(), || {
+			let call = Box::new(Call::KittyModule(kitties::Call::set_max_owned(0)));
+			assert_ok!(SchedulerModule::schedule_named(Origin::ROOT, b"daily".to_vec(), 2, Some((1, 2)), call.clone()));
+			for when in 3..=3 + MAX_RESCHEDULE_DELAY as u64 {
+				assert_ok!(SchedulerModule::schedule(Origin::ROOT, when, None, call.clone()));
+				assert_ok!(SchedulerModule::schedule(Origin::ROOT, when, None, call.clone()));
+			}
+			// signed calls don't use the Root slots
+			assert_ok!(SchedulerModule::schedule(Origin::signed(1), 3, None, call));
+
+			run_to_block(2);
+			assert_eq!(SchedulerModule::scheduled_at(&(ScheduledOrigin::Root, b"daily".to_vec())), None);
+			assert_eq!(SchedulerModule::agenda(3).len(), 3);
+		});
+	}
+
+	#[test]
+	fn kitty_listing_expires() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(KittyModule::create(Origin::signed(1)));
+			// the expiry is scheduled by the owner, who reserves the deposit
+			assert_ok!(KittyModule::ask_until(Origin::signed(1), 0, 10, 5));
+			assert_eq!(Balances::reserved_balance(&1), 10);
+			// asking again until a later block replaces the expiry
+			assert_ok!(KittyModule::ask_until(Origin::signed(1), 0, 20, 8));
+			assert_eq!(Balances::reserved_balance(&1), 10);
+
+			run_to_block(5);
+			assert_eq!(KittyModule::kitty_price(0), Some(20));
+
+			run_to_block(8);
+			assert_eq!(KittyModule::kitty_price(0), None);
+			assert_eq!(Balances::reserved_balance(&1), 0);
+
+			// the expiry uses the slots of signed origins
+			let set_max_owned = Box::new(Call::KittyModule(kitties::Call::set_max_owned(3)));
+			assert_ok!(SchedulerModule::schedule(Origin::signed(2), 12, None, set_max_owned.clone()));
+			assert_ok!(SchedulerModule::schedule(Origin::signed(2), 12, None, set_max_owned));
+			assert_noop!(KittyModule::ask_until(Origin::signed(1), 0, 20, 12), "Too many calls scheduled at block");
+
+			// a sale cancels the expiry
+			assert_ok!(KittyModule::ask_until(Origin::signed(1), 0, 20, 10));
+			assert_ok!(KittyModule::buy(Origin::signed(2), 0, 20));
+			assert_eq!(Balances::reserved_balance(&1), 0);
+			assert_ok!(KittyModule::ask(Origin::signed(2), 0, Some(30)));
+
+			run_to_block(10);
+			assert_eq!(KittyModule::kitty_price(0), Some(30));
+		});
+	}
+}
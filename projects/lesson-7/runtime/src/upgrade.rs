@@ -0,0 +1,117 @@
+use support::{decl_module, decl_event, ensure, traits::Get};
+use system::ensure_root;
+use rstd::prelude::*;
+use codec::{Encode, Decode};
+use primitives::storage::well_known_keys;
+use sr_primitives::traits::SignedExtension;
+use sr_primitives::transaction_validity::{TransactionValidity, TransactionValidityError, InvalidTransaction};
+use sr_primitives::weights::DispatchInfo;
+use crate::{AccountId, Call};
+
+/// Runtime upgrades declaring the spec name and version of the new runtime.
+///
+/// The runtime can't read the version of a wasm blob, so the caller declares it and only the
+/// declaration is compared with the running runtime. Nothing about the blob itself is verified: a
+/// wrong declaration goes through. The `upgrade-runtime` command of the node reads the version from
+/// the blob before it submits it.
+pub trait Trait: system::Trait {
+	type Event: From<Event> + Into<<Self as system::Trait>::Event>;
+}
+
+decl_event!(
+	pub enum Event {
+		/// The runtime code is replaced. (spec_version)
+		CodeUpdated(u32),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Replace the runtime code, declared to be a runtime of the same spec name and a higher spec
+		/// version
+		pub fn set_code(origin, code: Vec<u8>, spec_name: Vec<u8>, spec_version: u32) {
+			ensure_root(origin)?;
+
+			let current = T::Version::get();
+			ensure!(spec_name == current.spec_name.as_bytes(), "Spec name must not change");
+			ensure!(spec_version > current.spec_version, "Spec version must increase");
+
+			<system::Module<T>>::set_code(system::RawOrigin::Root.into(), code)?;
+
+			Self::deposit_event(Event::CodeUpdated(spec_version));
+		}
+	}
+}
+
+/// Whether `call` replaces the runtime code without going through `RuntimeUpgrade::set_code`.
+fn replaces_code(call: &Call) -> bool {
+	match call {
+		Call::System(system::Call::set_code(..)) => true,
+		Call::System(system::Call::set_storage(items)) => items.iter().any(|(key, _)| key == well_known_keys::CODE),
+		Call::System(system::Call::kill_storage(keys)) => keys.iter().any(|key| key == well_known_keys::CODE),
+		_ => false,
+	}
+}
+
+/// Rejects transactions of the sudo key replacing the runtime code with the calls of `System`, so
+/// the sudo key upgrades the runtime with `RuntimeUpgrade::set_code`.
+///
+/// Referenda and council motions dispatch their proposals as Root without a transaction, so they
+/// can still call `System::set_code`.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Eq, PartialEq)]
+pub struct CheckCodeUpgrade;
+
+impl SignedExtension for CheckCodeUpgrade {
+	type AccountId = AccountId;
+	type Call = Call;
+	type AdditionalSigned = ();
+	type Pre = ();
+
+	fn additional_signed(&self) -> rstd::result::Result<(), TransactionValidityError> {
+		Ok(())
+	}
+
+	fn validate(&self, _who: &AccountId, call: &Call, _info: DispatchInfo, _len: usize) -> TransactionValidity {
+		match call {
+			Call::Sudo(sudo::Call::sudo(call)) if replaces_code(call) => Err(InvalidTransaction::Call.into()),
+			_ => Ok(Default::default()),
+		}
+	}
+}
+
+/// Tests for RuntimeUpgrade module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::{Blake2Hasher, storage::well_known_keys};
+	use support::{assert_ok, assert_noop};
+	use crate::mock::{Test, Origin};
+
+	impl Trait for Test {
+		type Event = ();
+	}
+	type RuntimeUpgrade = Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		crate::mock::new_test_ext(vec![])
+	}
+
+	#[test]
+	fn set_code_checks_spec_name_and_version() {
+		with_externalities(&mut new_test_ext(), || {
+			let name = b"substrate-kitties".to_vec();
+
+			assert_noop!(RuntimeUpgrade::set_code(Origin::signed(1), vec![1], name.clone(), 5), "bad origin: expected to be a root origin");
+			assert_noop!(RuntimeUpgrade::set_code(Origin::ROOT, vec![1], b"other".to_vec(), 5), "Spec name must not change");
+			assert_noop!(RuntimeUpgrade::set_code(Origin::ROOT, vec![1], name.clone(), 4), "Spec version must increase");
+
+			assert_ok!(RuntimeUpgrade::set_code(Origin::ROOT, vec![1, 2, 3], name, 5));
+			assert_eq!(runtime_io::storage(well_known_keys::CODE), Some(vec![1, 2, 3]));
+		});
+	}
+}
@@ -0,0 +1,454 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageValue, StorageMap,
+	traits::{Currency, ReservableCurrency, Get}, dispatch::Dispatchable
+};
+use sr_primitives::traits::{StaticLookup, Convert, EnsureOrigin, Zero};
+use codec::Encode;
+use runtime_io::twox_128;
+use system::ensure_signed;
+use session::{OnSessionEnding, SelectInitialValidators, SessionIndex};
+use rstd::{prelude::*, result, marker::PhantomData};
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+/// A validator set driving the session module.
+///
+/// A stash account bonds a controller and reserves `ValidatorBond`, and `ApproveOrigin` approves
+/// it. The controller sets the session keys with `set_keys`, then joins the validators with
+/// `validate` and leaves them with `chill`. Changes apply from the session after the next one.
+pub trait Trait: system::Trait + session::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// Amount reserved from the stash while it is bonded
+	type ValidatorBond: Get<BalanceOf<Self>>;
+	/// Maximum number of validators
+	type MaxValidators: Get<u32>;
+	/// The origin allowed to approve stashes as validators and to remove validators
+	type ApproveOrigin: EnsureOrigin<Self::Origin>;
+	/// The authorities of a chain upgraded from fixed authorities, which become its first validators
+	type ExistingAuthorities: ExistingAuthorities<Self::AccountId, <Self as session::Trait>::Keys>;
+}
+
+/// The authorities of a chain without a validator set.
+pub trait ExistingAuthorities<AccountId, Keys> {
+	/// The account and the session keys of each authority
+	fn existing_authorities() -> Vec<(AccountId, Keys)>;
+}
+
+impl<AccountId, Keys> ExistingAuthorities<AccountId, Keys> for () {
+	fn existing_authorities() -> Vec<(AccountId, Keys)> {
+		Vec::new()
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as ValidatorSet {
+		/// The controller of a bonded stash
+		pub Bonded get(bonded): map T::AccountId => Option<T::AccountId>;
+		/// The stash of a controller
+		pub Ledger get(ledger): map T::AccountId => Option<T::AccountId>;
+		/// The amount reserved from a bonded stash
+		pub BondOf get(bond_of): map T::AccountId => Option<BalanceOf<T>>;
+		/// Whether `ApproveOrigin` approved a stash as a validator
+		pub Approved get(is_approved): map T::AccountId => bool;
+		/// Whether the controller of a stash set its session keys with `set_keys`
+		pub HasKeys get(has_keys): map T::AccountId => bool;
+		/// The stashes of the validators, in the order they joined
+		pub Validators get(validators): Vec<T::AccountId>;
+		/// Whether the validators changed since the end of the last session
+		Changed: bool;
+	}
+	add_extra_genesis {
+		// Bonded validators of the genesis block. (stash, controller)
+		config(validators): Vec<(T::AccountId, T::AccountId)>;
+		build(|config: &GenesisConfig<T>| {
+			for (stash, controller) in &config.validators {
+				<Module<T>>::do_bond(stash, controller)
+					.expect("Genesis validators are distinct and their stashes can pay the bond; qed");
+				// the session keys of the genesis validators are in the session genesis config
+				<Approved<T>>::insert(stash, true);
+				<HasKeys<T>>::insert(stash, true);
+				<Validators<T>>::mutate(|validators| validators.push(stash.clone()));
+			}
+		});
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+	{
+		/// A stash is bonded. (stash, controller)
+		Bonded(AccountId, AccountId),
+		/// A stash is unbonded and its bond unreserved. (stash)
+		Unbonded(AccountId),
+		/// A stash is approved as a validator. (stash)
+		Approved(AccountId),
+		/// The controller of a stash set its session keys. (stash)
+		KeysSet(AccountId),
+		/// A validator joins from the session after the next one. (stash)
+		ValidatorAdded(AccountId),
+		/// A validator leaves from the session after the next one. (stash)
+		ValidatorRemoved(AccountId),
+		/// The authorities of the chain are bonded as its first validators. (count)
+		AuthoritiesMigrated(u32),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		fn on_initialize(_now: T::BlockNumber) {
+			if !<Validators<T>>::exists() {
+				Self::migrate_authorities();
+			}
+		}
+
+		/// Bond the sender as a stash controlled by `controller`, reserving the validator bond
+		pub fn bond(origin, controller: <T::Lookup as StaticLookup>::Source) {
+			let stash = ensure_signed(origin)?;
+			let controller = T::Lookup::lookup(controller)?;
+
+			Self::do_bond(&stash, &controller)?;
+
+			Self::deposit_event(RawEvent::Bonded(stash, controller));
+		}
+
+		/// Unbond the sender stash and unreserve its bond
+		pub fn unbond(origin) {
+			let stash = ensure_signed(origin)?;
+
+			let controller = Self::bonded(&stash);
+			ensure!(controller.is_some(), "Stash is not bonded");
+			ensure!(!Self::validators().contains(&stash), "Validator must chill before unbonding");
+
+			// stashes bonded before their bond was recorded reserved `ValidatorBond`
+			let bond = <BondOf<T>>::take(&stash).unwrap_or_else(T::ValidatorBond::get);
+			T::Currency::unreserve(&stash, bond);
+			<Bonded<T>>::remove(&stash);
+			<Ledger<T>>::remove(controller.unwrap());
+			<Approved<T>>::remove(&stash);
+			<HasKeys<T>>::remove(&stash);
+
+			Self::deposit_event(RawEvent::Unbonded(stash));
+		}
+
+		/// Approve `stash` as a validator
+		pub fn approve(origin, stash: T::AccountId) {
+			T::ApproveOrigin::ensure_origin(origin)?;
+
+			<Approved<T>>::insert(&stash, true);
+
+			Self::deposit_event(RawEvent::Approved(stash));
+		}
+
+		/// Set the session keys of the stash of the sender controller, rotated from the session
+		/// after the next one
+		pub fn set_keys(origin, keys: <T as session::Trait>::Keys, proof: Vec<u8>) {
+			let controller = ensure_signed(origin)?;
+
+			let stash = Self::ledger(&controller);
+			ensure!(stash.is_some(), "Not a controller");
+			let stash = stash.unwrap();
+			ensure!(keys != Default::default(), "Session keys are empty");
+
+			session::Call::<T>::set_keys(keys, proof).dispatch(system::RawOrigin::Signed(controller).into())?;
+			<HasKeys<T>>::insert(&stash, true);
+
+			Self::deposit_event(RawEvent::KeysSet(stash));
+		}
+
+		/// Add the approved stash of the sender controller to the validators
+		/// The session keys must be set first with `set_keys`
+		pub fn validate(origin) {
+			let controller = ensure_signed(origin)?;
+
+			let stash = Self::ledger(&controller);
+			ensure!(stash.is_some(), "Not a controller");
+			let stash = stash.unwrap();
+			ensure!(Self::is_approved(&stash), "Stash is not approved");
+			ensure!(Self::has_keys(&stash), "Session keys are not set");
+
+			let mut validators = Self::validators();
+			ensure!(!validators.contains(&stash), "Already validating");
+			ensure!((validators.len() as u32) < T::MaxValidators::get(), "Too many validators");
+
+			validators.push(stash.clone());
+			<Validators<T>>::put(validators);
+			Changed::put(true);
+
+			Self::deposit_event(RawEvent::ValidatorAdded(stash));
+		}
+
+		/// Remove the stash of the sender controller from the validators
+		pub fn chill(origin) {
+			let controller = ensure_signed(origin)?;
+
+			let stash = Self::ledger(&controller);
+			ensure!(stash.is_some(), "Not a controller");
+
+			Self::remove_validator(&stash.unwrap())?;
+		}
+
+		/// Remove a validator, e.g. an offline one, and withdraw its approval
+		pub fn force_chill(origin, stash: T::AccountId) {
+			T::ApproveOrigin::ensure_origin(origin)?;
+
+			Self::remove_validator(&stash)?;
+			<Approved<T>>::remove(&stash);
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	fn do_bond(stash: &T::AccountId, controller: &T::AccountId) -> result::Result<(), &'static str> {
+		ensure!(!<Bonded<T>>::exists(stash), "Stash is already bonded");
+		ensure!(!<Ledger<T>>::exists(controller), "Controller is already paired");
+
+		let bond = T::ValidatorBond::get();
+		T::Currency::reserve(stash, bond)?;
+		<Bonded<T>>::insert(stash, controller);
+		<Ledger<T>>::insert(controller, stash);
+		<BondOf<T>>::insert(stash, bond);
+
+		Ok(())
+	}
+
+	/// Bond the authorities of a chain upgraded from fixed authorities as its first validators,
+	/// each account as its own stash and controller, and queue their keys for the next session
+	fn migrate_authorities() {
+		let mut stashes = Vec::new();
+		let mut queued_keys = Vec::new();
+
+		for (account, keys) in T::ExistingAuthorities::existing_authorities() {
+			// the authorities are approved and bonded without reserving anything
+			<Bonded<T>>::insert(&account, &account);
+			<Ledger<T>>::insert(&account, &account);
+			<BondOf<T>>::insert(&account, BalanceOf::<T>::zero());
+			<Approved<T>>::insert(&account, true);
+
+			let set_keys = session::Call::<T>::set_keys(keys.clone(), Vec::new());
+			if let Err(e) = set_keys.dispatch(system::RawOrigin::Signed(account.clone()).into()) {
+				runtime_io::print(e);
+				continue;
+			}
+			<HasKeys<T>>::insert(&account, true);
+
+			if let Some(validator_id) = <T as session::Trait>::ValidatorIdOf::convert(account.clone()) {
+				queued_keys.push((validator_id, keys));
+			}
+			stashes.push(account);
+		}
+
+		if stashes.is_empty() {
+			return;
+		}
+
+		// `Session` keeps its validators and queued keys in private storage. Without them the next
+		// session would hand BABE and GRANDPA an empty authority set
+		let session_validators = queued_keys.iter().map(|(validator_id, _)| validator_id.clone()).collect::<Vec<_>>();
+		runtime_io::set_storage(&twox_128(b"Session Validators"), &session_validators.encode());
+		runtime_io::set_storage(&twox_128(b"Session QueuedKeys"), &queued_keys.encode());
+
+		let count = stashes.len() as u32;
+		<Validators<T>>::put(stashes);
+
+		Self::deposit_event(RawEvent::AuthoritiesMigrated(count));
+	}
+
+	fn remove_validator(stash: &T::AccountId) -> result::Result<(), &'static str> {
+		let mut validators = Self::validators();
+		ensure!(validators.contains(stash), "Not validating");
+		ensure!(validators.len() > 1, "Cannot remove the last validator");
+
+		validators.retain(|validator| validator != stash);
+		<Validators<T>>::put(validators);
+		Changed::put(true);
+
+		Self::deposit_event(RawEvent::ValidatorRemoved(stash.clone()));
+
+		Ok(())
+	}
+}
+
+impl<T: Trait> OnSessionEnding<T::AccountId> for Module<T> {
+	fn on_session_ending(_ending: SessionIndex, _will_apply_at: SessionIndex) -> Option<Vec<T::AccountId>> {
+		if Changed::take() {
+			Some(Self::validators())
+		} else {
+			None
+		}
+	}
+}
+
+impl<T: Trait> SelectInitialValidators<T::AccountId> for Module<T> {
+	fn select_initial_validators() -> Option<Vec<T::AccountId>> {
+		Some(Self::validators())
+	}
+}
+
+/// The stash of a controller, which is the validator id of the session keys the controller sets.
+pub struct StashOf<T>(PhantomData<T>);
+
+impl<T: Trait> Convert<T::AccountId, Option<T::AccountId>> for StashOf<T> {
+	fn convert(controller: T::AccountId) -> Option<T::AccountId> {
+		<Module<T>>::ledger(&controller)
+	}
+}
+
+/// Tests for ValidatorSet module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop, parameter_types};
+	use sr_primitives::{Perbill, testing::UintAuthorityId, traits::OnInitialize};
+	use crate::mock::{Test, Origin, Balances};
+
+	pub struct TestAuthorities;
+	impl ExistingAuthorities<u64, UintAuthorityId> for TestAuthorities {
+		fn existing_authorities() -> Vec<(u64, UintAuthorityId)> {
+			vec![(1, UintAuthorityId(1)), (2, UintAuthorityId(2))]
+		}
+	}
+
+	parameter_types! {
+		pub const ValidatorBond: u64 = 50;
+		pub const MaxValidators: u32 = 3;
+		pub const Period: u64 = 1;
+		pub const Offset: u64 = 0;
+		pub const DisabledValidatorsThreshold: Perbill = Perbill::from_percent(33);
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Currency = balances::Module<Test>;
+		type ValidatorBond = ValidatorBond;
+		type MaxValidators = MaxValidators;
+		type ApproveOrigin = system::EnsureRoot<u64>;
+		type ExistingAuthorities = TestAuthorities;
+	}
+	impl session::Trait for Test {
+		type Event = ();
+		type ValidatorId = u64;
+		type ValidatorIdOf = StashOf<Test>;
+		type ShouldEndSession = session::PeriodicSessions<Period, Offset>;
+		type OnSessionEnding = ValidatorSet;
+		type SessionHandler = ();
+		type Keys = UintAuthorityId;
+		type DisabledValidatorsThreshold = DisabledValidatorsThreshold;
+		type SelectInitialValidators = ValidatorSet;
+	}
+	type ValidatorSet = Module<Test>;
+	type Session = session::Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = crate::mock::storage(vec![(1, 100), (2, 100), (3, 100), (4, 100), (5, 10)]);
+		GenesisConfig::<Test> {
+			validators: vec![(1, 11)],
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	/// Bond `stash` with controller `stash + 10`, approve it and set its keys
+	fn join(stash: u64) {
+		assert_ok!(ValidatorSet::bond(Origin::signed(stash), stash + 10));
+		assert_ok!(ValidatorSet::approve(Origin::ROOT, stash));
+		assert_ok!(ValidatorSet::set_keys(Origin::signed(stash + 10), UintAuthorityId(stash), vec![]));
+	}
+
+	#[test]
+	fn genesis_validators_are_bonded() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(ValidatorSet::validators(), vec![1]);
+			assert_eq!(ValidatorSet::select_initial_validators(), Some(vec![1]));
+			assert_eq!(StashOf::<Test>::convert(11), Some(1));
+			assert_eq!(Balances::reserved_balance(&1), 50);
+			assert_eq!(ValidatorSet::on_session_ending(0, 1), None);
+
+			// the validator set exists, so nothing is migrated
+			ValidatorSet::on_initialize(1);
+			assert_eq!(ValidatorSet::validators(), vec![1]);
+		});
+	}
+
+	#[test]
+	fn validators_join_and_leave_at_session_end() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(ValidatorSet::bond(Origin::signed(5), 15), "not enough free funds");
+			assert_noop!(ValidatorSet::bond(Origin::signed(2), 11), "Controller is already paired");
+			assert_ok!(ValidatorSet::bond(Origin::signed(2), 12));
+			assert_noop!(ValidatorSet::bond(Origin::signed(2), 22), "Stash is already bonded");
+
+			assert_noop!(ValidatorSet::validate(Origin::signed(2)), "Not a controller");
+			assert_noop!(ValidatorSet::validate(Origin::signed(12)), "Stash is not approved");
+			assert_noop!(ValidatorSet::approve(Origin::signed(1), 2), "bad origin: expected to be a root origin");
+			assert_ok!(ValidatorSet::approve(Origin::ROOT, 2));
+			assert_noop!(ValidatorSet::validate(Origin::signed(12)), "Session keys are not set");
+			assert_noop!(ValidatorSet::set_keys(Origin::signed(12), UintAuthorityId(0), vec![]), "Session keys are empty");
+			assert_ok!(ValidatorSet::set_keys(Origin::signed(12), UintAuthorityId(2), vec![]));
+
+			assert_ok!(ValidatorSet::validate(Origin::signed(12)));
+			assert_noop!(ValidatorSet::validate(Origin::signed(12)), "Already validating");
+			assert_eq!(ValidatorSet::on_session_ending(1, 2), Some(vec![1, 2]));
+			assert_eq!(ValidatorSet::on_session_ending(2, 3), None);
+
+			assert_noop!(ValidatorSet::unbond(Origin::signed(2)), "Validator must chill before unbonding");
+			assert_ok!(ValidatorSet::chill(Origin::signed(12)));
+			assert_ok!(ValidatorSet::unbond(Origin::signed(2)));
+			assert_eq!(Balances::reserved_balance(&2), 0);
+			assert!(!ValidatorSet::is_approved(&2));
+
+			assert_noop!(ValidatorSet::force_chill(Origin::signed(3), 1), "bad origin: expected to be a root origin");
+			assert_noop!(ValidatorSet::force_chill(Origin::ROOT, 1), "Cannot remove the last validator");
+			assert_eq!(ValidatorSet::on_session_ending(3, 4), Some(vec![1]));
+		});
+	}
+
+	#[test]
+	fn force_chill_withdraws_the_approval() {
+		with_externalities(&mut new_test_ext(), || {
+			join(2);
+			assert_ok!(ValidatorSet::validate(Origin::signed(12)));
+
+			assert_ok!(ValidatorSet::force_chill(Origin::ROOT, 2));
+			assert_noop!(ValidatorSet::validate(Origin::signed(12)), "Stash is not approved");
+		});
+	}
+
+	#[test]
+	fn validators_are_capped() {
+		with_externalities(&mut new_test_ext(), || {
+			for stash in 2..=4 {
+				join(stash);
+			}
+			assert_ok!(ValidatorSet::validate(Origin::signed(12)));
+			assert_ok!(ValidatorSet::validate(Origin::signed(13)));
+			assert_noop!(ValidatorSet::validate(Origin::signed(14)), "Too many validators");
+		});
+	}
+
+	#[test]
+	fn existing_authorities_become_validators() {
+		with_externalities(&mut crate::mock::new_test_ext(vec![(1, 100), (2, 100)]), || {
+			ValidatorSet::on_initialize(1);
+			assert_eq!(ValidatorSet::validators(), vec![1, 2]);
+			assert_eq!(ValidatorSet::bonded(&2), Some(2));
+			assert_eq!(StashOf::<Test>::convert(2), Some(2));
+			assert!(ValidatorSet::is_approved(&2) && ValidatorSet::has_keys(&2));
+			assert_eq!(Balances::reserved_balance(&2), 0);
+
+			// the next session keeps the authorities
+			Session::rotate_session();
+			assert_eq!(Session::validators(), vec![1, 2]);
+
+			// nothing was reserved, so nothing is unreserved
+			assert_ok!(Balances::reserve(&2, 10));
+			assert_ok!(ValidatorSet::chill(Origin::signed(2)));
+			assert_ok!(ValidatorSet::unbond(Origin::signed(2)));
+			assert_eq!(Balances::reserved_balance(&2), 10);
+		});
+	}
+}
@@ -0,0 +1,213 @@
+use support::{
+	decl_module, decl_storage, decl_event, ensure, StorageMap,
+	traits::{Currency, LockableCurrency, LockIdentifier, WithdrawReason, WithdrawReasons, Get}
+};
+use sr_primitives::traits::{StaticLookup, Convert, SimpleArithmetic, Bounded, One, Zero};
+use rstd::prelude::*;
+use codec::{Encode, Decode};
+use system::ensure_signed;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+
+const VESTING_ID: LockIdentifier = *b"vesting ";
+
+/// The vesting balance can pay transaction fees, so an account with nothing unlocked can `vest`
+fn lock_reasons() -> WithdrawReasons {
+	WithdrawReasons::except(WithdrawReason::TransactionPayment)
+}
+
+/// Balances unlocked linearly per block.
+///
+/// The vesting amount is locked in the account and the lock is lowered to the amount still vesting
+/// by `vest`, so the account can use the unlocked balance.
+pub trait Trait: system::Trait {
+	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
+	type Currency: LockableCurrency<Self::AccountId, Moment = Self::BlockNumber>;
+	type BlockNumberToBalance: Convert<Self::BlockNumber, BalanceOf<Self>>;
+	/// Minimum amount of a vested transfer
+	type MinVestedTransfer: Get<BalanceOf<Self>>;
+}
+
+/// A vesting schedule.
+#[cfg_attr(feature = "std", derive(Debug))]
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq)]
+pub struct VestingInfo<Balance, BlockNumber> {
+	/// Amount locked at the starting block
+	pub locked: Balance,
+	/// Amount unlocked each block after the starting block
+	pub per_block: Balance,
+	pub starting_block: BlockNumber,
+}
+
+impl<Balance: SimpleArithmetic + Copy, BlockNumber: SimpleArithmetic + Copy> VestingInfo<Balance, BlockNumber> {
+	/// Amount still locked at block `n`
+	pub fn locked_at<BlockNumberToBalance: Convert<BlockNumber, Balance>>(&self, n: BlockNumber) -> Balance {
+		let vested_blocks = BlockNumberToBalance::convert(n.saturating_sub(self.starting_block));
+		self.locked.saturating_sub(self.per_block.saturating_mul(vested_blocks))
+	}
+}
+
+decl_storage! {
+	trait Store for Module<T: Trait> as Vesting {
+		/// The vesting schedule of an account
+		pub Vesting get(vesting): map T::AccountId => Option<VestingInfo<BalanceOf<T>, T::BlockNumber>>;
+	}
+	add_extra_genesis {
+		// Vesting schedules of genesis balances. (who, start, length, liquid)
+		// The free balance above `liquid` unlocks linearly over `length` blocks from block `start`.
+		config(vesting): Vec<(T::AccountId, T::BlockNumber, T::BlockNumber, BalanceOf<T>)>;
+		build(|config: &GenesisConfig<T>| {
+			for &(ref who, start, length, liquid) in &config.vesting {
+				let locked = T::Currency::free_balance(who).saturating_sub(liquid);
+				let length = T::BlockNumberToBalance::convert(length).max(One::one());
+				// rounded up so the whole amount is unlocked after `length` blocks
+				let per_block = locked / length + if (locked % length).is_zero() { Zero::zero() } else { One::one() };
+
+				<Vesting<T>>::insert(who, VestingInfo { locked, per_block, starting_block: start });
+				T::Currency::set_lock(VESTING_ID, who, locked, T::BlockNumber::max_value(), lock_reasons());
+			}
+		});
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		<T as system::Trait>::AccountId,
+		Balance = BalanceOf<T>,
+	{
+		/// The lock of an account is lowered to the amount still vesting. (who, locked)
+		VestingUpdated(AccountId, Balance),
+		/// An account has no balance vesting anymore. (who)
+		VestingCompleted(AccountId),
+	}
+);
+
+decl_module! {
+	pub struct Module<T: Trait> for enum Call where origin: T::Origin {
+		fn deposit_event() = default;
+
+		/// Unlock the vested balance of the sender
+		pub fn vest(origin) {
+			let who = ensure_signed(origin)?;
+
+			ensure!(Self::vesting(&who).is_some(), "No vesting schedule");
+			Self::update_lock(who);
+		}
+
+		/// Unlock the vested balance of `target`
+		pub fn vest_other(origin, target: <T::Lookup as StaticLookup>::Source) {
+			ensure_signed(origin)?;
+			let target = T::Lookup::lookup(target)?;
+
+			ensure!(Self::vesting(&target).is_some(), "No vesting schedule");
+			Self::update_lock(target);
+		}
+
+		/// Transfer `schedule.locked` to `target`, vesting with `schedule`
+		pub fn vested_transfer(origin, target: <T::Lookup as StaticLookup>::Source, schedule: VestingInfo<BalanceOf<T>, T::BlockNumber>) {
+			let who = ensure_signed(origin)?;
+			let target = T::Lookup::lookup(target)?;
+
+			ensure!(schedule.locked >= T::MinVestedTransfer::get(), "Amount vested is below minimum");
+			ensure!(!schedule.per_block.is_zero(), "Vesting schedule never unlocks");
+			ensure!(!<Vesting<T>>::exists(&target), "Target already has a vesting schedule");
+
+			T::Currency::transfer(&who, &target, schedule.locked)?;
+			<Vesting<T>>::insert(&target, schedule);
+			Self::update_lock(target);
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Lower the lock of `who` to the amount still vesting, removing the schedule once all is vested
+	fn update_lock(who: T::AccountId) {
+		let vesting = match Self::vesting(&who) {
+			Some(vesting) => vesting,
+			None => return,
+		};
+
+		let locked = vesting.locked_at::<T::BlockNumberToBalance>(<system::Module<T>>::block_number());
+		if locked.is_zero() {
+			T::Currency::remove_lock(VESTING_ID, &who);
+			<Vesting<T>>::remove(&who);
+			Self::deposit_event(RawEvent::VestingCompleted(who));
+		} else {
+			T::Currency::set_lock(VESTING_ID, &who, locked, T::BlockNumber::max_value(), lock_reasons());
+			Self::deposit_event(RawEvent::VestingUpdated(who, locked));
+		}
+	}
+}
+
+/// Tests for Vesting module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::Blake2Hasher;
+	use support::{assert_ok, assert_noop, parameter_types};
+	use sr_primitives::traits::ConvertInto;
+	use crate::mock::{Test, Origin, Balances, System};
+
+	parameter_types! {
+		pub const MinVestedTransfer: u64 = 10;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type Currency = balances::Module<Test>;
+		type BlockNumberToBalance = ConvertInto;
+		type MinVestedTransfer = MinVestedTransfer;
+	}
+	type VestingModule = Module<Test>;
+
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = crate::mock::storage(vec![(1, 100), (2, 100), (3, 100)]);
+		GenesisConfig::<Test> {
+			// 1 vests 90 over 9 blocks from block 1
+			vesting: vec![(1, 1, 9, 10)],
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	#[test]
+	fn genesis_balance_vests_per_block() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(VestingModule::vesting(1), Some(VestingInfo { locked: 90, per_block: 10, starting_block: 1 }));
+			assert_ok!(Balances::transfer(Origin::signed(1), 2, 10));
+			assert_noop!(Balances::transfer(Origin::signed(1), 2, 10), "account liquidity restrictions prevent withdrawal");
+
+			System::set_block_number(4);
+			assert_ok!(VestingModule::vest(Origin::signed(1)));
+			assert_ok!(Balances::transfer(Origin::signed(1), 2, 30));
+			assert_noop!(Balances::transfer(Origin::signed(1), 2, 10), "account liquidity restrictions prevent withdrawal");
+
+			System::set_block_number(10);
+			assert_ok!(VestingModule::vest_other(Origin::signed(2), 1));
+			assert_eq!(VestingModule::vesting(1), None);
+			assert_ok!(Balances::transfer(Origin::signed(1), 2, 60));
+			assert_noop!(VestingModule::vest(Origin::signed(1)), "No vesting schedule");
+		});
+	}
+
+	#[test]
+	fn vested_transfer_locks_the_amount() {
+		with_externalities(&mut new_test_ext(), || {
+			let schedule = VestingInfo { locked: 50, per_block: 5, starting_block: 0 };
+			assert_noop!(
+				VestingModule::vested_transfer(Origin::signed(2), 3, VestingInfo { locked: 5, ..schedule }),
+				"Amount vested is below minimum"
+			);
+			assert_noop!(VestingModule::vested_transfer(Origin::signed(2), 1, schedule), "Target already has a vesting schedule");
+
+			assert_ok!(VestingModule::vested_transfer(Origin::signed(2), 3, schedule));
+			assert_eq!(Balances::free_balance(&3), 150);
+			assert_ok!(Balances::transfer(Origin::signed(3), 2, 100));
+			assert_noop!(Balances::transfer(Origin::signed(3), 2, 10), "account liquidity restrictions prevent withdrawal");
+
+			System::set_block_number(2);
+			assert_ok!(VestingModule::vest(Origin::signed(3)));
+			assert_ok!(Balances::transfer(Origin::signed(3), 2, 10));
+		});
+	}
+}
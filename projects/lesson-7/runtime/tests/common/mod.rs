@@ -0,0 +1,71 @@
+//! The genesis of the integration tests: the development chain without authorities.
+
+use primitives::{Pair, Blake2Hasher, sr25519};
+use sr_primitives::BuildStorage;
+use substrate_kitties_runtime::{
+	AccountId, Balance, BalancesConfig, GenesisConfig, IndicesConfig, SudoConfig, SystemConfig, KittiesConfig,
+	ChainParamsConfig, CouncilConfig, DemocracyConfig, VestingConfig, ContractsConfig, Permill, Treasury, WASM_BINARY,
+};
+
+/// Initial balance of the endowed accounts.
+pub const ENDOWMENT: Balance = 1 << 60;
+
+/// Initial balance of the treasury account, as on the development chain.
+pub const TREASURY_ENDOWMENT: Balance = 1 << 50;
+
+/// The account of a development seed, e.g. `Alice`.
+pub fn account(seed: &str) -> AccountId {
+	sr25519::Pair::from_string(&format!("//{}", seed), None)
+		.expect("static values are valid; qed")
+		.public()
+		.into()
+}
+
+/// The genesis of a chain endowing `endowed_accounts`, with `council` as the council.
+pub fn genesis(root_key: Option<AccountId>, endowed_accounts: Vec<AccountId>, council: Vec<AccountId>) -> GenesisConfig {
+	GenesisConfig {
+		system: Some(SystemConfig {
+			code: WASM_BINARY.to_vec(),
+			changes_trie_config: Default::default(),
+		}),
+		indices: Some(IndicesConfig {
+			ids: endowed_accounts.clone(),
+		}),
+		balances: Some(BalancesConfig {
+			balances: endowed_accounts.iter().cloned().map(|k| (k, ENDOWMENT))
+				.chain(Some((Treasury::account_id(), TREASURY_ENDOWMENT)))
+				.collect(),
+			vesting: vec![],
+		}),
+		sudo: root_key.map(|key| SudoConfig {
+			key,
+		}),
+		babe: None,
+		grandpa: None,
+		kitties: Some(KittiesConfig {
+			kitties: vec![],
+			sale_fee: Permill::zero(),
+			max_owned: 0,
+		}),
+		chain_params: Some(ChainParamsConfig::default()),
+		democracy: Some(DemocracyConfig::default()),
+		collective_Instance1: Some(CouncilConfig {
+			members: council,
+			phantom: Default::default(),
+		}),
+		validator_set: None,
+		session: None,
+		vesting: Some(VestingConfig {
+			vesting: vec![],
+		}),
+		contracts: Some(ContractsConfig {
+			current_schedule: Default::default(),
+			gas_price: 1_000,
+		}),
+	}
+}
+
+/// The externalities of the genesis block of `genesis`.
+pub fn new_test_ext(genesis: GenesisConfig) -> runtime_io::TestExternalities<Blake2Hasher> {
+	runtime_io::TestExternalities::new(genesis.build_storage().unwrap())
+}
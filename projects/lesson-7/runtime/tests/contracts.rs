@@ -0,0 +1,50 @@
+//! Breeding and trading kitties through the example kitty club contract.
+
+mod common;
+
+use codec::Encode;
+use contracts::ContractAddressFor;
+use sr_primitives::traits::Hash;
+use substrate_kitties_runtime::{AccountId, Balances, Call, Contracts, Kitties, Origin, Runtime, kitties};
+use common::{account, genesis, new_test_ext};
+
+#[test]
+fn contract_breeds_and_trades_kitties() {
+	let alice = account("Alice");
+	let bob = account("Bob");
+	let mut ext = new_test_ext(genesis(Some(alice.clone()), vec![alice.clone(), bob.clone()], vec![alice.clone()]));
+
+	runtime_io::with_externalities(&mut ext, || {
+		let gas_limit = 500_000;
+		let wasm = wabt::wat2wasm(include_str!("../../contracts/kitty_club.wat")).unwrap();
+		let code_hash = <Runtime as system::Trait>::Hashing::hash(&wasm);
+		assert!(Contracts::put_code(Origin::signed(alice.clone()), gas_limit, wasm).is_ok());
+
+		// anyone can breed the kitties of the club
+		let breed_prefix = Call::Kitties(kitties::Call::breed(0, 0)).encode()[..2].to_vec();
+		let club = contracts::SimpleAddressDeterminator::<Runtime>::contract_address_for(&code_hash, &breed_prefix, &alice);
+		assert!(Contracts::instantiate(Origin::signed(alice.clone()), 1 << 50, gas_limit, code_hash, breed_prefix).is_ok());
+
+		let call_club = |who: &AccountId, call: Call| {
+			Contracts::call(Origin::signed(who.clone()), club.clone().into(), 0, gas_limit, call.encode())
+		};
+
+		assert!(call_club(&alice, Call::Kitties(kitties::Call::create())).is_ok());
+		assert!(call_club(&alice, Call::Kitties(kitties::Call::create())).is_ok());
+		assert_eq!(Kitties::kitty_owner(0), Some(club.clone()));
+		assert_eq!(Kitties::kitty_owner(1), Some(club.clone()));
+
+		assert!(call_club(&bob, Call::Kitties(kitties::Call::breed(0, 1))).is_ok());
+		assert_eq!(Kitties::kitty_owner(2), Some(club.clone()));
+
+		// only the deployer trades them
+		assert!(call_club(&bob, Call::Kitties(kitties::Call::ask(2, Some(1_000)))).is_err());
+		assert_eq!(Kitties::kitty_price(2), None);
+		assert!(call_club(&alice, Call::Kitties(kitties::Call::ask(2, Some(1_000)))).is_ok());
+
+		let club_balance = Balances::free_balance(&club);
+		assert!(Kitties::buy(Origin::signed(bob.clone()), 2, 1_000).is_ok());
+		assert_eq!(Kitties::kitty_owner(2), Some(bob));
+		assert_eq!(Balances::free_balance(&club), club_balance + 1_000);
+	});
+}
@@ -0,0 +1,55 @@
+//! Fee estimates against the fees charged when applying extrinsics.
+
+mod common;
+
+use codec::Encode;
+use primitives::{Pair, blake2_256, sr25519};
+use sr_primitives::{generic::Era, traits::SignedExtension};
+use substrate_kitties_runtime::{
+	Address, Balances, BalancesCall, Call, Executive, Runtime, SignedExtra, UncheckedExtrinsic, fees, upgrade,
+};
+use common::{account, genesis, new_test_ext};
+
+/// `call` signed by the development seed `seed`, with the nonce `nonce` and no tip.
+fn signed(seed: &str, nonce: u32, call: Call) -> UncheckedExtrinsic {
+	let pair = sr25519::Pair::from_string(&format!("//{}", seed), None).unwrap();
+	let extra: SignedExtra = (
+		system::CheckVersion::<Runtime>::new(),
+		system::CheckGenesis::<Runtime>::new(),
+		system::CheckEra::<Runtime>::from(Era::Immortal),
+		system::CheckNonce::<Runtime>::from(nonce),
+		system::CheckWeight::<Runtime>::new(),
+		balances::TakeFees::<Runtime>::from(0),
+		upgrade::CheckCodeUpgrade,
+	);
+
+	let signature = (&call, &extra, &extra.additional_signed().unwrap()).using_encoded(|payload| if payload.len() > 256 {
+		pair.sign(&blake2_256(payload)[..])
+	} else {
+		pair.sign(payload)
+	});
+
+	UncheckedExtrinsic::new_signed(call, Address::from(account(seed)), signature.into(), extra)
+}
+
+#[test]
+fn query_info_is_the_fee_charged_when_applied() {
+	let alice = account("Alice");
+	let bob = account("Bob");
+	let mut ext = new_test_ext(genesis(Some(alice.clone()), vec![alice.clone(), bob.clone()], vec![alice.clone()]));
+
+	runtime_io::with_externalities(&mut ext, || {
+		system::Module::<Runtime>::set_block_number(1);
+
+		for (nonce, amount) in [1_000, 1_000_000_000_000].iter().enumerate() {
+			let xt = signed("Alice", nonce as u32, Call::Balances(BalancesCall::transfer(Address::from(bob.clone()), *amount)));
+			let len = xt.encode().len() as u32;
+			let estimate = fees::query_info(&xt, len);
+			assert!(estimate.partial_fee > 0);
+
+			let before = Balances::free_balance(&alice);
+			assert!(Executive::apply_extrinsic(xt).is_ok());
+			assert_eq!(before - Balances::free_balance(&alice) - amount, estimate.partial_fee);
+		}
+	});
+}
@@ -0,0 +1,55 @@
+//! Changing the kitties parameters through the council and a referendum.
+
+mod common;
+
+use sr_primitives::traits::{Hash, OnInitialize};
+use support::traits::Get;
+use democracy::{Vote, Conviction};
+use substrate_kitties_runtime::{
+	AccountId, Call, Council, Democracy, DemocracyCall, Kitties, LaunchPeriod, VotingPeriod, EnactmentPeriod, Origin,
+	Permill, Runtime, Sudo, kitties,
+};
+use common::{account, genesis, new_test_ext};
+
+fn run_to_block(n: u32) {
+	system::Module::<Runtime>::set_block_number(n);
+	Democracy::on_initialize(n);
+}
+
+#[test]
+fn council_referendum_changes_kitties_params() {
+	let alice = account("Alice");
+	let bob = account("Bob");
+	let charlie = account("Charlie");
+	let mut ext = new_test_ext(genesis(
+		None,
+		vec![alice.clone(), bob.clone(), charlie.clone()],
+		vec![alice.clone(), bob.clone(), charlie.clone()],
+	));
+
+	runtime_io::with_externalities(&mut ext, || {
+		let set_sale_fee = Call::Kitties(kitties::Call::set_sale_fee(Permill::from_percent(5)));
+		let external = Call::Democracy(DemocracyCall::external_propose_majority(Box::new(set_sale_fee)));
+		let external_hash = <Runtime as system::Trait>::Hashing::hash_of(&external);
+
+		// a council motion proposes the external referendum once a majority agrees
+		assert!(Council::propose(Origin::signed(alice.clone()), 2, Box::new(external)).is_ok());
+		assert!(Council::vote(Origin::signed(bob.clone()), external_hash, 0, true).is_ok());
+
+		// the external proposal becomes a referendum at the next launch period
+		let launch = LaunchPeriod::get();
+		run_to_block(launch);
+		assert!(Democracy::vote(Origin::signed(alice), 0, Vote { aye: true, conviction: Conviction::Locked1x }).is_ok());
+		assert!(Democracy::vote(Origin::signed(bob), 0, Vote { aye: false, conviction: Conviction::None }).is_ok());
+
+		// it passes at the end of the voting period and is enacted as Root after the enactment period
+		let end = launch + VotingPeriod::get();
+		run_to_block(end);
+		assert_eq!(Kitties::sale_fee(), Permill::zero());
+		run_to_block(end + EnactmentPeriod::get());
+		assert_eq!(Kitties::sale_fee(), Permill::from_percent(5));
+
+		// nobody holds the sudo key
+		assert_eq!(Sudo::key(), AccountId::default());
+	});
+}
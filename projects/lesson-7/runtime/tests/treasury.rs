@@ -0,0 +1,25 @@
+//! Sale fees paid into the treasury.
+
+mod common;
+
+use substrate_kitties_runtime::{Balances, Kitties, Origin, Permill, Treasury};
+use common::{ENDOWMENT, TREASURY_ENDOWMENT, account, genesis, new_test_ext};
+
+#[test]
+fn sale_fees_go_to_the_treasury() {
+	let alice = account("Alice");
+	let bob = account("Bob");
+	let mut ext = new_test_ext(genesis(Some(alice.clone()), vec![alice.clone(), bob.clone()], vec![alice.clone()]));
+
+	runtime_io::with_externalities(&mut ext, || {
+		assert_eq!(Treasury::pot(), TREASURY_ENDOWMENT);
+
+		assert!(Kitties::set_sale_fee(Origin::ROOT, Permill::from_percent(10)).is_ok());
+		assert!(Kitties::create(Origin::signed(alice.clone())).is_ok());
+		assert!(Kitties::ask(Origin::signed(alice.clone()), 0, Some(1_000)).is_ok());
+		assert!(Kitties::buy(Origin::signed(bob), 0, 1_000).is_ok());
+
+		assert_eq!(Treasury::pot(), TREASURY_ENDOWMENT + 100);
+		assert_eq!(Balances::free_balance(&alice), ENDOWMENT + 900);
+	});
+}
@@ -0,0 +1,24 @@
+//! Replacing the runtime code with the transactions of the sudo key.
+
+use primitives::storage::well_known_keys;
+use sr_primitives::{traits::SignedExtension, weights::GetDispatchInfo};
+use substrate_kitties_runtime::{AccountId, Call, RuntimeUpgradeCall, SudoCall, upgrade::CheckCodeUpgrade};
+
+/// Whether a transaction of the sudo key dispatching `call` is valid.
+fn sudo_is_valid(call: Call) -> bool {
+	let sudo = Call::Sudo(SudoCall::sudo(Box::new(call)));
+	CheckCodeUpgrade.validate(&AccountId::default(), &sudo, sudo.get_dispatch_info(), 0).is_ok()
+}
+
+#[test]
+fn sudo_replaces_the_code_only_with_runtime_upgrade() {
+	let code = well_known_keys::CODE.to_vec();
+
+	assert!(!sudo_is_valid(Call::System(system::Call::set_code(vec![1]))));
+	assert!(!sudo_is_valid(Call::System(system::Call::set_storage(vec![(code.clone(), vec![1])]))));
+	assert!(!sudo_is_valid(Call::System(system::Call::kill_storage(vec![code]))));
+
+	assert!(sudo_is_valid(Call::System(system::Call::set_storage(vec![(b"other".to_vec(), vec![1])]))));
+	assert!(sudo_is_valid(Call::System(system::Call::remark(vec![1]))));
+	assert!(sudo_is_valid(Call::RuntimeUpgrade(RuntimeUpgradeCall::set_code(vec![1], b"substrate-kitties".to_vec(), 100))));
+}
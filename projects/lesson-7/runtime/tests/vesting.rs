@@ -0,0 +1,56 @@
+//! Vesting of genesis allocations and paying for `vest`.
+
+mod common;
+
+use codec::Encode;
+use sr_primitives::{traits::SignedExtension, weights::GetDispatchInfo};
+use substrate_kitties_runtime::{AccountId, Balance, Balances, Call, GenesisConfig, Origin, Runtime, Vesting, vesting};
+use common::{ENDOWMENT, account, genesis, new_test_ext};
+
+/// The genesis of the development chain with `allocation` vesting. (account, balance, start, length, liquid)
+fn genesis_with_allocation(allocation: (AccountId, Balance, u32, u32, Balance)) -> GenesisConfig {
+	let (who, balance, start, length, liquid) = allocation;
+	let alice = account("Alice");
+	let mut genesis = genesis(Some(alice.clone()), vec![alice.clone()], vec![alice]);
+	genesis.balances.as_mut().unwrap().balances.push((who.clone(), balance));
+	genesis.vesting.as_mut().unwrap().vesting.push((who, start, length, liquid));
+	genesis
+}
+
+#[test]
+fn allocations_vest_from_genesis() {
+	let alice = account("Alice");
+	let dave = account("Dave");
+	let mut ext = new_test_ext(genesis_with_allocation((dave.clone(), 10_000, 0, 10, 1_000)));
+
+	runtime_io::with_externalities(&mut ext, || {
+		assert_eq!(Vesting::vesting(&dave), Some(vesting::VestingInfo { locked: 9_000, per_block: 900, starting_block: 0 }));
+
+		// only the liquid balance can be spent before the vesting starts
+		assert!(Balances::transfer(Origin::signed(dave.clone()), alice.clone(), 2_000).is_err());
+		assert!(Balances::transfer(Origin::signed(dave.clone()), alice.clone(), 1_000).is_ok());
+
+		system::Module::<Runtime>::set_block_number(2);
+		assert!(Vesting::vest(Origin::signed(dave.clone())).is_ok());
+		assert!(Balances::transfer(Origin::signed(dave), alice, 1_800).is_ok());
+	});
+}
+
+#[test]
+fn vest_is_paid_for_from_the_locked_balance() {
+	let dave = account("Dave");
+	// nothing is liquid, so the fee of `vest` comes out of the vesting balance
+	let mut ext = new_test_ext(genesis_with_allocation((dave.clone(), ENDOWMENT, 0, 10, 0)));
+
+	runtime_io::with_externalities(&mut ext, || {
+		system::Module::<Runtime>::set_block_number(2);
+
+		let vest = Call::Vesting(vesting::Call::vest());
+		let fees = balances::TakeFees::<Runtime>::from(0);
+		assert!(fees.pre_dispatch(&dave, &vest, vest.get_dispatch_info(), vest.encode().len()).is_ok());
+		assert!(Balances::free_balance(&dave) < ENDOWMENT);
+
+		assert!(Vesting::vest(Origin::signed(dave.clone())).is_ok());
+		assert!(Balances::transfer(Origin::signed(dave), account("Alice"), ENDOWMENT / 10).is_ok());
+	});
+}
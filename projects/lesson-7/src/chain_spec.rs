@@ -1,7 +1,10 @@
-use primitives::{Pair, Public};
+use std::{fs::File, path::PathBuf};
+use primitives::{Pair, Public, ed25519, sr25519, crypto::Ss58Codec};
+use serde::Deserialize;
 use substrate_kitties_runtime::{
-	AccountId, BabeConfig, BalancesConfig, GenesisConfig, GrandpaConfig,
-	SudoConfig, IndicesConfig, SystemConfig, WASM_BINARY, 
+	AccountId, BabeConfig, BalancesConfig, GenesisConfig, GrandpaConfig, SudoConfig, IndicesConfig,
+	SystemConfig, KittiesConfig, ChainParamsConfig, CouncilConfig, DemocracyConfig, SessionConfig, ValidatorSetConfig,
+	VestingConfig, ContractsConfig, Permill, Balance, BlockNumber, Treasury, WASM_BINARY, opaque::SessionKeys,
 };
 use babe_primitives::{AuthorityId as BabeId};
 use grandpa_primitives::{AuthorityId as GrandpaId};
@@ -22,23 +25,146 @@ pub enum Alternative {
 	Development,
 	/// Whatever the current runtime is, with simple Alice/Bob auths.
 	LocalTestnet,
+	/// Whatever the current runtime is, with the authorities read from a staging config file.
+	StagingTestnet(PathBuf),
+}
+
+/// Initial balance of the treasury account, which must stay above the existential deposit to
+/// receive fees.
+const TREASURY_ENDOWMENT: Balance = 1 << 50;
+
+/// Price of a unit of contract gas.
+const GAS_PRICE: Balance = 1_000;
+
+/// The staging config file used when none is given with `--chain=staging:<path>`.
+const DEFAULT_STAGING_CONFIG: &str = "staging.json";
+
+/// Authorities and accounts of a staging testnet, read from a JSON file.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StagingConfig {
+	name: String,
+	id: String,
+	authorities: Vec<StagingAuthority>,
+	/// The sudo key. Without it no account can use `Sudo` and the chain is governed by the council
+	/// and referenda only.
+	root_key: Option<String>,
+	endowed_accounts: Vec<String>,
+	/// Accounts endowed with their own balance, e.g. team and investor allocations.
+	#[serde(default)]
+	allocations: Vec<StagingAllocation>,
+	/// Initial council members.
+	#[serde(default)]
+	council: Vec<String>,
+	#[serde(default)]
+	boot_nodes: Vec<String>,
+	/// Slot duration in milliseconds, defaults to the runtime default.
+	slot_duration: Option<u64>,
+	/// Epoch length in slots, defaults to the runtime default.
+	epoch_duration: Option<u64>,
+	/// BABE `c` as `[numerator, denominator]`, defaults to the runtime default.
+	primary_probability: Option<(u64, u64)>,
+}
+
+impl StagingConfig {
+	fn chain_params(&self) -> ChainParamsConfig {
+		let defaults = ChainParamsConfig::default();
+		ChainParamsConfig {
+			slot_duration: self.slot_duration.unwrap_or(defaults.slot_duration),
+			epoch_duration: self.epoch_duration.unwrap_or(defaults.epoch_duration),
+			primary_probability: self.primary_probability.unwrap_or(defaults.primary_probability),
+		}
+	}
+}
+
+/// An account endowed with its own balance, vesting if `vesting` is given.
+#[derive(Deserialize)]
+struct StagingAllocation {
+	account: String,
+	balance: Balance,
+	vesting: Option<StagingVesting>,
+}
+
+/// A vesting schedule: the balance above `liquid` unlocks linearly over `length` blocks from
+/// block `start`.
+#[derive(Deserialize)]
+struct StagingVesting {
+	start: BlockNumber,
+	length: BlockNumber,
+	#[serde(default)]
+	liquid: Balance,
+}
+
+impl StagingAllocation {
+	fn allocation(&self) -> Result<Allocation, String> {
+		Ok(Allocation {
+			account: from_ss58::<AccountId>(&self.account)?,
+			balance: self.balance,
+			vesting: self.vesting.as_ref().map(|vesting| (vesting.start, vesting.length, vesting.liquid)),
+		})
+	}
+}
+
+/// An account endowed in genesis with its own balance and an optional vesting schedule
+/// `(start, length, liquid)`.
+#[derive(Clone)]
+pub(crate) struct Allocation {
+	pub account: AccountId,
+	pub balance: Balance,
+	pub vesting: Option<(BlockNumber, BlockNumber, Balance)>,
+}
+
+/// SS58 addresses of the keys of one staging authority.
+#[derive(Deserialize)]
+struct StagingAuthority {
+	stash: String,
+	controller: String,
+	grandpa: String,
+	babe: String,
+}
+
+impl StagingAuthority {
+	fn keys(&self) -> Result<(AccountId, AccountId, GrandpaId, BabeId), String> {
+		Ok((
+			from_ss58::<AccountId>(&self.stash)?,
+			from_ss58::<AccountId>(&self.controller)?,
+			from_ss58::<ed25519::Public>(&self.grandpa)?.into(),
+			from_ss58::<sr25519::Public>(&self.babe)?.into(),
+		))
+	}
+}
+
+fn from_ss58<T: Ss58Codec>(address: &str) -> Result<T, String> {
+	T::from_ss58check(address).map_err(|e| format!("Invalid address {}: {:?}", address, e))
 }
 
 /// Helper function to generate a crypto pair from seed
 pub fn get_from_seed<TPublic: Public>(seed: &str) -> <TPublic::Pair as Pair>::Public {
-	TPublic::Pair::from_string(&format!("//{}", seed), None)
+	get_from_suri::<TPublic>(&format!("//{}", seed))
 		.expect("static values are valid; qed")
-		.public()
+}
+
+/// Helper function to generate a crypto pair from a secret URI, e.g. a secret phrase
+pub fn get_from_suri<TPublic: Public>(suri: &str) -> Result<<TPublic::Pair as Pair>::Public, String> {
+	TPublic::Pair::from_string(suri, None)
+		.map(|pair| pair.public())
+		.map_err(|e| format!("Invalid secret URI: {:?}", e))
 }
 
 /// Helper function to generate stash, controller and session key from seed
 pub fn get_authority_keys_from_seed(seed: &str) -> (AccountId, AccountId, GrandpaId, BabeId) {
-	(
-		get_from_seed::<AccountId>(&format!("{}//stash", seed)),
-		get_from_seed::<AccountId>(seed),
-		get_from_seed::<GrandpaId>(seed),
-		get_from_seed::<BabeId>(seed),
-	)
+	get_authority_keys_from_suri(&format!("//{}", seed))
+		.expect("static values are valid; qed")
+}
+
+/// Helper function to generate stash, controller and session key from a secret URI
+pub fn get_authority_keys_from_suri(suri: &str) -> Result<(AccountId, AccountId, GrandpaId, BabeId), String> {
+	Ok((
+		get_from_suri::<AccountId>(&format!("{}//stash", suri))?,
+		get_from_suri::<AccountId>(suri)?,
+		get_from_suri::<GrandpaId>(suri)?,
+		get_from_suri::<BabeId>(suri)?,
+	))
 }
 
 impl Alternative {
@@ -51,7 +177,7 @@ impl Alternative {
 				|| testnet_genesis(vec![
 					get_authority_keys_from_seed("Alice"),
 				],
-				get_from_seed::<AccountId>("Alice"),
+				Some(get_from_seed::<AccountId>("Alice")),
 				vec![
 					get_from_seed::<AccountId>("Alice"),
 					get_from_seed::<AccountId>("Bob"),
@@ -66,6 +192,12 @@ impl Alternative {
 					get_from_seed::<AccountId>("Eve//stash"),
 					get_from_seed::<AccountId>("Ferdie//stash"),
 				],
+				vec![
+					get_from_seed::<AccountId>("Alice"),
+					get_from_seed::<AccountId>("Bob"),
+					get_from_seed::<AccountId>("Charlie"),
+				],
+				ChainParamsConfig::default(),
 				true),
 				vec![],
 				None,
@@ -80,7 +212,7 @@ impl Alternative {
 					get_authority_keys_from_seed("Alice"),
 					get_authority_keys_from_seed("Bob"),
 				], 
-				get_from_seed::<AccountId>("Alice"),
+				Some(get_from_seed::<AccountId>("Alice")),
 				vec![
 					get_from_seed::<AccountId>("Alice"),
 					get_from_seed::<AccountId>("Bob"),
@@ -95,6 +227,12 @@ impl Alternative {
 					get_from_seed::<AccountId>("Eve//stash"),
 					get_from_seed::<AccountId>("Ferdie//stash"),
 				],
+				vec![
+					get_from_seed::<AccountId>("Alice"),
+					get_from_seed::<AccountId>("Bob"),
+					get_from_seed::<AccountId>("Charlie"),
+				],
+				ChainParamsConfig::default(),
 				true),
 				vec![],
 				None,
@@ -102,6 +240,52 @@ impl Alternative {
 				None,
 				None
 			),
+			Alternative::StagingTestnet(path) => {
+				let file = File::open(&path)
+					.map_err(|e| format!("Error opening staging config {}: {}", path.display(), e))?;
+				let config: StagingConfig = serde_json::from_reader(file)
+					.map_err(|e| format!("Error parsing staging config {}: {}", path.display(), e))?;
+
+				let initial_authorities = config.authorities.iter()
+					.map(StagingAuthority::keys)
+					.collect::<Result<Vec<_>, _>>()?;
+				let root_key = match &config.root_key {
+					Some(address) => Some(from_ss58::<AccountId>(address)?),
+					None => None,
+				};
+				let ChainParamsConfig { slot_duration, epoch_duration, primary_probability } = config.chain_params();
+				let endowed_accounts = config.endowed_accounts.iter()
+					.map(|address| from_ss58::<AccountId>(address))
+					.collect::<Result<Vec<_>, _>>()?;
+				let council = config.council.iter()
+					.map(|address| from_ss58::<AccountId>(address))
+					.collect::<Result<Vec<_>, _>>()?;
+				let allocations = config.allocations.iter()
+					.map(StagingAllocation::allocation)
+					.collect::<Result<Vec<_>, _>>()?;
+				check_allocations(&endowed_accounts, &allocations)?;
+
+				ChainSpec::from_genesis(
+					&config.name,
+					&config.id,
+					move || with_allocations(
+						testnet_genesis(
+							initial_authorities.clone(),
+							root_key.clone(),
+							endowed_accounts.clone(),
+							council.clone(),
+							ChainParamsConfig { slot_duration, epoch_duration, primary_probability },
+							false,
+						),
+						&allocations,
+					),
+					config.boot_nodes,
+					None,
+					None,
+					None,
+					None
+				)
+			},
 		})
 	}
 
@@ -109,15 +293,19 @@ impl Alternative {
 		match s {
 			"dev" => Some(Alternative::Development),
 			"" | "local" => Some(Alternative::LocalTestnet),
+			"staging" => Some(Alternative::StagingTestnet(DEFAULT_STAGING_CONFIG.into())),
+			s if s.starts_with("staging:") => Some(Alternative::StagingTestnet(s["staging:".len()..].into())),
 			_ => None,
 		}
 	}
 }
 
-fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, GrandpaId, BabeId)>,
-	root_key: AccountId, 
+pub(crate) fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, GrandpaId, BabeId)>,
+	root_key: Option<AccountId>,
 	endowed_accounts: Vec<AccountId>,
-	_enable_println: bool) -> GenesisConfig {
+	council: Vec<AccountId>,
+	chain_params: ChainParamsConfig,
+	enable_println: bool) -> GenesisConfig {
 	GenesisConfig {
 		system: Some(SystemConfig {
 			code: WASM_BINARY.to_vec(),
@@ -127,17 +315,114 @@ fn testnet_genesis(initial_authorities: Vec<(AccountId, AccountId, GrandpaId, Ba
 			ids: endowed_accounts.clone(),
 		}),
 		balances: Some(BalancesConfig {
-			balances: endowed_accounts.iter().cloned().map(|k|(k, 1 << 60)).collect(),
+			balances: endowed_accounts.iter().cloned().map(|k|(k, 1 << 60))
+				.chain(Some((Treasury::account_id(), TREASURY_ENDOWMENT)))
+				.collect(),
 			vesting: vec![],
 		}),
-		sudo: Some(SudoConfig {
-			key: root_key,
+		sudo: root_key.map(|key| SudoConfig {
+			key,
 		}),
+		// the initial BABE and GRANDPA authorities are set from the session keys
 		babe: Some(BabeConfig {
-			authorities: initial_authorities.iter().map(|x| (x.3.clone(), 1)).collect(),
+			authorities: vec![],
 		}),
 		grandpa: Some(GrandpaConfig {
-			authorities: initial_authorities.iter().map(|x| (x.2.clone(), 1)).collect(),
+			authorities: vec![],
+		}),
+		kitties: Some(KittiesConfig {
+			kitties: vec![],
+			sale_fee: Permill::zero(),
+			max_owned: 0,
+		}),
+		chain_params: Some(chain_params),
+		democracy: Some(DemocracyConfig::default()),
+		collective_Instance1: Some(CouncilConfig {
+			members: council,
+			phantom: Default::default(),
+		}),
+		validator_set: Some(ValidatorSetConfig {
+			validators: initial_authorities.iter().map(|x| (x.0.clone(), x.1.clone())).collect(),
+		}),
+		session: Some(SessionConfig {
+			keys: initial_authorities.iter()
+				.map(|x| (x.0.clone(), SessionKeys { grandpa: x.2.clone(), babe: x.3.clone() }))
+				.collect(),
+		}),
+		vesting: Some(VestingConfig {
+			vesting: vec![],
+		}),
+		contracts: Some(ContractsConfig {
+			current_schedule: contracts::Schedule {
+				enable_println,
+				..Default::default()
+			},
+			gas_price: GAS_PRICE,
 		}),
 	}
 }
+
+/// Check that each allocated account is allocated once and is not an endowed account, whose
+/// balance would be replaced.
+fn check_allocations(endowed_accounts: &[AccountId], allocations: &[Allocation]) -> Result<(), String> {
+	for (index, allocation) in allocations.iter().enumerate() {
+		if endowed_accounts.contains(&allocation.account) {
+			return Err(format!("Account {} is both endowed and allocated", allocation.account));
+		}
+		if allocations[..index].iter().any(|other| other.account == allocation.account) {
+			return Err(format!("Account {} is allocated twice", allocation.account));
+		}
+	}
+	Ok(())
+}
+
+/// Add the balances and vesting schedules of `allocations` to `genesis`.
+fn with_allocations(mut genesis: GenesisConfig, allocations: &[Allocation]) -> GenesisConfig {
+	if let Some(balances) = genesis.balances.as_mut() {
+		balances.balances.extend(allocations.iter().map(|a| (a.account.clone(), a.balance)));
+	}
+	if let Some(vesting) = genesis.vesting.as_mut() {
+		vesting.vesting.extend(allocations.iter().filter_map(|a| {
+			a.vesting.map(|(start, length, liquid)| (a.account.clone(), start, length, liquid))
+		}));
+	}
+	genesis
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn allocations_are_added_to_the_genesis() {
+		let alice = get_from_seed::<AccountId>("Alice");
+		let dave = get_from_seed::<AccountId>("Dave");
+		let eve = get_from_seed::<AccountId>("Eve");
+		let endowed_accounts = vec![alice.clone(), get_from_seed::<AccountId>("Alice//stash")];
+		let allocations = vec![
+			Allocation { account: dave.clone(), balance: 10_000, vesting: Some((0, 10, 1_000)) },
+			Allocation { account: eve.clone(), balance: 5_000, vesting: None },
+		];
+		assert!(check_allocations(&endowed_accounts, &allocations).is_ok());
+		assert!(check_allocations(&endowed_accounts, &[Allocation { account: alice.clone(), balance: 1, vesting: None }]).is_err());
+		assert!(check_allocations(&endowed_accounts, &[allocations[1].clone(), allocations[1].clone()]).is_err());
+
+		let genesis = with_allocations(
+			testnet_genesis(
+				vec![get_authority_keys_from_seed("Alice")],
+				Some(alice.clone()),
+				endowed_accounts,
+				vec![alice],
+				ChainParamsConfig::default(),
+				false,
+			),
+			&allocations,
+		);
+
+		let balances = genesis.balances.unwrap().balances;
+		assert!(balances.contains(&(dave.clone(), 10_000)));
+		assert!(balances.contains(&(eve, 5_000)));
+		assert_eq!(genesis.vesting.unwrap().vesting, vec![(dave, 0, 10, 1_000)]);
+	}
+
+}
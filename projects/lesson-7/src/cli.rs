@@ -1,12 +1,81 @@
 use crate::service;
 use futures::{future, Future, sync::oneshot};
-use std::cell::RefCell;
-use tokio::runtime::Runtime;
+use std::{cell::RefCell, net::SocketAddr, path::PathBuf, sync::{Arc, mpsc}, thread, time::Duration};
+use tokio::{runtime::Runtime, timer::Timeout};
 pub use substrate_cli::{VersionInfo, IntoExit, error};
-use substrate_cli::{informant, parse_and_prepare, ParseAndPrepare, NoCustom};
+use substrate_cli::{informant, parse_and_prepare, impl_augment_clap, ParseAndPrepare, GetLogFilter};
 use substrate_service::{AbstractService, Roles as ServiceRoles};
+use structopt::StructOpt;
 use crate::chain_spec;
-use log::info;
+use crate::testnet::GenerateTestnetCmd;
+use crate::snapshot::{ExportKittiesCmd, ImportKittiesCmd};
+use crate::upgrade::UpgradeRuntimeCmd;
+use crate::dev_seal::DevSeal;
+use crate::metrics;
+use crate::kitties_index::KittiesIndex;
+use substrate_kitties_runtime::opaque::Block;
+use log::{info, warn};
+
+/// Custom subcommands.
+#[derive(Clone, Debug, StructOpt)]
+pub enum CustomSubcommands {
+	/// Generate validator keys and a raw chain spec for a new testnet.
+	#[structopt(
+		name = "generate-testnet",
+		about = "Generates fresh validator keys, their keystores and a raw chain spec using them."
+	)]
+	GenerateTestnet(GenerateTestnetCmd),
+
+	/// Export the kitties registry at a block.
+	#[structopt(name = "export-kitties", about = "Export the kitties registry at a block as JSON or CSV.")]
+	ExportKitties(ExportKittiesCmd),
+
+	/// Turn an exported kitties registry into the kitties genesis of a chain spec.
+	#[structopt(
+		name = "import-kitties",
+		about = "Write a chain spec whose genesis contains the kitties of an export-kitties file."
+	)]
+	ImportKitties(ImportKittiesCmd),
+
+	/// Replace the runtime of a running chain through `Sudo`.
+	#[structopt(
+		name = "upgrade-runtime",
+		about = "Submit a sudo runtime upgrade to a node, checking the spec name and version of the new runtime."
+	)]
+	UpgradeRuntime(UpgradeRuntimeCmd),
+}
+
+impl GetLogFilter for CustomSubcommands {
+	fn get_log_filter(&self) -> Option<String> {
+		None
+	}
+}
+
+/// Custom flags of the `run` command.
+#[derive(Clone, Debug, StructOpt)]
+pub struct RunParams {
+	/// Seal blocks without BABE and GRANDPA for local development, `instant` or `manual`.
+	///
+	/// `instant` authors a block as soon as a transaction enters the pool, `manual` authors a
+	/// block on each `engine_createBlock` RPC call. Sealed blocks are final right away.
+	#[structopt(long = "dev-seal", value_name = "MODE")]
+	pub dev_seal: Option<DevSeal>,
+
+	/// Serve Prometheus metrics of the node and of the kitties activity on `127.0.0.1:<PORT>`.
+	#[structopt(long = "metrics-port", value_name = "PORT")]
+	pub metrics_port: Option<u16>,
+
+	/// Index the kitties events of the best chain in a database at `PATH`, queried with the
+	/// `kitties_eventsOfKitty` and `kitties_tradesOf` RPCs.
+	#[structopt(long = "kitties-index", value_name = "PATH", parse(from_os_str))]
+	pub kitties_index: Option<PathBuf>,
+
+	/// Seconds to wait for the tasks of the node to finish on shutdown before dropping them.
+	#[structopt(long = "shutdown-timeout", value_name = "SECONDS", default_value = "30")]
+	pub shutdown_timeout: u64,
+}
+
+impl_augment_clap!(RunParams);
 
 /// Parse command line arguments into service configuration.
 pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()> where
@@ -14,9 +83,9 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 	T: Into<std::ffi::OsString> + Clone,
 	E: IntoExit,
 {
-	match parse_and_prepare::<NoCustom, NoCustom, _>(&version, "substrate-node", args) {
+	match parse_and_prepare::<CustomSubcommands, RunParams, _>(&version, "substrate-node", args) {
 		ParseAndPrepare::Run(cmd) => cmd.run::<(), _, _, _, _>(load_spec, exit,
-		|exit, _cli_args, _custom_args, config| {
+		|exit, _cli_args, custom_args, config| {
 			info!("{}", version.name);
 			info!("  version {}", config.full_version());
 			info!("  by {}, 2017, 2018", version.author);
@@ -24,16 +93,45 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 			info!("Node name: {}", config.name);
 			info!("Roles: {:?}", config.roles);
 			let runtime = Runtime::new().map_err(|e| format!("{:?}", e))?;
-			match config.roles {
-				ServiceRoles::LIGHT => run_until_exit(
-					runtime,
-				 	service::new_light(config).map_err(|e| format!("{:?}", e))?,
-					exit
-				),
+			let kitties_index = match &custom_args.kitties_index {
+				Some(path) => Some(Arc::new(KittiesIndex::open(path)?)),
+				None => None,
+			};
+			let metrics_addr = custom_args.metrics_port.map(|port| SocketAddr::from(([127, 0, 0, 1], port)));
+			let shutdown_timeout = Duration::from_secs(custom_args.shutdown_timeout);
+			let (shutdown, shutdown_exits) = service::shutdown_signals();
+			match (config.roles, custom_args.dev_seal) {
+				(_, Some(mode)) => {
+					info!("Dev seal: {:?}", mode);
+					run_until_exit(
+						runtime,
+						service::new_dev(config, mode, kitties_index, shutdown_exits).map_err(|e| format!("{:?}", e))?,
+						exit,
+						metrics_addr,
+						shutdown,
+						shutdown_timeout,
+					)
+				},
+				(ServiceRoles::LIGHT, None) => {
+					if kitties_index.is_some() {
+						warn!("Light nodes don't index kitties events, they don't have the storage of the blocks");
+					}
+					run_until_exit(
+						runtime,
+						service::new_light(config).map_err(|e| format!("{:?}", e))?,
+						exit,
+						metrics_addr,
+						shutdown,
+						shutdown_timeout,
+					)
+				},
 				_ => run_until_exit(
 					runtime,
-					service::new_full(config).map_err(|e| format!("{:?}", e))?,
-					exit
+					service::new_full(config, kitties_index, shutdown_exits).map_err(|e| format!("{:?}", e))?,
+					exit,
+					metrics_addr,
+					shutdown,
+					shutdown_timeout,
 				),
 			}.map_err(|e| format!("{:?}", e))
 		}),
@@ -45,16 +143,20 @@ pub fn run<I, T, E>(args: I, exit: E, version: VersionInfo) -> error::Result<()>
 		ParseAndPrepare::PurgeChain(cmd) => cmd.run(load_spec),
 		ParseAndPrepare::RevertChain(cmd) => cmd.run_with_builder::<(), _, _, _, _>(|config|
 			Ok(new_full_start!(config).0), load_spec),
-		ParseAndPrepare::CustomCommand(_) => Ok(())
+		ParseAndPrepare::CustomCommand(CustomSubcommands::GenerateTestnet(cmd)) => cmd.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::ExportKitties(cmd)) => cmd.run(&version),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::ImportKitties(cmd)) => cmd.run(),
+		ParseAndPrepare::CustomCommand(CustomSubcommands::UpgradeRuntime(cmd)) => cmd.run(),
 	}?;
 
 	Ok(())
 }
 
-fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
+/// Loads one of the built-in chain specs, or a JSON chain spec from the file at `id`.
+pub(crate) fn load_spec(id: &str) -> Result<Option<chain_spec::ChainSpec>, String> {
 	Ok(match chain_spec::Alternative::from(id) {
 		Some(spec) => Some(spec.load()?),
-		None => None,
+		None => Some(chain_spec::ChainSpec::from_json_file(std::path::PathBuf::from(id))?),
 	})
 }
 
@@ -62,22 +164,41 @@ fn run_until_exit<T, E>(
 	mut runtime: Runtime,
 	service: T,
 	e: E,
+	metrics_addr: Option<SocketAddr>,
+	shutdown: service::ShutdownSignals,
+	shutdown_timeout: Duration,
 ) -> error::Result<()>
 where
-	T: AbstractService,
+	T: AbstractService<Block = Block>,
 	E: IntoExit,
 {
 	let (exit_send, exit) = exit_future::signal();
 
 	let informant = informant::build(&service);
-	runtime.executor().spawn(exit.until(informant).map(|_| ()));
+	runtime.executor().spawn(exit.clone().until(informant).map(|_| ()));
+
+	if let Some(addr) = metrics_addr {
+		let metrics = metrics::build(&service, addr)?;
+		info!("Serving metrics on http://{}", addr);
+		runtime.executor().spawn(exit.until(metrics).map(|_| ()));
+	}
 
 	// we eagerly drop the service so that the internal exit future is fired,
 	// but we need to keep holding a reference to the global telemetry guard
 	let _telemetry = service.telemetry();
+	// the database is closed when the last reference to the client is dropped
+	let client = service.client();
 
 	let service_res = {
-		let exit = e.into_exit().map_err(|_| error::Error::Other("Exit future failed.".into()));
+		// stop the block authoring and the GRANDPA voter while the rest of the service is still running
+		let exit = e.into_exit()
+			.and_then(move |_| Timeout::new(shutdown.fire(), shutdown_timeout).then(|result| {
+				if result.is_err() {
+					warn!("Block authoring and GRANDPA voter still running after {:?}", shutdown_timeout);
+				}
+				Ok(())
+			}))
+			.map_err(|_| error::Error::Other("Exit future failed.".into()));
 		let service = service.map_err(|err| error::Error::Service(err));
 		let select = service.select(exit).map(|_| ()).map_err(|(err, _)| err);
 		runtime.block_on(select)
@@ -85,13 +206,28 @@ where
 
 	exit_send.fire();
 
-	// TODO [andre]: timeout this future #1318
-	let _ = runtime.shutdown_on_idle().wait();
+	let (idle_send, idle) = mpsc::channel();
+	thread::spawn(move || {
+		let _ = runtime.shutdown_on_idle().wait();
+		let _ = idle_send.send(());
+	});
+	if idle.recv_timeout(shutdown_timeout).is_err() {
+		warn!("Tasks still running after {:?}, dropping them", shutdown_timeout);
+	}
+
+	// the tasks are done and dropped their clients, unless they timed out
+	match Arc::try_unwrap(client) {
+		Ok(client) => {
+			drop(client);
+			info!("Database closed");
+		},
+		Err(_) => warn!("Client still in use, exiting without closing the database"),
+	}
 
 	service_res
 }
 
-// handles ctrl-c
+/// Handles Ctrl-C, and SIGTERM and SIGHUP with the `termination` feature of `ctrlc`.
 pub struct Exit;
 impl IntoExit for Exit {
 	type Exit = future::MapErr<oneshot::Receiver<()>, fn(oneshot::Canceled) -> ()>;
@@ -103,9 +239,10 @@ impl IntoExit for Exit {
 		ctrlc::set_handler(move || {
 			let exit_send = exit_send_cell.try_borrow_mut().expect("signal handler not reentrant; qed").take();
 			if let Some(exit_send) = exit_send {
+				info!("Exit signal received, shutting down");
 				exit_send.send(()).expect("Error sending exit notification");
 			}
-		}).expect("Error setting Ctrl-C handler");
+		}).expect("Error setting exit signal handler");
 
 		exit.map_err(drop)
 	}
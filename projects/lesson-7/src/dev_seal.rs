@@ -0,0 +1,239 @@
+//! Instant and manual block sealing for local development.
+//!
+//! Blocks are authored without BABE slots and imported as final, so no GRANDPA voter is needed.
+//! The runtime still checks the timestamp and BABE slot inherents, so they are provided from a
+//! clock which moves forward by one slot per block instead of following the wall clock.
+
+use std::{collections::HashMap, str::FromStr, sync::atomic::{AtomicU64, Ordering}, time::{Duration, SystemTime, UNIX_EPOCH}};
+use futures::{prelude::*, future, sync::{mpsc, oneshot}};
+use jsonrpc_core::{BoxFuture, Error as RpcError, ErrorCode};
+use jsonrpc_derive::rpc;
+use consensus_common::{
+	BlockImport, BlockImportParams, BlockOrigin, Environment, ForkChoiceStrategy, Proposer, SelectChain,
+	import_queue::{BasicQueue, CacheKeyId, Verifier},
+};
+use inherents::{InherentData, InherentDataProviders, InherentIdentifier, ProvideInherentData};
+use sr_primitives::{Justification, traits::Block as BlockT};
+use log::{info, warn};
+
+/// How the dev seal authors blocks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DevSeal {
+	/// Author a block as soon as a transaction enters the pool.
+	Instant,
+	/// Author a block on each `engine_createBlock` RPC call.
+	Manual,
+}
+
+impl FromStr for DevSeal {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, String> {
+		match s {
+			"instant" => Ok(DevSeal::Instant),
+			"manual" => Ok(DevSeal::Manual),
+			_ => Err(format!("Unknown dev seal mode {}, expected instant or manual", s)),
+		}
+	}
+}
+
+/// A request to seal a block, answered with the hash of the new block when sent by the RPC.
+pub struct SealCommand<Hash> {
+	reply: Option<oneshot::Sender<Result<Hash, String>>>,
+}
+
+/// Accepts every block. Blocks are imported as final since the dev seal node is the only author.
+pub struct DevSealVerifier;
+
+impl<B: BlockT> Verifier<B> for DevSealVerifier {
+	fn verify(
+		&mut self,
+		origin: BlockOrigin,
+		header: B::Header,
+		justification: Option<Justification>,
+		body: Option<Vec<B::Extrinsic>>,
+	) -> Result<(BlockImportParams<B>, Option<Vec<(CacheKeyId, Vec<u8>)>>), String> {
+		let import_block = BlockImportParams {
+			origin,
+			header,
+			justification,
+			post_digests: Vec::new(),
+			body,
+			finalized: true,
+			auxiliary: Vec::new(),
+			fork_choice: ForkChoiceStrategy::LongestChain,
+		};
+
+		Ok((import_block, None))
+	}
+}
+
+/// The import queue of a dev seal node.
+pub fn import_queue<B, I>(block_import: I) -> BasicQueue<B> where
+	B: BlockT,
+	I: BlockImport<B, Error = consensus_common::Error> + Send + Sync + 'static,
+{
+	BasicQueue::new(DevSealVerifier, Box::new(block_import), None, None)
+}
+
+/// Provides the timestamp and BABE slot inherents from a clock moving by at least one slot per block.
+pub struct SlotInherentDataProvider {
+	slot_duration: u64,
+	last_timestamp: AtomicU64,
+}
+
+impl SlotInherentDataProvider {
+	/// Create a provider for slots of `slot_duration` milliseconds.
+	pub fn new(slot_duration: u64) -> Self {
+		SlotInherentDataProvider {
+			slot_duration,
+			last_timestamp: AtomicU64::new(0),
+		}
+	}
+}
+
+impl ProvideInherentData for SlotInherentDataProvider {
+	fn inherent_identifier(&self) -> &'static InherentIdentifier {
+		&babe_runtime::timestamp::INHERENT_IDENTIFIER
+	}
+
+	fn provide_inherent_data(&self, inherent_data: &mut InherentData) -> Result<(), inherents::Error> {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH)
+			.map_err(|e| inherents::Error::from(format!("Current time is before unix epoch: {:?}", e)))?;
+		let now = now.as_millis() as u64;
+
+		let last = self.last_timestamp.load(Ordering::SeqCst);
+		let timestamp = now.max(last + self.slot_duration);
+		self.last_timestamp.store(timestamp, Ordering::SeqCst);
+
+		inherent_data.put_data(babe_runtime::timestamp::INHERENT_IDENTIFIER, &timestamp)?;
+		inherent_data.put_data(babe_runtime::INHERENT_IDENTIFIER, &(timestamp / self.slot_duration))
+	}
+
+	fn error_to_string(&self, error: &[u8]) -> Option<String> {
+		Some(format!("{:?}", error))
+	}
+}
+
+fn seal_block<B, E, I, S>(
+	env: &mut E,
+	block_import: &mut I,
+	select_chain: &S,
+	inherent_data_providers: &InherentDataProviders,
+) -> Result<B::Hash, String> where
+	B: BlockT,
+	E: Environment<B>,
+	E::Error: std::fmt::Debug,
+	<E::Proposer as Proposer<B>>::Error: std::fmt::Debug,
+	I: BlockImport<B>,
+	I::Error: std::fmt::Debug,
+	S: SelectChain<B>,
+{
+	let parent = select_chain.best_chain()
+		.map_err(|e| format!("Error getting best block: {:?}", e))?;
+	let mut proposer = env.init(&parent)
+		.map_err(|e| format!("Error creating proposer: {:?}", e))?;
+	let inherent_data = inherent_data_providers.create_inherent_data()
+		.map_err(|e| format!("Error creating inherent data: {:?}", e))?;
+
+	let block = proposer.propose(inherent_data, Default::default(), Duration::from_secs(2))
+		.into_future()
+		.wait()
+		.map_err(|e| format!("Error proposing block: {:?}", e))?;
+
+	let (header, body) = block.deconstruct();
+	let hash = header.hash();
+	let import_block = BlockImportParams {
+		origin: BlockOrigin::Own,
+		header,
+		justification: None,
+		post_digests: Vec::new(),
+		body: Some(body),
+		finalized: true,
+		auxiliary: Vec::new(),
+		fork_choice: ForkChoiceStrategy::LongestChain,
+	};
+
+	block_import.import_block(import_block, HashMap::new())
+		.map_err(|e| format!("Error importing block: {:?}", e))?;
+
+	Ok(hash)
+}
+
+/// The task sealing a block for each command, and for each transaction pool import when `pool_imports` is given.
+pub fn run<B, E, I, S, N>(
+	mut env: E,
+	mut block_import: I,
+	select_chain: S,
+	inherent_data_providers: InherentDataProviders,
+	commands: mpsc::UnboundedReceiver<SealCommand<B::Hash>>,
+	pool_imports: Option<N>,
+) -> impl Future<Item = (), Error = ()> where
+	B: BlockT,
+	E: Environment<B>,
+	E::Error: std::fmt::Debug,
+	<E::Proposer as Proposer<B>>::Error: std::fmt::Debug,
+	I: BlockImport<B>,
+	I::Error: std::fmt::Debug,
+	S: SelectChain<B>,
+	N: Stream<Item = (), Error = ()> + Send + 'static,
+{
+	let pool_imports: Box<dyn Stream<Item = SealCommand<B::Hash>, Error = ()> + Send> = match pool_imports {
+		Some(pool_imports) => Box::new(pool_imports.map(|_| SealCommand { reply: None })),
+		None => Box::new(futures::stream::empty()),
+	};
+
+	commands.select(pool_imports).for_each(move |command| {
+		let result = seal_block(&mut env, &mut block_import, &select_chain, &inherent_data_providers);
+		match (&result, command.reply) {
+			(Ok(hash), None) => info!("Sealed block {}", hash),
+			(Err(e), None) => warn!("Failed to seal block: {}", e),
+			(_, Some(reply)) => {
+				let _ = reply.send(result);
+			},
+		}
+		Ok(())
+	})
+}
+
+/// Dev seal RPC methods.
+#[rpc]
+pub trait DevSealApi<Hash> {
+	/// Author and finalize a block with the transactions in the pool. Returns the hash of the new block.
+	#[rpc(name = "engine_createBlock")]
+	fn create_block(&self) -> BoxFuture<Hash>;
+}
+
+/// Implementation of the dev seal RPC, forwarding the requests to the sealing task.
+pub struct DevSealRpc<Hash> {
+	commands: mpsc::UnboundedSender<SealCommand<Hash>>,
+}
+
+impl<Hash> DevSealRpc<Hash> {
+	/// Create the RPC sending its commands to the task started by `run`.
+	pub fn new(commands: mpsc::UnboundedSender<SealCommand<Hash>>) -> Self {
+		DevSealRpc { commands }
+	}
+}
+
+fn internal_error(message: String) -> RpcError {
+	RpcError {
+		code: ErrorCode::InternalError,
+		message,
+		data: None,
+	}
+}
+
+impl<Hash: Send + 'static> DevSealApi<Hash> for DevSealRpc<Hash> {
+	fn create_block(&self) -> BoxFuture<Hash> {
+		let (reply, result) = oneshot::channel();
+		if self.commands.unbounded_send(SealCommand { reply: Some(reply) }).is_err() {
+			return Box::new(future::err(internal_error("Dev seal task is not running".into())));
+		}
+
+		Box::new(result
+			.map_err(|_| internal_error("Dev seal task stopped".into()))
+			.and_then(|result| result.map_err(internal_error)))
+	}
+}
+
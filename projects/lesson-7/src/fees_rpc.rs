@@ -0,0 +1,55 @@
+//! Fee estimates RPC, asking the runtime for the fee of an extrinsic before it is submitted.
+
+use std::sync::Arc;
+use codec::Decode;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use primitives::Bytes;
+use sr_primitives::traits::{Block as BlockT, ProvideRuntimeApi};
+use substrate_client::blockchain::HeaderBackend;
+use substrate_kitties_runtime::{Hash, fees::{FeeInfo, FeesApi as FeesRuntimeApi}, opaque::{Block, BlockId}};
+
+/// Fee estimates RPC methods.
+#[rpc]
+pub trait FeesApi<BlockHash> {
+	/// The weight and the fee without tip of an encoded extrinsic at a block, the best block if
+	/// `at` is not given.
+	#[rpc(name = "payment_queryInfo")]
+	fn query_info(&self, encoded_xt: Bytes, at: Option<BlockHash>) -> Result<FeeInfo>;
+}
+
+/// Implementation of the fee estimates RPC.
+pub struct Fees<C> {
+	client: Arc<C>,
+}
+
+impl<C> Fees<C> {
+	/// Create the RPC calling the runtime of `client`.
+	pub fn new(client: Arc<C>) -> Self {
+		Fees { client }
+	}
+}
+
+fn error(message: String) -> RpcError {
+	RpcError {
+		code: ErrorCode::InternalError,
+		message,
+		data: None,
+	}
+}
+
+impl<C> FeesApi<Hash> for Fees<C> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: FeesRuntimeApi<Block>,
+{
+	fn query_info(&self, encoded_xt: Bytes, at: Option<Hash>) -> Result<FeeInfo> {
+		let at = BlockId::Hash(at.unwrap_or_else(|| self.client.info().best_hash));
+
+		let len = encoded_xt.len() as u32;
+		let uxt = <Block as BlockT>::Extrinsic::decode(&mut &*encoded_xt)
+			.map_err(|e| error(format!("Invalid extrinsic: {:?}", e)))?;
+
+		self.client.runtime_api().query_info(&at, uxt, len)
+			.map_err(|e| error(format!("Error querying fees at {:?}: {:?}", at, e)))
+	}
+}
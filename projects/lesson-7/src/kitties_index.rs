@@ -0,0 +1,542 @@
+//! An index of the kitties events of the canonical chain, kept in a RocksDB database next to the node.
+//!
+//! Each new best block is indexed with its `Created`, `Transferred`, `Ask` and `Sold` events. When the
+//! best chain switches to another fork, the blocks of the old fork above the common ancestor are removed
+//! from the index before the blocks of the new fork are added, so the index always follows the best chain.
+
+use std::{path::Path, sync::Arc};
+use codec::{Encode, Decode};
+use futures::{Async, Future, Poll, Stream, future, task};
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result as RpcResult};
+use jsonrpc_derive::rpc;
+use kvdb::{DBTransaction, KeyValueDB};
+use kvdb_rocksdb::{Database, DatabaseConfig};
+use log::{info, warn};
+use primitives::Blake2Hasher;
+use serde::{Serialize, Deserialize};
+use sr_primitives::traits::{Header as HeaderT, ProvideRuntimeApi};
+use substrate_client::{Client, CallExecutor, BlockchainEvents, backend::Backend};
+use substrate_kitties_runtime::{
+	AccountId, Balance, BlockNumber, Hash, kitties, kitties_api::{self, KittiesApi}, opaque::{Block, BlockId, Header},
+};
+use crate::kitties_state::KittyIndex;
+
+const COL_META: Option<u32> = Some(0);
+/// Indexed events, keyed by `event_key`.
+const COL_EVENTS: Option<u32> = Some(1);
+/// `kitty_id ++ event_key` of each event of a kitty.
+const COL_BY_KITTY: Option<u32> = Some(2);
+/// `account ++ event_key` of each sale an account took part in.
+const COL_BY_ACCOUNT: Option<u32> = Some(3);
+const NUM_COLUMNS: u32 = 4;
+
+const META_BEST: &[u8] = b"best";
+const META_CANON_PREFIX: &[u8] = b"canon";
+
+/// A kitties event of the index.
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum KittyEvent {
+	#[serde(rename_all = "camelCase")]
+	Created { owner: AccountId, kitty_id: KittyIndex },
+	#[serde(rename_all = "camelCase")]
+	Transferred { from: AccountId, to: AccountId, kitty_id: KittyIndex },
+	/// None means not for sale anymore.
+	#[serde(rename_all = "camelCase")]
+	Ask { owner: AccountId, kitty_id: KittyIndex, price: Option<Balance> },
+	#[serde(rename_all = "camelCase")]
+	Sold { from: AccountId, to: AccountId, kitty_id: KittyIndex, price: Balance },
+}
+
+impl KittyEvent {
+	fn from_runtime(event: &kitties_api::KittyEvent) -> Option<Self> {
+		Some(match event {
+			kitties::RawEvent::Created(owner, kitty_id) =>
+				KittyEvent::Created { owner: owner.clone(), kitty_id: *kitty_id },
+			kitties::RawEvent::Transferred(from, to, kitty_id) =>
+				KittyEvent::Transferred { from: from.clone(), to: to.clone(), kitty_id: *kitty_id },
+			kitties::RawEvent::Ask(owner, kitty_id, price) =>
+				KittyEvent::Ask { owner: owner.clone(), kitty_id: *kitty_id, price: *price },
+			kitties::RawEvent::Sold(from, to, kitty_id, price) =>
+				KittyEvent::Sold { from: from.clone(), to: to.clone(), kitty_id: *kitty_id, price: *price },
+			_ => return None,
+		})
+	}
+
+	fn kitty_id(&self) -> KittyIndex {
+		match self {
+			KittyEvent::Created { kitty_id, .. } |
+			KittyEvent::Transferred { kitty_id, .. } |
+			KittyEvent::Ask { kitty_id, .. } |
+			KittyEvent::Sold { kitty_id, .. } => *kitty_id,
+		}
+	}
+
+	/// The buyer and the seller of a sale.
+	fn traders(&self) -> Vec<&AccountId> {
+		match self {
+			KittyEvent::Sold { from, to, .. } => vec![from, to],
+			_ => Vec::new(),
+		}
+	}
+}
+
+/// An event of the index with its position in the chain.
+#[derive(Serialize, Deserialize, Encode, Decode, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexedEvent {
+	pub block_number: BlockNumber,
+	pub block_hash: Hash,
+	/// Index of the event in the events of the block.
+	pub event_index: u32,
+	pub event: KittyEvent,
+}
+
+fn block_key(number: BlockNumber, hash: &Hash) -> Vec<u8> {
+	let mut key = number.to_be_bytes().to_vec();
+	key.extend_from_slice(hash.as_ref());
+	key
+}
+
+fn event_key(number: BlockNumber, hash: &Hash, event_index: u32) -> Vec<u8> {
+	let mut key = block_key(number, hash);
+	key.extend_from_slice(&event_index.to_be_bytes());
+	key
+}
+
+fn canon_key(number: BlockNumber) -> Vec<u8> {
+	let mut key = META_CANON_PREFIX.to_vec();
+	key.extend_from_slice(&number.to_be_bytes());
+	key
+}
+
+fn prefixed(prefix: &[u8], key: &[u8]) -> Vec<u8> {
+	let mut prefixed = prefix.to_vec();
+	prefixed.extend_from_slice(key);
+	prefixed
+}
+
+fn db_error(e: std::io::Error) -> String {
+	format!("Kitties index database error: {}", e)
+}
+
+/// The kitties events index.
+pub struct KittiesIndex {
+	db: Arc<dyn KeyValueDB>,
+}
+
+impl KittiesIndex {
+	/// Open or create the index at `path`.
+	pub fn open(path: &Path) -> Result<Self, String> {
+		let config = DatabaseConfig::with_columns(Some(NUM_COLUMNS));
+		let path = path.to_str().ok_or_else(|| format!("Invalid kitties index path {}", path.display()))?;
+		let db = Database::open(&config, path).map_err(db_error)?;
+
+		Ok(KittiesIndex { db: Arc::new(db) })
+	}
+
+	/// An index kept in memory.
+	#[cfg(test)]
+	fn in_memory() -> Self {
+		KittiesIndex { db: Arc::new(kvdb_memorydb::create(NUM_COLUMNS)) }
+	}
+
+	fn get<T: Decode>(&self, col: Option<u32>, key: &[u8]) -> Result<Option<T>, String> {
+		match self.db.get(col, key).map_err(db_error)? {
+			Some(value) => T::decode(&mut &value[..])
+				.map(Some)
+				.map_err(|e| format!("Error decoding kitties index entry: {:?}", e)),
+			None => Ok(None),
+		}
+	}
+
+	/// The best block of the index.
+	pub fn best(&self) -> Result<Option<BlockNumber>, String> {
+		self.get(COL_META, META_BEST)
+	}
+
+	/// The hash of the indexed block at `number`.
+	pub fn canon_hash(&self, number: BlockNumber) -> Result<Option<Hash>, String> {
+		self.get(COL_META, &canon_key(number))
+	}
+
+	/// Add a block on top of the index.
+	pub fn insert_block(&self, number: BlockNumber, hash: Hash, events: &[(u32, KittyEvent)]) -> Result<(), String> {
+		let mut transaction = DBTransaction::new();
+		for (event_index, event) in events {
+			let key = event_key(number, &hash, *event_index);
+			let indexed = IndexedEvent {
+				block_number: number,
+				block_hash: hash,
+				event_index: *event_index,
+				event: event.clone(),
+			};
+
+			transaction.put(COL_EVENTS, &key, &indexed.encode());
+			transaction.put(COL_BY_KITTY, &prefixed(&event.kitty_id().to_be_bytes(), &key), &[]);
+			for trader in event.traders() {
+				transaction.put(COL_BY_ACCOUNT, &prefixed(trader.as_ref(), &key), &[]);
+			}
+		}
+		transaction.put(COL_META, &canon_key(number), &hash.encode());
+		transaction.put(COL_META, META_BEST, &number.encode());
+
+		self.db.write(transaction).map_err(db_error)
+	}
+
+	/// Remove the best block of the index, which must be the block at `number`.
+	fn remove_best_block(&self, number: BlockNumber) -> Result<(), String> {
+		let hash = match self.canon_hash(number)? {
+			Some(hash) => hash,
+			None => return Ok(()),
+		};
+
+		let mut transaction = DBTransaction::new();
+		for (key, value) in self.db.iter_from_prefix(COL_EVENTS, &block_key(number, &hash)) {
+			let indexed = IndexedEvent::decode(&mut &value[..])
+				.map_err(|e| format!("Error decoding kitties index entry: {:?}", e))?;
+
+			transaction.delete(COL_EVENTS, &key);
+			transaction.delete(COL_BY_KITTY, &prefixed(&indexed.event.kitty_id().to_be_bytes(), &key));
+			for trader in indexed.event.traders() {
+				transaction.delete(COL_BY_ACCOUNT, &prefixed(trader.as_ref(), &key));
+			}
+		}
+		transaction.delete(COL_META, &canon_key(number));
+		match number.checked_sub(1) {
+			Some(parent) => transaction.put(COL_META, META_BEST, &parent.encode()),
+			None => transaction.delete(COL_META, META_BEST),
+		}
+
+		self.db.write(transaction).map_err(db_error)
+	}
+
+	/// Remove the indexed blocks from `number` up to the best block.
+	pub fn retract_from(&self, number: BlockNumber) -> Result<(), String> {
+		while let Some(best) = self.best()? {
+			if best < number {
+				break;
+			}
+			self.remove_best_block(best)?;
+		}
+		Ok(())
+	}
+
+	fn events_with_prefix(&self, col: Option<u32>, prefix: &[u8]) -> Result<Vec<IndexedEvent>, String> {
+		self.db.iter_from_prefix(col, prefix)
+			.map(|(key, _)| self.get(COL_EVENTS, &key[prefix.len()..])?
+				.ok_or_else(|| String::from("Kitties index is inconsistent")))
+			.collect()
+	}
+
+	/// All the indexed events of a kitty, oldest first.
+	pub fn events_of_kitty(&self, kitty_id: KittyIndex) -> Result<Vec<IndexedEvent>, String> {
+		self.events_with_prefix(COL_BY_KITTY, &kitty_id.to_be_bytes())
+	}
+
+	/// All the indexed sales an account bought or sold in, oldest first.
+	pub fn trades_of(&self, account: &AccountId) -> Result<Vec<IndexedEvent>, String> {
+		self.events_with_prefix(COL_BY_ACCOUNT, account.as_ref())
+	}
+}
+
+/// Blocks indexed at a time while catching up with the best block.
+const CATCH_UP_BATCH: BlockNumber = 256;
+
+/// Bring the index to the best block `best_hash`, retracting the blocks of another fork first.
+pub fn sync_to<B, E, RA>(index: &KittiesIndex, client: &Client<B, E, Block, RA>, best_hash: Hash) -> Result<(), String> where
+	B: Backend<Block, Blake2Hasher>,
+	E: CallExecutor<Block, Blake2Hasher>,
+	Client<B, E, Block, RA>: ProvideRuntimeApi,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: KittiesApi<Block>,
+{
+	let header = |hash: Hash| client.header(&BlockId::Hash(hash))
+		.map_err(|e| format!("Error reading header {}: {:?}", hash, e))?
+		.ok_or_else(|| format!("Header {} is missing", hash));
+
+	let events = |hash: Hash| {
+		let events = client.runtime_api().kitty_events(&BlockId::Hash(hash))
+			.map_err(|e| format!("Error reading the kitties events of block {}: {:?}", hash, e))?;
+
+		Ok(events.iter()
+			.filter_map(|(event_index, event)| KittyEvent::from_runtime(event)
+				.map(|event| (*event_index, event)))
+			.collect())
+	};
+
+	sync_with(index, best_hash, &header, &events)
+}
+
+/// Bring the index to the best block `best_hash`, reading headers and kitties events with `header` and
+/// `events`.
+fn sync_with(
+	index: &KittiesIndex,
+	best_hash: Hash,
+	header: &dyn Fn(Hash) -> Result<Header, String>,
+	events: &dyn Fn(Hash) -> Result<Vec<(u32, KittyEvent)>, String>,
+) -> Result<(), String> {
+	let best = header(best_hash)?;
+
+	// the blocks missing from the index, newest first
+	let mut route = Vec::new();
+	let mut current = best.clone();
+	loop {
+		let number = *current.number();
+		let hash = current.hash();
+		if index.canon_hash(number)? == Some(hash) {
+			break;
+		}
+		route.push((number, hash));
+		if number == 0 {
+			break;
+		}
+		current = header(*current.parent_hash())?;
+	}
+
+	// the indexed blocks above the common ancestor are on another fork, or above an indexed best block
+	let first = route.last().map_or(*best.number() + 1, |(number, _)| *number);
+	if index.best()?.map_or(false, |indexed| indexed >= first) {
+		info!(target: "kitties-index", "Retracting indexed blocks from #{}", first);
+		index.retract_from(first)?;
+	}
+
+	for (number, hash) in route.into_iter().rev() {
+		index.insert_block(number, hash, &events(hash)?)?;
+	}
+
+	Ok(())
+}
+
+/// Index the next `CATCH_UP_BATCH` blocks of the best chain. Returns whether the index reached the best block.
+pub fn catch_up<B, E, RA>(index: &KittiesIndex, client: &Client<B, E, Block, RA>) -> Result<bool, String> where
+	B: Backend<Block, Blake2Hasher>,
+	E: CallExecutor<Block, Blake2Hasher>,
+	Client<B, E, Block, RA>: ProvideRuntimeApi,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: KittiesApi<Block>,
+{
+	let info = client.info().chain;
+	let next = index.best()?.map_or(0, |best| best + 1);
+	let target = next.saturating_add(CATCH_UP_BATCH - 1).min(info.best_number);
+	let target_hash = if target == info.best_number {
+		info.best_hash
+	} else {
+		client.block_hash(target)
+			.map_err(|e| format!("Error reading block hash #{}: {:?}", target, e))?
+			.ok_or_else(|| format!("Block #{} is missing", target))?
+	};
+
+	sync_to(index, client, target_hash)?;
+	Ok(target == info.best_number)
+}
+
+/// The task catching up with the best block, a batch of blocks at a time, and then indexing each new
+/// best block.
+///
+/// Only for full nodes: a light node would fetch the events of every block from a full node.
+pub fn run<B, E, RA>(index: Arc<KittiesIndex>, client: Arc<Client<B, E, Block, RA>>) -> impl Future<Item = (), Error = ()> where
+	B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,	Client<B, E, Block, RA>: ProvideRuntimeApi,
+	<Client<B, E, Block, RA> as ProvideRuntimeApi>::Api: KittiesApi<Block>,
+{
+	// subscribe first, the blocks imported while catching up are indexed right after
+	let notifications = client.import_notification_stream();
+
+	let catching_up = {
+		let (index, client) = (index.clone(), client.clone());
+		future::poll_fn(move || -> Poll<(), ()> {
+			match catch_up(&index, &client) {
+				Ok(false) => {
+					// give way to the other tasks before the next batch
+					task::current().notify();
+					Ok(Async::NotReady)
+				},
+				Ok(true) => Ok(Async::Ready(())),
+				Err(e) => {
+					warn!(target: "kitties-index", "Error indexing kitties events: {}", e);
+					Ok(Async::Ready(()))
+				},
+			}
+		})
+	};
+
+	catching_up.and_then(move |_| notifications
+		.filter(|notification| notification.is_new_best)
+		.for_each(move |notification| {
+			if let Err(e) = sync_to(&index, &client, notification.hash) {
+				warn!(target: "kitties-index", "Error indexing kitties events: {}", e);
+			}
+			Ok(())
+		}))
+}
+
+/// An indexed event and whether its block is finalized.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KittyHistoryItem {
+	#[serde(flatten)]
+	pub event: IndexedEvent,
+	pub finalized: bool,
+}
+
+/// Kitties history RPC methods, served from the index.
+#[rpc]
+pub trait KittiesHistoryApi {
+	/// All the events of a kitty on the best chain, oldest first.
+	#[rpc(name = "kitties_eventsOfKitty")]
+	fn events_of_kitty(&self, kitty_id: KittyIndex) -> RpcResult<Vec<KittyHistoryItem>>;
+
+	/// All the sales an account bought or sold in on the best chain, oldest first.
+	#[rpc(name = "kitties_tradesOf")]
+	fn trades_of(&self, account: AccountId) -> RpcResult<Vec<KittyHistoryItem>>;
+}
+
+/// Implementation of the kitties history RPC.
+pub struct KittiesHistory<B, E, RA> {
+	index: Arc<KittiesIndex>,
+	client: Arc<Client<B, E, Block, RA>>,
+}
+
+impl<B, E, RA> KittiesHistory<B, E, RA> {
+	/// Create the RPC reading `index`, and the last finalized block of `client`.
+	pub fn new(index: Arc<KittiesIndex>, client: Arc<Client<B, E, Block, RA>>) -> Self {
+		KittiesHistory { index, client }
+	}
+}
+
+impl<B, E, RA> KittiesHistory<B, E, RA> where
+	B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+{
+	fn history(&self, events: Result<Vec<IndexedEvent>, String>) -> RpcResult<Vec<KittyHistoryItem>> {
+		let finalized = self.client.info().chain.finalized_number;
+		let events = events.map_err(|message| RpcError {
+			code: ErrorCode::InternalError,
+			message,
+			data: None,
+		})?;
+
+		// the index only holds blocks of the best chain, which is final up to the finalized number
+		Ok(events.into_iter()
+			.map(|event| KittyHistoryItem {
+				finalized: event.block_number <= finalized,
+				event,
+			})
+			.collect())
+	}
+}
+
+impl<B, E, RA> KittiesHistoryApi for KittiesHistory<B, E, RA> where
+	B: Backend<Block, Blake2Hasher> + Send + Sync + 'static,
+	E: CallExecutor<Block, Blake2Hasher> + Send + Sync + 'static,
+	RA: Send + Sync + 'static,
+{
+	fn events_of_kitty(&self, kitty_id: KittyIndex) -> RpcResult<Vec<KittyHistoryItem>> {
+		self.history(self.index.events_of_kitty(kitty_id))
+	}
+
+	fn trades_of(&self, account: AccountId) -> RpcResult<Vec<KittyHistoryItem>> {
+		self.history(self.index.trades_of(&account))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::collections::HashMap;
+	use crate::chain_spec::get_from_seed;
+
+	fn hash(n: u8) -> Hash {
+		Hash::repeat_byte(n)
+	}
+
+	/// Headers of a chain with forks, each block of `fork` with a state root of `fork`.
+	#[derive(Default)]
+	struct Chain(HashMap<Hash, Header>);
+
+	impl Chain {
+		fn add(&mut self, parent: Option<Hash>, fork: u8) -> Hash {
+			let number = parent.map_or(0, |parent| self.0[&parent].number() + 1);
+			let header = Header::new(number, Default::default(), hash(fork), parent.unwrap_or_default(), Default::default());
+			let hash = header.hash();
+			self.0.insert(hash, header);
+			hash
+		}
+
+		fn sync(&self, index: &KittiesIndex, best_hash: Hash) {
+			let alice = get_from_seed::<AccountId>("Alice");
+			let header = |hash: Hash| self.0.get(&hash).cloned().ok_or_else(|| String::from("Missing header"));
+			// a kitty created in each block, numbered after the block
+			let events = |hash: Hash| Ok(vec![
+				(0, KittyEvent::Created { owner: alice.clone(), kitty_id: *self.0[&hash].number() as KittyIndex }),
+			]);
+			sync_with(index, best_hash, &header, &events).unwrap();
+		}
+	}
+
+	#[test]
+	fn sync_follows_the_best_chain() {
+		let index = KittiesIndex::in_memory();
+		let mut chain = Chain::default();
+		let genesis = chain.add(None, 0);
+		let a1 = chain.add(Some(genesis), 1);
+		let a2 = chain.add(Some(a1), 1);
+		let b2 = chain.add(Some(a1), 2);
+		let b3 = chain.add(Some(b2), 2);
+
+		chain.sync(&index, a2);
+		assert_eq!(index.best().unwrap(), Some(2));
+		assert_eq!(index.canon_hash(2).unwrap(), Some(a2));
+
+		// another fork becomes the best chain
+		chain.sync(&index, b3);
+		assert_eq!(index.best().unwrap(), Some(3));
+		assert_eq!(index.canon_hash(2).unwrap(), Some(b2));
+		assert_eq!(index.events_of_kitty(2).unwrap().len(), 1);
+		assert_eq!(index.events_of_kitty(2).unwrap()[0].block_hash, b2);
+
+		// the best block goes back to an indexed block, the blocks above it are retracted
+		chain.sync(&index, a1);
+		assert_eq!(index.best().unwrap(), Some(1));
+		assert_eq!(index.canon_hash(1).unwrap(), Some(a1));
+		assert_eq!(index.canon_hash(2).unwrap(), None);
+		assert!(index.events_of_kitty(2).unwrap().is_empty());
+		assert!(index.events_of_kitty(3).unwrap().is_empty());
+		assert_eq!(index.events_of_kitty(1).unwrap().len(), 1);
+	}
+
+	#[test]
+	fn retracted_blocks_are_removed_from_the_index() {
+		let alice = get_from_seed::<AccountId>("Alice");
+		let bob = get_from_seed::<AccountId>("Bob");
+		let index = KittiesIndex::in_memory();
+
+		index.insert_block(0, hash(0), &[]).unwrap();
+		index.insert_block(1, hash(1), &[(0, KittyEvent::Created { owner: alice.clone(), kitty_id: 0 })]).unwrap();
+		index.insert_block(2, hash(2), &[
+			(1, KittyEvent::Ask { owner: alice.clone(), kitty_id: 0, price: Some(10) }),
+			(3, KittyEvent::Sold { from: alice.clone(), to: bob.clone(), kitty_id: 0, price: 10 }),
+		]).unwrap();
+
+		assert_eq!(index.best().unwrap(), Some(2));
+		assert_eq!(index.events_of_kitty(0).unwrap().len(), 3);
+		assert_eq!(index.trades_of(&bob).unwrap().len(), 1);
+
+		// block 2 is replaced by another fork
+		index.retract_from(2).unwrap();
+		index.insert_block(2, hash(22), &[
+			(0, KittyEvent::Transferred { from: alice.clone(), to: bob.clone(), kitty_id: 0 }),
+		]).unwrap();
+
+		assert_eq!(index.canon_hash(2).unwrap(), Some(hash(22)));
+		assert!(index.trades_of(&alice).unwrap().is_empty());
+		assert_eq!(
+			index.events_of_kitty(0).unwrap().into_iter().map(|e| (e.block_number, e.event)).collect::<Vec<_>>(),
+			vec![
+				(1, KittyEvent::Created { owner: alice.clone(), kitty_id: 0 }),
+				(2, KittyEvent::Transferred { from: alice, to: bob, kitty_id: 0 }),
+			],
+		);
+	}
+}
@@ -0,0 +1,171 @@
+//! Kitties RPC, calling the `KittiesApi` of the runtime at a block.
+//!
+//! On a light client the call is executed by a full node, which returns the storage proof of the
+//! execution. The light client executes the call again on the proof, checked against the state root
+//! of the block header, so a light node answers without trusting the full node it asks.
+
+use std::sync::Arc;
+use jsonrpc_core::{Error as RpcError, ErrorCode, Result};
+use jsonrpc_derive::rpc;
+use sr_primitives::traits::ProvideRuntimeApi;
+use substrate_client::blockchain::HeaderBackend;
+use substrate_kitties_runtime::{
+	AccountId, Hash, kitties_api::KittiesApi as KittiesRuntimeApi, opaque::{Block, BlockId},
+};
+use crate::kitties_state::{KittyEntry, KittyIndex, KittyOwner};
+
+/// Kitties RPC methods.
+#[rpc]
+pub trait KittiesApi<BlockHash> {
+	/// Number of kitties at a block, the best block if `at` is not given.
+	#[rpc(name = "kitties_count")]
+	fn kitties_count(&self, at: Option<BlockHash>) -> Result<KittyIndex>;
+
+	/// A kitty, its owner and its price at a block.
+	#[rpc(name = "kitties_kitty")]
+	fn kitty(&self, kitty_id: KittyIndex, at: Option<BlockHash>) -> Result<Option<KittyEntry>>;
+
+	/// The owner of a kitty at a block, with the display name of its identity.
+	#[rpc(name = "kitties_kittyOwner")]
+	fn kitty_owner(&self, kitty_id: KittyIndex, at: Option<BlockHash>) -> Result<Option<KittyOwner>>;
+
+	/// The kitties owned by an account at a block, in the order of its `OwnedKitties` list.
+	#[rpc(name = "kitties_ownedKitties")]
+	fn owned_kitties(&self, owner: AccountId, at: Option<BlockHash>) -> Result<Vec<KittyIndex>>;
+}
+
+/// Implementation of the kitties RPC for full and light clients.
+pub struct Kitties<C> {
+	client: Arc<C>,
+}
+
+impl<C> Kitties<C> {
+	/// Create the RPC calling the runtime of `client`.
+	pub fn new(client: Arc<C>) -> Self {
+		Kitties { client }
+	}
+}
+
+fn error(message: String) -> RpcError {
+	RpcError {
+		code: ErrorCode::InternalError,
+		message,
+		data: None,
+	}
+}
+
+impl<C> Kitties<C> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: KittiesRuntimeApi<Block>,
+{
+	fn call_at<T, E: std::fmt::Debug>(
+		&self,
+		at: Option<Hash>,
+		f: impl FnOnce(&C::Api, &BlockId) -> std::result::Result<T, E>,
+	) -> Result<T> {
+		let at = BlockId::Hash(at.unwrap_or_else(|| self.client.info().best_hash));
+		f(&*self.client.runtime_api(), &at)
+			.map_err(|e| error(format!("Error calling the kitties runtime API at {:?}: {:?}", at, e)))
+	}
+}
+
+impl<C> KittiesApi<Hash> for Kitties<C> where
+	C: ProvideRuntimeApi + HeaderBackend<Block> + Send + Sync + 'static,
+	C::Api: KittiesRuntimeApi<Block>,
+{
+	fn kitties_count(&self, at: Option<Hash>) -> Result<KittyIndex> {
+		self.call_at(at, |api, at| api.kitties_count(at))
+	}
+
+	fn kitty(&self, kitty_id: KittyIndex, at: Option<Hash>) -> Result<Option<KittyEntry>> {
+		let kitty = self.call_at(at, |api, at| api.kitty(at, kitty_id))?;
+		Ok(kitty.map(|kitty| KittyEntry::new(kitty_id, kitty)))
+	}
+
+	fn kitty_owner(&self, kitty_id: KittyIndex, at: Option<Hash>) -> Result<Option<KittyOwner>> {
+		let owner = self.call_at(at, |api, at| api.kitty_owner(at, kitty_id))?;
+		Ok(owner.map(Into::into))
+	}
+
+	fn owned_kitties(&self, owner: AccountId, at: Option<Hash>) -> Result<Vec<KittyIndex>> {
+		self.call_at(at, |api, at| api.owned_kitties(at, owner))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use codec::{Encode, Decode};
+	use primitives::Blake2Hasher;
+	use primitives::hexdisplay::HexDisplay;
+	use sr_primitives::traits::Header as HeaderT;
+	use substrate_client::light::{call_executor::check_execution_proof, fetcher::RemoteCallRequest};
+	use substrate_executor::NativeExecutor;
+	use substrate_kitties_runtime::{ChainParamsConfig, KittiesConfig, RuntimeApi, kitties_api::KittyInfo};
+	use crate::chain_spec::{self, get_from_seed, get_authority_keys_from_seed};
+	use crate::service::Executor;
+
+	#[test]
+	fn full_and_light_clients_read_the_same_kitties() {
+		let alice = get_from_seed::<AccountId>("Alice");
+		let bob = get_from_seed::<AccountId>("Bob");
+		let mut genesis = chain_spec::testnet_genesis(
+			vec![get_authority_keys_from_seed("Alice")],
+			Some(alice.clone()),
+			vec![alice.clone(), bob.clone(), get_from_seed::<AccountId>("Alice//stash")],
+			vec![alice.clone()],
+			ChainParamsConfig::default(),
+			false,
+		);
+		genesis.kitties = Some(KittiesConfig {
+			kitties: vec![
+				(alice.clone(), [1; 16], None),
+				(bob.clone(), [2; 16], Some(100)),
+				(alice.clone(), [3; 16], None),
+			],
+			..Default::default()
+		});
+
+		let client = Arc::new(substrate_client::new_in_mem::<_, Block, _, RuntimeApi>(
+			NativeExecutor::<Executor>::new(None),
+			genesis,
+			None,
+		).unwrap());
+		let kitties = Kitties::new(client.clone());
+
+		assert_eq!(kitties.kitties_count(None).unwrap(), 3);
+		assert_eq!(kitties.kitty(1, None).unwrap(), Some(KittyEntry {
+			id: 1,
+			dna: format!("0x{}", HexDisplay::from(&[2u8; 16])),
+			owner: bob.clone(),
+			price: Some(100),
+		}));
+		assert_eq!(kitties.kitty(3, None).unwrap(), None);
+		assert_eq!(
+			kitties.kitty_owner(2, None).unwrap(),
+			Some(KittyOwner { account: alice.clone(), display_name: None, verified: false }),
+		);
+		assert_eq!(kitties.owned_kitties(alice, None).unwrap(), vec![0, 2]);
+		assert_eq!(kitties.owned_kitties(bob, None).unwrap(), vec![1]);
+
+		// what a light client does: execute the call on the proof of the full node, checked against
+		// the state root of the header
+		let genesis_hash = client.info().chain.best_hash;
+		let header = client.header(&BlockId::Hash(genesis_hash)).unwrap().unwrap();
+		let (full_result, proof) = client.execution_proof(&BlockId::Hash(genesis_hash), "KittiesApi_kitty", &1u32.encode())
+			.unwrap();
+		let request = RemoteCallRequest {
+			block: header.hash(),
+			header,
+			method: "KittiesApi_kitty".into(),
+			call_data: 1u32.encode(),
+			retry_count: None,
+		};
+		let light_result = check_execution_proof::<_, _, Blake2Hasher>(&NativeExecutor::<Executor>::new(None), &request, proof)
+			.unwrap();
+
+		assert_eq!(light_result, full_result);
+		let kitty = Option::<KittyInfo>::decode(&mut &light_result[..]).unwrap().unwrap();
+		assert_eq!((kitty.dna, kitty.price), ([2; 16], Some(100)));
+	}
+}
@@ -0,0 +1,272 @@
+//! The kitties registry at a block, read through the `KittiesApi` of the runtime.
+
+use std::collections::{BTreeMap, BTreeSet};
+use primitives::hexdisplay::HexDisplay;
+use serde::{Serialize, Deserialize};
+use sr_primitives::traits::{ApiRef, ProvideRuntimeApi};
+use substrate_kitties_runtime::{
+	AccountId, Balance, BlockNumber, KittiesConfig, opaque::{Block, BlockId},
+	kitties_api::{KittiesApi, KittyInfo, OwnerInfo},
+};
+
+pub use substrate_kitties_runtime::kitties_api::KittyIndex;
+
+/// A kitty of a snapshot.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct KittyEntry {
+	pub id: KittyIndex,
+	/// The hex encoded DNA, `0x` prefixed.
+	pub dna: String,
+	pub owner: AccountId,
+	/// None means not for sale.
+	pub price: Option<Balance>,
+}
+
+/// The kitties owned by an account, in the order of the `OwnedKitties` list.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OwnedKittiesEntry {
+	pub owner: AccountId,
+	pub kitties: Vec<KittyIndex>,
+}
+
+/// The owner of a kitty and the display name of its identity.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KittyOwner {
+	pub account: AccountId,
+	/// None if the owner has no identity or an empty display name.
+	pub display_name: Option<String>,
+	/// Whether a registrar judged the identity right and none judged it wrong.
+	pub verified: bool,
+}
+
+/// The kitties registry at a block.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct KittiesSnapshot {
+	pub block: BlockNumber,
+	pub kitties: Vec<KittyEntry>,
+	pub owned_kitties: Vec<OwnedKittiesEntry>,
+}
+
+impl KittyEntry {
+	/// The entry of the kitty `kitty_id` returned by `KittiesApi::kitty`.
+	pub fn new(kitty_id: KittyIndex, kitty: KittyInfo) -> Self {
+		KittyEntry {
+			id: kitty_id,
+			dna: format!("0x{}", HexDisplay::from(&kitty.dna)),
+			owner: kitty.owner,
+			price: kitty.price,
+		}
+	}
+}
+
+impl From<OwnerInfo> for KittyOwner {
+	fn from(owner: OwnerInfo) -> Self {
+		KittyOwner {
+			account: owner.account,
+			display_name: Some(owner.display)
+				.filter(|display| !display.is_empty())
+				.map(|display| String::from_utf8_lossy(&display).into_owned()),
+			verified: owner.verified,
+		}
+	}
+}
+
+/// The reads of the kitties registry at a block a snapshot is made of.
+pub trait KittiesReader {
+	/// `KittiesApi::kitties_count`
+	fn kitties_count(&self) -> Result<KittyIndex, String>;
+	/// `KittiesApi::kitty`
+	fn kitty(&self, kitty_id: KittyIndex) -> Result<Option<KittyInfo>, String>;
+	/// `KittiesApi::owned_kitties`
+	fn owned_kitties(&self, owner: &AccountId) -> Result<Vec<KittyIndex>, String>;
+}
+
+/// Reads calling the `KittiesApi` of a client at a block.
+pub struct RuntimeReader<'a, A> {
+	api: ApiRef<'a, A>,
+	at: BlockId,
+}
+
+impl<'a, A: KittiesApi<Block>> RuntimeReader<'a, A> {
+	/// Read at block `at` of `client`.
+	pub fn new<C: ProvideRuntimeApi<Api = A>>(client: &'a C, at: BlockId) -> Self {
+		RuntimeReader { api: client.runtime_api(), at }
+	}
+
+	fn call<T, E: std::fmt::Debug>(&self, f: impl FnOnce(&A, &BlockId) -> Result<T, E>) -> Result<T, String> {
+		f(&*self.api, &self.at).map_err(|e| format!("Error calling the kitties runtime API at {:?}: {:?}", self.at, e))
+	}
+}
+
+impl<'a, A: KittiesApi<Block>> KittiesReader for RuntimeReader<'a, A> {
+	fn kitties_count(&self) -> Result<KittyIndex, String> {
+		self.call(|api, at| api.kitties_count(at))
+	}
+
+	fn kitty(&self, kitty_id: KittyIndex) -> Result<Option<KittyInfo>, String> {
+		self.call(|api, at| api.kitty(at, kitty_id))
+	}
+
+	fn owned_kitties(&self, owner: &AccountId) -> Result<Vec<KittyIndex>, String> {
+		self.call(|api, at| api.owned_kitties(at, owner.clone()))
+	}
+}
+
+/// Read the whole kitties registry at `block` with `reader`.
+pub fn read_snapshot(block: BlockNumber, reader: &dyn KittiesReader) -> Result<KittiesSnapshot, String> {
+	let count = reader.kitties_count()?;
+
+	let mut kitties = Vec::new();
+	let mut owners = BTreeSet::new();
+	for kitty_id in 0..count {
+		let kitty = reader.kitty(kitty_id)?
+			.ok_or_else(|| format!("Kitty {} is missing", kitty_id))?;
+
+		owners.insert(kitty.owner.clone());
+		kitties.push(KittyEntry::new(kitty_id, kitty));
+	}
+
+	let owned_kitties = owners.into_iter()
+		.map(|owner| Ok(OwnedKittiesEntry {
+			kitties: reader.owned_kitties(&owner)?,
+			owner,
+		}))
+		.collect::<Result<Vec<_>, String>>()?;
+
+	Ok(KittiesSnapshot {
+		block,
+		kitties,
+		owned_kitties,
+	})
+}
+
+fn parse_dna(dna: &str) -> Result<[u8; 16], String> {
+	let bytes = hex::decode(dna.trim_start_matches("0x"))
+		.map_err(|e| format!("Invalid DNA {}: {}", dna, e))?;
+	if bytes.len() != 16 {
+		return Err(format!("Invalid DNA {}: expected 16 bytes", dna));
+	}
+	let mut result = [0u8; 16];
+	result.copy_from_slice(&bytes);
+	Ok(result)
+}
+
+impl KittiesSnapshot {
+	/// The `KittiesConfig` genesis section recreating the kitties of the snapshot with the same ids.
+	pub fn into_genesis(mut self) -> Result<KittiesConfig, String> {
+		self.kitties.sort_by_key(|kitty| kitty.id);
+
+		let kitties = self.kitties.into_iter()
+			.enumerate()
+			.map(|(index, kitty)| {
+				if kitty.id as usize != index {
+					return Err(format!("Kitty ids must be contiguous from 0, {} is missing", index));
+				}
+				Ok((kitty.owner, parse_dna(&kitty.dna)?, kitty.price))
+			})
+			.collect::<Result<Vec<_>, String>>()?;
+
+		Ok(KittiesConfig { kitties, ..Default::default() })
+	}
+
+	/// One `id,dna,owner,price` line per kitty with a header line. The block and the owned kitties
+	/// lists are not part of the CSV; the lists are rebuilt in kitty id order when reading it back.
+	pub fn to_csv(&self) -> String {
+		let mut csv = String::from("id,dna,owner,price\n");
+		for kitty in &self.kitties {
+			csv.push_str(&format!(
+				"{},{},{},{}\n",
+				kitty.id,
+				kitty.dna,
+				kitty.owner,
+				kitty.price.map(|price| price.to_string()).unwrap_or_default(),
+			));
+		}
+		csv
+	}
+
+	/// Parse a CSV written by `to_csv`.
+	pub fn from_csv(csv: &str) -> Result<Self, String> {
+		use primitives::crypto::Ss58Codec;
+
+		let mut kitties = Vec::new();
+		for (line_number, line) in csv.lines().enumerate().skip(1).filter(|(_, line)| !line.trim().is_empty()) {
+			let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+			if fields.len() != 4 {
+				return Err(format!("Line {}: expected 4 fields", line_number + 1));
+			}
+			let parse_error = |e: String| format!("Line {}: {}", line_number + 1, e);
+
+			kitties.push(KittyEntry {
+				id: fields[0].parse().map_err(|e| parse_error(format!("Invalid id: {}", e)))?,
+				dna: fields[1].to_string(),
+				owner: AccountId::from_ss58check(fields[2])
+					.map_err(|e| parse_error(format!("Invalid owner: {:?}", e)))?,
+				price: match fields[3] {
+					"" => None,
+					price => Some(price.parse().map_err(|e| parse_error(format!("Invalid price: {}", e)))?),
+				},
+			});
+		}
+
+		let mut owners: BTreeMap<AccountId, Vec<KittyIndex>> = BTreeMap::new();
+		for kitty in &kitties {
+			owners.entry(kitty.owner.clone()).or_default().push(kitty.id);
+		}
+
+		Ok(KittiesSnapshot {
+			block: 0,
+			kitties,
+			owned_kitties: owners.into_iter()
+				.map(|(owner, kitties)| OwnedKittiesEntry { owner, kitties })
+				.collect(),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use substrate_executor::NativeExecutor;
+	use substrate_kitties_runtime::{ChainParamsConfig, RuntimeApi};
+	use crate::chain_spec::{self, get_from_seed, get_authority_keys_from_seed};
+	use crate::service::Executor;
+
+	#[test]
+	fn snapshot_is_read_through_the_runtime_api() {
+		let alice = get_from_seed::<AccountId>("Alice");
+		let bob = get_from_seed::<AccountId>("Bob");
+		let mut genesis = chain_spec::testnet_genesis(
+			vec![get_authority_keys_from_seed("Alice")],
+			Some(alice.clone()),
+			vec![alice.clone(), bob.clone(), get_from_seed::<AccountId>("Alice//stash")],
+			vec![alice.clone()],
+			ChainParamsConfig::default(),
+			false,
+		);
+		genesis.kitties = Some(KittiesConfig {
+			kitties: vec![
+				(alice.clone(), [1; 16], None),
+				(bob.clone(), [2; 16], Some(100)),
+				(alice.clone(), [3; 16], None),
+			],
+			..Default::default()
+		});
+		let client = substrate_client::new_in_mem::<_, Block, _, RuntimeApi>(
+			NativeExecutor::<Executor>::new(None),
+			genesis,
+			None,
+		).unwrap();
+
+		let snapshot = read_snapshot(0, &RuntimeReader::new(&client, BlockId::Number(0))).unwrap();
+
+		assert_eq!(snapshot.kitties.len(), 3);
+		assert_eq!(snapshot.kitties[1].price, Some(100));
+		assert_eq!(
+			snapshot.owned_kitties.iter().map(|entry| (entry.owner.clone(), entry.kitties.clone())).collect::<BTreeMap<_, _>>(),
+			vec![(alice, vec![0, 2]), (bob, vec![1])].into_iter().collect(),
+		);
+	}
+}
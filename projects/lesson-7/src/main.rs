@@ -7,6 +7,15 @@ mod chain_spec;
 #[macro_use]
 mod service;
 mod cli;
+mod testnet;
+mod kitties_state;
+mod snapshot;
+mod kitties_rpc;
+mod fees_rpc;
+mod kitties_index;
+mod dev_seal;
+mod metrics;
+mod upgrade;
 
 pub use substrate_cli::{VersionInfo, IntoExit, error};
 
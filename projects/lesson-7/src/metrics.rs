@@ -0,0 +1,204 @@
+//! Node metrics in the Prometheus text format, served over HTTP next to the informant.
+//!
+//! The kitties counters are updated from the `Kitties` events of each imported best block. Blocks
+//! retracted by a reorg are not subtracted, so the counters are an approximation across forks.
+
+use std::{io::{Read, Write}, net::{SocketAddr, TcpListener}, sync::{Arc, Mutex}, thread, time::Duration};
+use futures::{Future, Stream};
+use log::{debug, warn};
+use substrate_client::{BlockchainEvents, Client};
+use substrate_service::AbstractService;
+use substrate_kitties_runtime::{Balance, kitties, kitties_api::{KittiesApi, KittyEvent}, opaque::{Block, BlockId}};
+use sr_primitives::traits::{Header, ProvideRuntimeApi};
+
+#[derive(Default)]
+struct Values {
+	kitties_created: u64,
+	kitties_bred: u64,
+	kitties_transferred: u64,
+	kitties_sold: u64,
+	kitties_listed: u64,
+	sale_volume: Balance,
+	blocks_imported: u64,
+	best_block: u64,
+	finalized_block: u64,
+	pool_ready: usize,
+	pool_future: usize,
+}
+
+impl Values {
+	fn note_event(&mut self, event: &KittyEvent) {
+		match event {
+			kitties::RawEvent::Created(..) => self.kitties_created += 1,
+			kitties::RawEvent::Bred(..) => self.kitties_bred += 1,
+			kitties::RawEvent::Transferred(..) => self.kitties_transferred += 1,
+			kitties::RawEvent::Sold(_, _, _, price) => {
+				self.kitties_sold += 1;
+				self.sale_volume = self.sale_volume.saturating_add(*price);
+			},
+			_ => {},
+		}
+	}
+
+	fn render(&self) -> String {
+		let mut out = String::new();
+		let mut metric = |name: &str, kind: &str, help: &str, value: String| {
+			out.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n{} {}\n", name, help, name, kind, name, value));
+		};
+
+		metric("kitties_created_total", "counter", "Kitties created, including bred ones.", self.kitties_created.to_string());
+		metric("kitties_bred_total", "counter", "Kitties bred.", self.kitties_bred.to_string());
+		metric("kitties_transferred_total", "counter", "Kitties transferred.", self.kitties_transferred.to_string());
+		metric("kitties_sold_total", "counter", "Kitties sold.", self.kitties_sold.to_string());
+		metric("kitties_sale_volume_total", "counter", "Sum of the prices of sold kitties.", self.sale_volume.to_string());
+		metric("kitties_listed", "gauge", "Kitties for sale at the best block.", self.kitties_listed.to_string());
+		metric("node_blocks_imported_total", "counter", "Blocks imported since start.", self.blocks_imported.to_string());
+		metric("node_best_block", "gauge", "Number of the best block.", self.best_block.to_string());
+		metric("node_finalized_block", "gauge", "Number of the last finalized block.", self.finalized_block.to_string());
+		metric("node_pool_ready", "gauge", "Ready transactions in the pool.", self.pool_ready.to_string());
+		metric("node_pool_future", "gauge", "Future transactions in the pool.", self.pool_future.to_string());
+
+		out
+	}
+}
+
+/// How long a connection may take to send its request or read the response before it is dropped, so
+/// a client that never sends blocks the other scrapes for that long at most.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Serve the metrics on `addr` from a background thread, one plain HTTP response per connection.
+fn serve(addr: SocketAddr, values: Arc<Mutex<Values>>) -> Result<(), String> {
+	let listener = TcpListener::bind(addr)
+		.map_err(|e| format!("Error binding metrics listener on {}: {}", addr, e))?;
+
+	thread::Builder::new()
+		.name("metrics".into())
+		.spawn(move || {
+			for stream in listener.incoming() {
+				let mut stream = match stream {
+					Ok(stream) => stream,
+					Err(e) => {
+						debug!(target: "metrics", "Metrics connection failed: {}", e);
+						continue;
+					},
+				};
+
+				let timeouts = stream.set_read_timeout(Some(CONNECTION_TIMEOUT))
+					.and_then(|_| stream.set_write_timeout(Some(CONNECTION_TIMEOUT)));
+				if let Err(e) = timeouts {
+					debug!(target: "metrics", "Error setting metrics connection timeouts: {}", e);
+					continue;
+				}
+
+				// the request is not parsed, every path returns the metrics
+				let mut request = [0u8; 1024];
+				let _ = stream.read(&mut request);
+
+				let body = values.lock().expect("metrics lock is never poisoned; qed").render();
+				let response = format!(
+					"HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+					body.len(),
+					body,
+				);
+				if let Err(e) = stream.write_all(response.as_bytes()) {
+					debug!(target: "metrics", "Error writing metrics response: {}", e);
+				}
+			}
+		})
+		.map_err(|e| format!("Error spawning metrics thread: {}", e))?;
+
+	Ok(())
+}
+
+/// Start serving the metrics of `service` on `addr`. The returned future updates them on each block import.
+pub fn build<S>(service: &S, addr: SocketAddr) -> Result<impl Future<Item = (), Error = ()>, String> where
+	S: AbstractService<Block = Block>,
+	Client<S::Backend, S::CallExecutor, Block, S::RuntimeApi>: ProvideRuntimeApi,
+	<Client<S::Backend, S::CallExecutor, Block, S::RuntimeApi> as ProvideRuntimeApi>::Api: KittiesApi<Block>,
+{
+	let values = Arc::new(Mutex::new(Values::default()));
+	serve(addr, values.clone())?;
+
+	let client = service.client();
+	let transaction_pool = service.transaction_pool();
+
+	Ok(client.import_notification_stream().for_each(move |notification| {
+		let info = client.info().chain;
+		let status = transaction_pool.status();
+
+		// the runtime is called before taking the lock, which `serve` needs to answer scrapes
+		let (events, listed) = if notification.is_new_best {
+			let at = BlockId::Hash(notification.hash);
+			let api = client.runtime_api();
+			let error = |e| warn!(
+				target: "metrics",
+				"Error calling the kitties runtime API at block {}: {:?}", notification.header.number(), e,
+			);
+
+			let events = api.kitty_events(&at).map_err(&error).unwrap_or_default();
+			let listed = api.listed_count(&at).map_err(&error).ok();
+			(events, listed)
+		} else {
+			(Vec::new(), None)
+		};
+
+		let mut values = values.lock().expect("metrics lock is never poisoned; qed");
+		values.blocks_imported += 1;
+		values.best_block = info.best_number.into();
+		values.finalized_block = info.finalized_number.into();
+		values.pool_ready = status.ready;
+		values.pool_future = status.future;
+
+		events.iter().for_each(|(_, event)| values.note_event(event));
+		if let Some(listed) = listed {
+			values.kitties_listed = listed.into();
+		}
+
+		Ok(())
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::net::TcpStream;
+	use substrate_kitties_runtime::AccountId;
+	use crate::chain_spec::get_from_seed;
+
+	#[test]
+	fn kitties_events_update_counters() {
+		let alice = get_from_seed::<AccountId>("Alice");
+		let bob = get_from_seed::<AccountId>("Bob");
+		let mut values = Values::default();
+
+		values.note_event(&kitties::RawEvent::Created(alice.clone(), 0));
+		values.note_event(&kitties::RawEvent::Created(alice.clone(), 1));
+		values.note_event(&kitties::RawEvent::Bred(alice.clone(), 2, 0, 1));
+		values.note_event(&kitties::RawEvent::Sold(alice.clone(), bob.clone(), 2, 30));
+		values.note_event(&kitties::RawEvent::Sold(bob, alice, 2, 12));
+
+		assert_eq!(values.kitties_created, 2);
+		assert_eq!(values.kitties_bred, 1);
+		assert_eq!(values.kitties_sold, 2);
+
+		let rendered = values.render();
+		assert!(rendered.contains("# TYPE kitties_sale_volume_total counter\nkitties_sale_volume_total 42\n"));
+		assert!(rendered.contains("\nkitties_transferred_total 0\n"));
+	}
+
+	#[test]
+	fn silent_connection_does_not_block_scrapes() {
+		let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+		serve(addr, Arc::new(Mutex::new(Values::default()))).unwrap();
+
+		// answered after the silent connection times out
+		let _silent = TcpStream::connect(addr).unwrap();
+		let mut scrape = TcpStream::connect(addr).unwrap();
+		scrape.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+		let mut response = String::new();
+		scrape.read_to_string(&mut response).unwrap();
+
+		assert!(response.starts_with("HTTP/1.1 200 OK"));
+		assert!(response.contains("\nkitties_listed 0\n"));
+	}
+}
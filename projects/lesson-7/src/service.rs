@@ -6,12 +6,17 @@ use substrate_client::LongestChain;
 use babe::{import_queue, start_babe, Config};
 use grandpa::{self, FinalityProofProvider as GrandpaFinalityProofProvider};
 use futures::prelude::*;
-use substrate_kitties_runtime::{self, GenesisConfig, opaque::Block, RuntimeApi};
+use substrate_kitties_runtime::{self, GenesisConfig, Hash, opaque::Block, RuntimeApi};
 use substrate_service::{error::{Error as ServiceError}, AbstractService, Configuration, ServiceBuilder};
 use transaction_pool::{self, txpool::{Pool as TransactionPool}};
 use inherents::InherentDataProviders;
 use network::construct_simple_protocol;
+use log::info;
 use substrate_executor::native_executor_instance;
+use crate::dev_seal::{self, DevSeal, DevSealApi};
+use crate::kitties_rpc::{Kitties, KittiesApi};
+use crate::fees_rpc::{Fees, FeesApi};
+use crate::kitties_index::{self, KittiesIndex, KittiesHistory, KittiesHistoryApi};
 pub use substrate_executor::NativeExecutor;
 
 // Our native executor instance.
@@ -26,6 +31,55 @@ construct_simple_protocol! {
 	pub struct NodeProtocol where Block = Block { }
 }
 
+/// Fires the exits of the block authoring and of the GRANDPA voter, in this order, ahead of the
+/// rest of the service on shutdown. Dropping it fires them as well.
+pub struct ShutdownSignals {
+	authoring: exit_future::Signal,
+	authoring_done: exit_future::Exit,
+	voter: exit_future::Signal,
+	voter_done: exit_future::Exit,
+}
+
+impl ShutdownSignals {
+	/// Stop the block authoring, then the GRANDPA voter once the block authoring task is done.
+	/// Resolves once the voter task is done.
+	pub fn fire(self) -> impl Future<Item = (), Error = ()> {
+		let ShutdownSignals { authoring, authoring_done, voter, voter_done } = self;
+
+		info!("Stopping block authoring");
+		authoring.fire();
+		authoring_done
+			.and_then(move |_| {
+				info!("Stopping GRANDPA voter");
+				voter.fire();
+				voter_done
+			})
+			.map(|_| info!("Block authoring and GRANDPA voter stopped"))
+	}
+}
+
+/// The exits fired by `ShutdownSignals`, and the signals the tasks fire when they are done. A task
+/// that is never started drops its signal, which counts as done.
+pub struct ShutdownExits {
+	authoring: exit_future::Exit,
+	authoring_done: exit_future::Signal,
+	voter: exit_future::Exit,
+	voter_done: exit_future::Signal,
+}
+
+/// Create the shutdown signals and the exits given to a full service.
+pub fn shutdown_signals() -> (ShutdownSignals, ShutdownExits) {
+	let (authoring_signal, authoring) = exit_future::signal();
+	let (authoring_done_signal, authoring_done) = exit_future::signal();
+	let (voter_signal, voter) = exit_future::signal();
+	let (voter_done_signal, voter_done) = exit_future::signal();
+
+	(
+		ShutdownSignals { authoring: authoring_signal, authoring_done, voter: voter_signal, voter_done },
+		ShutdownExits { authoring, authoring_done: authoring_done_signal, voter, voter_done: voter_done_signal },
+	)
+}
+
 /// Starts a `ServiceBuilder` for a full service.
 ///
 /// Use this macro if you don't actually need the full service, but just the builder in order to
@@ -75,8 +129,12 @@ macro_rules! new_full_start {
 	}}
 }
 
-/// Builds a new service for a full client.
-pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisConfig>)
+/// Builds a new service for a full client, indexing the kitties events in `kitties_index` if given.
+pub fn new_full<C: Send + Default + 'static>(
+	config: Configuration<C, GenesisConfig>,
+	kitties_index: Option<Arc<KittiesIndex>>,
+	shutdown: ShutdownExits,
+)
 	-> Result<impl AbstractService, ServiceError>
 {
 
@@ -86,8 +144,19 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 	let force_authoring = config.force_authoring;
 
 	let (builder, mut import_setup, inherent_data_providers, mut tasks_to_spawn) = new_full_start!(config);
+	let rpc_kitties_index = kitties_index.clone();
 
-	let service = builder.with_network_protocol(|_| Ok(NodeProtocol::new()))?
+	let service = builder
+		.with_rpc_extensions(move |client, _transaction_pool| {
+			let mut io = jsonrpc_core::IoHandler::<substrate_service::RpcMetadata>::default();
+			io.extend_with(KittiesApi::to_delegate(Kitties::new(client.clone())));
+			io.extend_with(FeesApi::to_delegate(Fees::new(client.clone())));
+			if let Some(index) = rpc_kitties_index.clone() {
+				io.extend_with(KittiesHistoryApi::to_delegate(KittiesHistory::new(index, client)));
+			}
+			io
+		})?
+		.with_network_protocol(|_| Ok(NodeProtocol::new()))?
 		.with_finality_proof_provider(|client, backend|
 			Ok(Arc::new(GrandpaFinalityProofProvider::new(backend, client)) as _)
 		)?
@@ -97,6 +166,8 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 		import_setup.take()
 			.expect("Link Half and Block Import are present for Full Services or setup failed before. qed");
 
+	spawn_kitties_index(&service, kitties_index);
+
 	// spawn any futures that were created in the previous setup steps
 	if let Some(tasks) = tasks_to_spawn.take() {
 		for task in tasks {
@@ -132,7 +203,12 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 		};
 
 		let babe = start_babe(babe_config)?;
-		let select = babe.select(service.on_exit()).then(|_| Ok(()));
+		let stop = shutdown.authoring.select(service.on_exit()).then(|_| Ok(()));
+		let authoring_done = shutdown.authoring_done;
+		let select = babe.select(stop).then(move |_| {
+			authoring_done.fire();
+			Ok(())
+		});
 
 		// the BABE authoring task is considered infallible, i.e. if it
 		// fails we take down the service with it.
@@ -164,13 +240,19 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 				link: link_half,
 				network: service.network(),
 				inherent_data_providers: inherent_data_providers.clone(),
-				on_exit: service.on_exit(),
+				// fired on shutdown after the block authoring is stopped, or when the signal is dropped
+				on_exit: shutdown.voter,
 				telemetry_on_connect: Some(service.telemetry_on_connect_stream()),
 			};
 
 			// the GRANDPA voter task is considered infallible, i.e.
 			// if it fails we take down the service with it.
-			service.spawn_essential_task(grandpa::run_grandpa_voter(voter_config)?);
+			let voter_done = shutdown.voter_done;
+			let voter = grandpa::run_grandpa_voter(voter_config)?.then(move |result| {
+				voter_done.fire();
+				result
+			});
+			service.spawn_essential_task(voter);
 		},
 		(_, true) => {
 			grandpa::setup_disabled_grandpa(
@@ -184,13 +266,82 @@ pub fn new_full<C: Send + Default + 'static>(config: Configuration<C, GenesisCon
 	Ok(service)
 }
 
-/// Builds a new service for a light client.
+/// Builds a new service for a full client sealing its own blocks, instantly or on `engine_createBlock`.
+///
+/// No BABE or GRANDPA task is started, blocks are imported as final.
+pub fn new_dev<C: Send + Default + 'static>(
+	config: Configuration<C, GenesisConfig>,
+	mode: DevSeal,
+	kitties_index: Option<Arc<KittiesIndex>>,
+	shutdown: ShutdownExits,
+)
+	-> Result<impl AbstractService, ServiceError>
+{
+	let (command_sink, commands) = futures::sync::mpsc::unbounded::<dev_seal::SealCommand<Hash>>();
+	let rpc_kitties_index = kitties_index.clone();
+
+	let service = ServiceBuilder::new_full::<Block, RuntimeApi, Executor>(config)?
+		.with_select_chain(|_config, backend| {
+			Ok(LongestChain::new(backend.clone()))
+		})?
+		.with_transaction_pool(|config, client|
+			Ok(TransactionPool::new(config, transaction_pool::ChainApi::new(client)))
+		)?
+		.with_import_queue(|_config, client, _select_chain, _transaction_pool| {
+			Ok(dev_seal::import_queue(client))
+		})?
+		.with_rpc_extensions(move |client, _transaction_pool| {
+			let mut io = jsonrpc_core::IoHandler::<substrate_service::RpcMetadata>::default();
+			io.extend_with(KittiesApi::to_delegate(Kitties::new(client.clone())));
+			io.extend_with(FeesApi::to_delegate(Fees::new(client.clone())));
+			if let Some(index) = rpc_kitties_index.clone() {
+				io.extend_with(KittiesHistoryApi::to_delegate(KittiesHistory::new(index, client)));
+			}
+			io.extend_with(DevSealApi::to_delegate(dev_seal::DevSealRpc::new(command_sink.clone())));
+			io
+		})?
+		.with_network_protocol(|_| Ok(NodeProtocol::new()))?
+		.build()?;
+
+	let slot_duration = Config::get_or_compute(&*service.client())?.get();
+	let inherent_data_providers = InherentDataProviders::new();
+	inherent_data_providers
+		.register_provider(dev_seal::SlotInherentDataProvider::new(slot_duration))
+		.map_err(|e| format!("{:?}", e))?;
+
+	let proposer = basic_authorship::ProposerFactory {
+		client: service.client(),
+		transaction_pool: service.transaction_pool(),
+	};
+	let select_chain = service.select_chain()
+		.ok_or(ServiceError::SelectChainRequired)?;
+	let pool_imports = match mode {
+		DevSeal::Instant => Some(service.transaction_pool().import_notification_stream()),
+		DevSeal::Manual => None,
+	};
+
+	let sealing = dev_seal::run(proposer, service.client(), select_chain, inherent_data_providers, commands, pool_imports);
+
+	spawn_kitties_index(&service, kitties_index);
+
+	// like the BABE authoring task, if sealing stops we take down the service with it.
+	let stop = shutdown.authoring.select(service.on_exit()).then(|_| Ok(()));
+	let authoring_done = shutdown.authoring_done;
+	service.spawn_essential_task(sealing.select(stop).then(move |_| {
+		authoring_done.fire();
+		Ok(())
+	}));
+
+	Ok(service)
+}
+
+/// Builds a new service for a light client, which has no kitties index.
 pub fn new_light<C: Send + Default + 'static>(config: Configuration<C, GenesisConfig>)
 	-> Result<impl AbstractService, ServiceError>
 {
 	let inherent_data_providers = InherentDataProviders::new();
 
-	ServiceBuilder::new_light::<Block, RuntimeApi, Executor>(config)?
+	let service = ServiceBuilder::new_light::<Block, RuntimeApi, Executor>(config)?
 		.with_select_chain(|_config, backend| {
 			Ok(LongestChain::new(backend.clone()))
 		})?
@@ -224,9 +375,26 @@ pub fn new_light<C: Send + Default + 'static>(config: Configuration<C, GenesisCo
 
 			Ok((import_queue, finality_proof_request_builder))
 		})?
+		.with_rpc_extensions(move |client, _transaction_pool| {
+			// runtime calls of light clients are executed on storage proofs from full nodes
+			let mut io = jsonrpc_core::IoHandler::<substrate_service::RpcMetadata>::default();
+			io.extend_with(KittiesApi::to_delegate(Kitties::new(client.clone())));
+			io.extend_with(FeesApi::to_delegate(Fees::new(client)));
+			io
+		})?
 		.with_network_protocol(|_| Ok(NodeProtocol::new()))?
 		.with_finality_proof_provider(|client, backend|
 			Ok(Arc::new(GrandpaFinalityProofProvider::new(backend, client)) as _)
 		)?
-		.build()
+		.build()?;
+
+	Ok(service)
+}
+
+/// Spawn the task indexing the kitties events if there is an index.
+fn spawn_kitties_index<S: AbstractService<Block = Block>>(service: &S, index: Option<Arc<KittiesIndex>>) {
+	if let Some(index) = index {
+		let indexing = kitties_index::run(index, service.client());
+		service.spawn_task(indexing.select(service.on_exit()).then(|_| Ok(())));
+	}
 }
@@ -0,0 +1,139 @@
+//! The `export-kitties` and `import-kitties` subcommands, moving the kitties registry between chains.
+
+use std::{fs, io::{self, Write}, path::PathBuf, str::FromStr};
+use structopt::StructOpt;
+use substrate_cli::{error, SharedParams, VersionInfo};
+use substrate_service::Configuration;
+use substrate_kitties_runtime::{BlockNumber, GenesisConfig, opaque::BlockId};
+use crate::cli::load_spec;
+use crate::kitties_state::{self, KittiesSnapshot};
+use log::info;
+
+/// The file format of a kitties snapshot.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotFormat {
+	/// The whole snapshot, including the owned kitties lists.
+	Json,
+	/// One line per kitty.
+	Csv,
+}
+
+impl FromStr for SnapshotFormat {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, String> {
+		match s {
+			"json" => Ok(SnapshotFormat::Json),
+			"csv" => Ok(SnapshotFormat::Csv),
+			_ => Err(format!("Unknown snapshot format {}, expected json or csv", s)),
+		}
+	}
+}
+
+impl SnapshotFormat {
+	fn encode(self, snapshot: &KittiesSnapshot) -> Result<String, String> {
+		match self {
+			SnapshotFormat::Json => serde_json::to_string_pretty(snapshot).map_err(|e| e.to_string()),
+			SnapshotFormat::Csv => Ok(snapshot.to_csv()),
+		}
+	}
+
+	fn decode(self, data: &str) -> Result<KittiesSnapshot, String> {
+		match self {
+			SnapshotFormat::Json => serde_json::from_str(data).map_err(|e| e.to_string()),
+			SnapshotFormat::Csv => KittiesSnapshot::from_csv(data),
+		}
+	}
+}
+
+/// The `export-kitties` command used to dump the kitties registry at a block.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportKittiesCmd {
+	/// Block number to read the kitties at. Defaults to the best block.
+	#[structopt(long = "at", value_name = "BLOCK")]
+	pub at: Option<BlockNumber>,
+
+	/// Output format, `json` or `csv`.
+	#[structopt(long = "format", value_name = "FORMAT", default_value = "json")]
+	pub format: SnapshotFormat,
+
+	/// Output file name or stdout if unspecified.
+	#[structopt(parse(from_os_str))]
+	pub output: Option<PathBuf>,
+
+	#[allow(missing_docs)]
+	#[structopt(flatten)]
+	pub shared_params: SharedParams,
+}
+
+impl ExportKittiesCmd {
+	/// Run the command.
+	pub fn run(self, version: &VersionInfo) -> error::Result<()> {
+		let config: Configuration<(), GenesisConfig> =
+			substrate_cli::create_config_with_db_path(load_spec, &self.shared_params, version)?;
+		let builder = new_full_start!(config).0;
+		let client = builder.client();
+
+		let number = self.at.unwrap_or_else(|| client.info().chain.best_number);
+		let at = BlockId::Number(number);
+
+		let snapshot = kitties_state::read_snapshot(number, &kitties_state::RuntimeReader::new(&*client, at))?;
+		info!("Exporting {} kitties at block {}", snapshot.kitties.len(), number);
+
+		let data = self.format.encode(&snapshot)?;
+		match self.output {
+			Some(path) => fs::write(path, data)?,
+			None => io::stdout().write_all(data.as_bytes())?,
+		}
+
+		Ok(())
+	}
+}
+
+/// The `import-kitties` command used to put an exported kitties registry in the genesis of a chain spec.
+#[derive(Debug, StructOpt, Clone)]
+pub struct ImportKittiesCmd {
+	/// Snapshot file written by `export-kitties`.
+	#[structopt(parse(from_os_str))]
+	pub input: PathBuf,
+
+	/// Format of the snapshot file, `json` or `csv`.
+	#[structopt(long = "format", value_name = "FORMAT", default_value = "json")]
+	pub format: SnapshotFormat,
+
+	/// The chain spec to start from. Any value accepted by `--chain`.
+	#[structopt(long = "chain", value_name = "CHAIN_SPEC", default_value = "local")]
+	pub chain: String,
+
+	/// File to write the new chain spec to.
+	#[structopt(long = "output", parse(from_os_str))]
+	pub output: PathBuf,
+}
+
+impl ImportKittiesCmd {
+	/// Run the command.
+	pub fn run(self) -> error::Result<()> {
+		let snapshot = self.format.decode(&fs::read_to_string(&self.input)?)?;
+		let kitties = snapshot.into_genesis()?;
+		info!("Importing {} kitties", kitties.kitties.len());
+
+		let spec = load_spec(&self.chain)?
+			.ok_or_else(|| format!("Unknown chain spec {}", self.chain))?;
+		let mut json: serde_json::Value = serde_json::from_str(&spec.to_json(false)?)
+			.map_err(|e| format!("Error parsing chain spec: {}", e))?;
+
+		let runtime = json.pointer_mut("/genesis/runtime")
+			.and_then(|runtime| runtime.as_object_mut())
+			.ok_or_else(|| String::from("Chain spec has no runtime genesis, raw chain specs are not supported"))?;
+		runtime.insert(
+			"kitties".into(),
+			serde_json::to_value(&kitties).map_err(|e| format!("Error encoding kitties: {}", e))?,
+		);
+
+		let spec = serde_json::to_string_pretty(&json).map_err(|e| format!("Error encoding chain spec: {}", e))?;
+		fs::write(&self.output, spec)?;
+		info!("Chain spec written to {}", self.output.display());
+
+		Ok(())
+	}
+}
@@ -0,0 +1,172 @@
+//! The `generate-testnet` subcommand, creating fresh validator keys and a chain spec using them.
+
+use std::{fs, io::Write, path::{Path, PathBuf}};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use structopt::StructOpt;
+use primitives::{Pair, sr25519, crypto::key_types};
+use substrate_cli::error;
+use substrate_kitties_runtime::{AccountId, ChainParamsConfig};
+use crate::chain_spec::{self, ChainSpec};
+use log::info;
+
+/// The `generate-testnet` command used to create the keys and the chain spec of a new testnet.
+#[derive(Debug, StructOpt, Clone)]
+pub struct GenerateTestnetCmd {
+	/// Number of validators of the testnet.
+	#[structopt(long = "validators", default_value = "2")]
+	pub validators: u32,
+
+	/// Directory to write the chain spec and the validator keystores to.
+	#[structopt(long = "out", parse(from_os_str))]
+	pub out: PathBuf,
+
+	/// Name of the testnet.
+	#[structopt(long = "name", default_value = "Kitties Testnet")]
+	pub name: String,
+
+	/// Id of the testnet. This is also the name of the chain directory of each validator.
+	#[structopt(long = "id", default_value = "kitties_testnet")]
+	pub id: String,
+
+	/// Slot duration in milliseconds. Defaults to the runtime default.
+	#[structopt(long = "slot-duration", value_name = "MILLISECONDS")]
+	pub slot_duration: Option<u64>,
+
+	/// Number of slots in a BABE epoch. Defaults to the runtime default.
+	#[structopt(long = "epoch-duration", value_name = "SLOTS")]
+	pub epoch_duration: Option<u64>,
+}
+
+impl GenerateTestnetCmd {
+	/// Run the command.
+	///
+	/// Writes `chainspec.json` (raw), a `root/phrase` file for the sudo key and, for each validator `i`,
+	/// a `validator-i` base path with the secret phrase and a keystore holding the session keys.
+	pub fn run(self) -> error::Result<()> {
+		if self.validators == 0 {
+			return Err(String::from("At least one validator is required").into());
+		}
+
+		fs::create_dir_all(&self.out)?;
+
+		let root_phrase = generate_phrase();
+		write_phrase(&self.out.join("root"), &root_phrase)?;
+		let root_key = chain_spec::get_from_suri::<AccountId>(&root_phrase)?;
+
+		let mut initial_authorities = Vec::new();
+		for i in 0..self.validators {
+			let base_path = self.out.join(format!("validator-{}", i));
+			let phrase = generate_phrase();
+			write_phrase(&base_path, &phrase)?;
+
+			let keys = chain_spec::get_authority_keys_from_suri(&phrase)?;
+			let keystore_path = base_path.join("chains").join(&self.id).join("keystore");
+			insert_session_keys(&keystore_path, &phrase, &keys.2, &keys.3)?;
+
+			info!("Validator {}: stash {}, controller {}", i, keys.0, keys.1);
+			initial_authorities.push(keys);
+		}
+
+		let council: Vec<_> = initial_authorities.iter().map(|x| x.1.clone()).collect();
+		let mut endowed_accounts = vec![root_key.clone()];
+		endowed_accounts.extend(initial_authorities.iter().flat_map(|x| vec![x.0.clone(), x.1.clone()]));
+
+		let defaults = ChainParamsConfig::default();
+		let slot_duration = self.slot_duration.unwrap_or(defaults.slot_duration);
+		let epoch_duration = self.epoch_duration.unwrap_or(defaults.epoch_duration);
+		let primary_probability = defaults.primary_probability;
+		if slot_duration < 2 || epoch_duration == 0 {
+			return Err(String::from("Slot duration must be at least 2ms and epoch duration positive").into());
+		}
+
+		let spec = ChainSpec::from_genesis(
+			&self.name,
+			&self.id,
+			move || chain_spec::testnet_genesis(
+				initial_authorities.clone(),
+				Some(root_key.clone()),
+				endowed_accounts.clone(),
+				council.clone(),
+				ChainParamsConfig { slot_duration, epoch_duration, primary_probability },
+				false,
+			),
+			vec![],
+			None,
+			None,
+			None,
+			None
+		);
+
+		let spec_path = self.out.join("chainspec.json");
+		fs::write(&spec_path, spec.to_json(true)?)?;
+
+		info!("Chain spec written to {}", spec_path.display());
+		info!(
+			"Start each validator with `--chain {} --base-path {} --validator`",
+			spec_path.display(),
+			self.out.join("validator-<i>").display(),
+		);
+
+		Ok(())
+	}
+}
+
+fn generate_phrase() -> String {
+	sr25519::Pair::generate_with_phrase(None).1
+}
+
+/// Write `phrase` to `dir/phrase`, readable and writable by the owner only.
+fn write_phrase(dir: &Path, phrase: &str) -> error::Result<()> {
+	fs::create_dir_all(dir)?;
+
+	let path = dir.join("phrase");
+	let mut options = fs::OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	#[cfg(unix)]
+	options.mode(0o600);
+	let mut file = options.open(&path)?;
+	// the mode only applies to new files, a phrase file of an earlier run keeps its permissions
+	#[cfg(unix)]
+	file.set_permissions(fs::Permissions::from_mode(0o600))?;
+	file.write_all(phrase.as_bytes())?;
+	Ok(())
+}
+
+/// Store the BABE and GRANDPA keys derived from `phrase` in the keystore at `path`.
+fn insert_session_keys(
+	path: &Path,
+	phrase: &str,
+	grandpa: &grandpa_primitives::AuthorityId,
+	babe: &babe_primitives::AuthorityId,
+) -> error::Result<()> {
+	let keystore = keystore::Store::open(path.to_path_buf(), None)
+		.map_err(|e| format!("Error opening keystore {}: {:?}", path.display(), e))?;
+	let mut keystore = keystore.write();
+
+	keystore.insert_unknown(key_types::GRANDPA, phrase, grandpa.as_ref())
+		.map_err(|e| format!("Error inserting GRANDPA key: {:?}", e))?;
+	keystore.insert_unknown(key_types::BABE, phrase, babe.as_ref())
+		.map_err(|e| format!("Error inserting BABE key: {:?}", e))?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[cfg(unix)]
+	#[test]
+	fn phrases_are_only_readable_by_the_owner() {
+		let dir = tempfile::tempdir().unwrap();
+		let path = dir.path().join("phrase");
+		fs::write(&path, "old phrase").unwrap();
+		fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+		write_phrase(dir.path(), "new phrase").unwrap();
+
+		assert_eq!(fs::read_to_string(&path).unwrap(), "new phrase");
+		assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+	}
+}
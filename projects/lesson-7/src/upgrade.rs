@@ -0,0 +1,336 @@
+//! The `upgrade-runtime` subcommand, submitting a runtime upgrade to a running node with the sudo key.
+
+use std::{fs, io::{Read, Write}, net::TcpStream, path::PathBuf};
+use codec::{Encode, Decode, Output};
+use primitives::{blake2_256, Blake2Hasher, Pair, sr25519};
+use serde_json::{json, Value};
+use sr_primitives::generic::{self, Era};
+use structopt::StructOpt;
+use substrate_cli::error;
+use substrate_executor::{NativeExecutor, RuntimeInfo};
+use substrate_kitties_runtime::{
+	AccountId, Address, Call, Hash, Index, Signature, SignedExtra, Runtime, RuntimeUpgradeCall, SudoCall, WASM_BINARY, upgrade,
+};
+use version::RuntimeVersion;
+use crate::service::Executor;
+use log::info;
+
+/// The `upgrade-runtime` command used to replace the runtime of a chain through `Sudo`.
+#[derive(Debug, StructOpt, Clone)]
+pub struct UpgradeRuntimeCmd {
+	/// The wasm runtime to upgrade to. Defaults to the runtime built with this node.
+	#[structopt(long = "wasm", parse(from_os_str))]
+	pub wasm: Option<PathBuf>,
+
+	/// Secret URI of the sudo key.
+	#[structopt(long = "suri", default_value = "//Alice")]
+	pub suri: String,
+
+	/// HTTP RPC endpoint of the node to submit the upgrade to.
+	#[structopt(long = "url", default_value = "http://127.0.0.1:9933")]
+	pub url: String,
+}
+
+impl UpgradeRuntimeCmd {
+	/// Run the command.
+	///
+	/// Reads the version of the new runtime, checks it against the version of the chain, then submits
+	/// `Sudo::sudo(RuntimeUpgrade::set_code(..))` signed by the sudo key.
+	pub fn run(self) -> error::Result<()> {
+		let code = match &self.wasm {
+			Some(path) => fs::read(path)?,
+			None => WASM_BINARY.to_vec(),
+		};
+		let new_version = wasm_version(&code)?;
+
+		let rpc = JsonRpc::new(&self.url)?;
+		let chain_version: RuntimeVersion = serde_json::from_value(rpc.call("state_getRuntimeVersion", json!([]))?)
+			.map_err(|e| format!("Error decoding chain runtime version: {}", e))?;
+		check_upgrade(&chain_version, &new_version)?;
+		info!(
+			"Upgrading {} from spec version {} to {}",
+			chain_version.spec_name, chain_version.spec_version, new_version.spec_version,
+		);
+
+		let pair = sr25519::Pair::from_string(&self.suri, None)
+			.map_err(|e| format!("Invalid secret URI: {:?}", e))?;
+		let signer: AccountId = pair.public().into();
+
+		let genesis_hash: Hash = serde_json::from_value(rpc.call("chain_getBlockHash", json!([0]))?)
+			.map_err(|e| format!("Error decoding genesis hash: {}", e))?;
+		let nonce = account_nonce(&rpc, &signer)?;
+
+		let call = upgrade_call(code, &new_version);
+		let address = ChainAddress::new(signer, chain_version.spec_version);
+		let extrinsic = sign(call, address, &pair, nonce, chain_version.spec_version, genesis_hash);
+
+		let hash = rpc.call("author_submitExtrinsic", json!([format!("0x{}", hex::encode(extrinsic.encode()))]))?;
+		info!("Upgrade submitted in extrinsic {}", hash);
+
+		Ok(())
+	}
+}
+
+/// The version of a wasm runtime, read by calling it.
+pub fn wasm_version(code: &[u8]) -> Result<RuntimeVersion, String> {
+	let mut ext = sr_io::TestExternalities::<Blake2Hasher>::new_with_code(code, Default::default());
+	NativeExecutor::<Executor>::new(None)
+		.runtime_version(&mut ext)
+		.ok_or_else(|| String::from("Error reading the version of the wasm runtime"))
+}
+
+/// Check that `new`, read from the wasm blob, can replace `current`. `RuntimeUpgrade::set_code` only
+/// compares the version declared next to the blob, so this is the only check of the blob itself.
+pub fn check_upgrade(current: &RuntimeVersion, new: &RuntimeVersion) -> Result<(), String> {
+	if current.spec_name != new.spec_name {
+		return Err(format!("Spec name {} does not match the chain spec name {}", new.spec_name, current.spec_name));
+	}
+	if new.spec_version <= current.spec_version {
+		return Err(format!(
+			"Spec version {} must be higher than the chain spec version {}",
+			new.spec_version, current.spec_version,
+		));
+	}
+	Ok(())
+}
+
+fn account_nonce(rpc: &JsonRpc, account: &AccountId) -> Result<Index, String> {
+	let mut key = b"System AccountNonce".to_vec();
+	account.encode_to(&mut key);
+	let key = format!("0x{}", hex::encode(blake2_256(&key)));
+
+	match rpc.call("state_getStorage", json!([key]))? {
+		Value::Null => Ok(0),
+		Value::String(value) => {
+			let bytes = hex::decode(value.trim_start_matches("0x")).map_err(|e| format!("Invalid nonce: {}", e))?;
+			Index::decode(&mut &bytes[..]).map_err(|e| format!("Invalid nonce: {:?}", e))
+		},
+		value => Err(format!("Unexpected nonce {}", value)),
+	}
+}
+
+/// The first spec version with `MultiAddress` addresses instead of the addresses of `Indices`.
+const MULTI_ADDRESS: u32 = 15;
+
+/// The address of the signer, encoded for the chain it is submitted to.
+#[derive(Clone, PartialEq, Debug)]
+pub enum ChainAddress {
+	/// An `Indices` address, with the account id after the `0xff` prefix
+	Indices(AccountId),
+	/// A `MultiAddress`
+	Multi(Address),
+}
+
+impl ChainAddress {
+	/// The address of `account` on a chain running `spec_version`.
+	pub fn new(account: AccountId, spec_version: u32) -> Self {
+		if spec_version < MULTI_ADDRESS {
+			ChainAddress::Indices(account)
+		} else {
+			ChainAddress::Multi(Address::from(account))
+		}
+	}
+}
+
+impl Encode for ChainAddress {
+	fn encode_to<T: Output>(&self, dest: &mut T) {
+		match self {
+			ChainAddress::Indices(account) => {
+				dest.push_byte(0xff);
+				account.encode_to(dest);
+			},
+			ChainAddress::Multi(address) => address.encode_to(dest),
+		}
+	}
+}
+
+/// `Sudo::sudo(RuntimeUpgrade::set_code(..))` upgrading to `code` of version `new_version`.
+fn upgrade_call(code: Vec<u8>, new_version: &RuntimeVersion) -> Call {
+	Call::Sudo(SudoCall::sudo(Box::new(Call::RuntimeUpgrade(RuntimeUpgradeCall::set_code(
+		code,
+		new_version.spec_name.as_bytes().to_vec(),
+		new_version.spec_version,
+	)))))
+}
+
+/// Sign an immortal extrinsic from `address`. The spec version is the one of the chain, which differs from the
+/// version of the runtime built with this node when upgrading, so the signed extensions are not
+/// asked for their additional data.
+fn sign(
+	call: Call,
+	address: ChainAddress,
+	pair: &sr25519::Pair,
+	nonce: Index,
+	spec_version: u32,
+	genesis_hash: Hash,
+) -> generic::UncheckedExtrinsic<ChainAddress, Call, Signature, SignedExtra> {
+	let extra: SignedExtra = (
+		system::CheckVersion::<Runtime>::new(),
+		system::CheckGenesis::<Runtime>::new(),
+		system::CheckEra::<Runtime>::from(Era::Immortal),
+		system::CheckNonce::<Runtime>::from(nonce),
+		system::CheckWeight::<Runtime>::new(),
+		balances::TakeFees::<Runtime>::from(0),
+		upgrade::CheckCodeUpgrade,
+	);
+	let additional_signed = (spec_version, genesis_hash, genesis_hash, (), (), (), ());
+
+	let signature = (&call, &extra, &additional_signed).using_encoded(|payload| if payload.len() > 256 {
+		pair.sign(&blake2_256(payload)[..])
+	} else {
+		pair.sign(payload)
+	});
+
+	generic::UncheckedExtrinsic::new_signed(call, address, signature.into(), extra)
+}
+
+/// A minimal JSON-RPC client over HTTP/1.1.
+struct JsonRpc {
+	host: String,
+	path: String,
+}
+
+impl JsonRpc {
+	fn new(url: &str) -> Result<Self, String> {
+		if !url.starts_with("http://") {
+			return Err(format!("Only http:// RPC endpoints are supported, got {}", url));
+		}
+		let rest = &url["http://".len()..];
+		let (host, path) = match rest.find('/') {
+			Some(index) => (&rest[..index], &rest[index..]),
+			None => (rest, "/"),
+		};
+
+		Ok(JsonRpc { host: host.into(), path: path.into() })
+	}
+
+	fn call(&self, method: &str, params: Value) -> Result<Value, String> {
+		let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+		let request = format!(
+			"POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+			self.path, self.host, body.len(), body,
+		);
+
+		let mut stream = TcpStream::connect(&self.host)
+			.map_err(|e| format!("Error connecting to {}: {}", self.host, e))?;
+		stream.write_all(request.as_bytes()).map_err(|e| format!("Error sending {}: {}", method, e))?;
+		let mut response = String::new();
+		stream.read_to_string(&mut response).map_err(|e| format!("Error reading {} response: {}", method, e))?;
+
+		let body = response.splitn(2, "\r\n\r\n").nth(1)
+			.ok_or_else(|| format!("Invalid {} response", method))?;
+		let mut response: Value = serde_json::from_str(body)
+			.map_err(|e| format!("Invalid {} response: {}", method, e))?;
+
+		match response.get("error") {
+			Some(error) => Err(format!("{} failed: {}", method, error)),
+			None => Ok(response["result"].take()),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use primitives::storage::well_known_keys;
+	use sr_primitives::{BuildStorage, traits::Dispatchable};
+	use substrate_kitties_runtime::{
+		ChainParamsConfig, Kitties, KittiesConfig, Origin, UncheckedExtrinsic, VERSION, kitties_api::{self, KittyInfo},
+	};
+	use crate::chain_spec::{self, get_from_seed, get_authority_keys_from_seed};
+	use crate::kitties_state::{self, KittiesReader, KittyIndex};
+
+	/// Reads calling the runtime natively, in the externalities of the test.
+	struct NativeReader;
+
+	impl KittiesReader for NativeReader {
+		fn kitties_count(&self) -> Result<KittyIndex, String> {
+			Ok(Kitties::kitties_count())
+		}
+
+		fn kitty(&self, kitty_id: KittyIndex) -> Result<Option<KittyInfo>, String> {
+			Ok(kitties_api::kitty(kitty_id))
+		}
+
+		fn owned_kitties(&self, owner: &AccountId) -> Result<Vec<KittyIndex>, String> {
+			Ok(Kitties::kitties_of(owner))
+		}
+	}
+
+	#[test]
+	fn upgrade_keeps_kitties_state() {
+		let alice = get_from_seed::<AccountId>("Alice");
+		let mut genesis = chain_spec::testnet_genesis(
+			vec![get_authority_keys_from_seed("Alice")],
+			Some(alice.clone()),
+			vec![alice.clone(), get_from_seed::<AccountId>("Alice//stash")],
+			vec![alice.clone()],
+			ChainParamsConfig::default(),
+			false,
+		);
+		genesis.kitties = Some(KittiesConfig {
+			kitties: vec![(alice.clone(), [7; 16], Some(5))],
+			..Default::default()
+		});
+		let mut ext = sr_io::TestExternalities::<Blake2Hasher>::new(genesis.build_storage().unwrap());
+
+		let before = sr_io::with_externalities(&mut ext, || {
+			kitties_state::read_snapshot(0, &NativeReader)
+		}).unwrap();
+
+		// the chain runs the built runtime, upgrade to the same code declared as the next version
+		let upgraded_code = WASM_BINARY.to_vec();
+		let name = VERSION.spec_name.as_bytes().to_vec();
+		sr_io::with_externalities(&mut ext, || {
+			let same_version = Call::RuntimeUpgrade(RuntimeUpgradeCall::set_code(upgraded_code.clone(), name.clone(), VERSION.spec_version));
+			assert!(same_version.dispatch(Origin::ROOT).is_err());
+
+			let upgrade = Call::RuntimeUpgrade(RuntimeUpgradeCall::set_code(upgraded_code.clone(), name, VERSION.spec_version + 1));
+			assert!(upgrade.dispatch(Origin::ROOT).is_ok());
+			assert_eq!(sr_io::storage(well_known_keys::CODE), Some(upgraded_code.clone()));
+		});
+
+		let after = sr_io::with_externalities(&mut ext, || {
+			kitties_state::read_snapshot(0, &NativeReader)
+		}).unwrap();
+		assert_eq!(after, before);
+		assert_eq!(after.kitties[0].price, Some(5));
+
+		// the new code runs on the upgraded state
+		assert_eq!(wasm_version(&upgraded_code).unwrap().spec_name, VERSION.spec_name);
+	}
+
+	#[test]
+	fn sign_uses_the_address_of_the_chain() {
+		let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+		let alice: AccountId = pair.public().into();
+
+		// spec 14 reads the `Indices` address `0xff ++ account`, which spec 15 can't decode
+		let spec_14 = MULTI_ADDRESS - 1;
+		let call = upgrade_call(vec![1, 2, 3], &VERSION);
+		let address = ChainAddress::new(alice.clone(), spec_14);
+		assert_eq!(address.encode(), [&[0xff][..], alice.as_ref()].concat());
+		let extrinsic = sign(call, address.clone(), &pair, 0, spec_14, Default::default());
+		assert_eq!(extrinsic.signature.unwrap().0, address);
+
+		// spec 15 reads `MultiAddress::Id`, `0x00 ++ account`
+		let call = upgrade_call(vec![1, 2, 3], &VERSION);
+		let address = ChainAddress::new(alice.clone(), MULTI_ADDRESS);
+		assert_eq!(address.encode(), [&[0x00][..], alice.as_ref()].concat());
+		let extrinsic = sign(call, address, &pair, 0, MULTI_ADDRESS, Default::default()).encode();
+		let decoded = UncheckedExtrinsic::decode(&mut &extrinsic[..]).unwrap();
+		assert_eq!(decoded.signature.unwrap().0, Address::from(alice));
+	}
+
+	#[test]
+	fn check_upgrade_rejects_other_chains_and_downgrades() {
+		let mut new = VERSION;
+		new.spec_version += 1;
+		assert!(check_upgrade(&VERSION, &new).is_ok());
+		assert!(check_upgrade(&new, &VERSION).is_err());
+		assert!(check_upgrade(&VERSION, &VERSION).is_err());
+
+		new.spec_name = "other".into();
+		assert!(check_upgrade(&VERSION, &new).is_err());
+	}
+}
@@ -0,0 +1,174 @@
+//! Upgrading the runtime of a running dev node with `upgrade-runtime`.
+//!
+//! The node runs the runtime built with it, and is upgraded to the same runtime with `Core_version`
+//! patched to return the next spec version.
+
+use std::{fs, io::{Read, Write}, net::TcpStream, path::Path, process::{Child, Command}, thread, time::{Duration, Instant}};
+use assert_cmd::cargo::cargo_bin;
+use codec::Encode;
+use parity_wasm::elements::{self, External, FuncBody, Func, Instruction, Instructions, Internal, Local, ValueType};
+use serde_json::{json, Value};
+use substrate_kitties_runtime::WASM_BINARY;
+use version::RuntimeVersion;
+
+const RPC_PORT: u16 = 19933;
+
+/// Kills the node when the test ends, even if it fails.
+struct Node(Child);
+
+impl Drop for Node {
+	fn drop(&mut self) {
+		let _ = self.0.kill();
+		let _ = self.0.wait();
+	}
+}
+
+fn rpc(method: &str, params: Value) -> Result<Value, String> {
+	let body = json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }).to_string();
+	let request = format!(
+		"POST / HTTP/1.1\r\nHost: 127.0.0.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+		body.len(), body,
+	);
+
+	let mut stream = TcpStream::connect(("127.0.0.1", RPC_PORT)).map_err(|e| e.to_string())?;
+	stream.write_all(request.as_bytes()).map_err(|e| e.to_string())?;
+	let mut response = String::new();
+	stream.read_to_string(&mut response).map_err(|e| e.to_string())?;
+
+	let body = response.splitn(2, "\r\n\r\n").nth(1).ok_or("Invalid response")?;
+	let mut response: Value = serde_json::from_str(body).map_err(|e| e.to_string())?;
+	match response.get("error") {
+		Some(error) => Err(error.to_string()),
+		None => Ok(response["result"].take()),
+	}
+}
+
+/// Poll `f` until it returns a value, for at most a minute.
+fn wait_for<T>(what: &str, f: impl Fn() -> Option<T>) -> T {
+	let start = Instant::now();
+	loop {
+		if let Some(value) = f() {
+			return value;
+		}
+		assert!(start.elapsed() < Duration::from_secs(60), "Timed out waiting for {}", what);
+		thread::sleep(Duration::from_millis(500));
+	}
+}
+
+fn runtime_version() -> Option<RuntimeVersion> {
+	rpc("state_getRuntimeVersion", json!([])).ok().and_then(|version| serde_json::from_value(version).ok())
+}
+
+/// `code` with `Core_version` returning `version`.
+///
+/// The new `Core_version` allocates the encoded version with `ext_malloc`, writes it byte by byte
+/// and returns its pointer and length.
+fn with_version(code: &[u8], version: &RuntimeVersion) -> Vec<u8> {
+	let mut module: elements::Module = elements::deserialize_buffer(code).unwrap();
+
+	let imported_functions = module.import_section().unwrap().entries().iter()
+		.filter(|entry| match entry.external() {
+			External::Function(_) => true,
+			_ => false,
+		})
+		.collect::<Vec<_>>();
+	let malloc = imported_functions.iter().position(|entry| entry.field() == "ext_malloc").unwrap() as u32;
+	let imported_count = imported_functions.len() as u32;
+
+	let core_version = module.export_section().unwrap().entries().iter()
+		.find(|entry| entry.field() == "Core_version")
+		.map(|entry| match entry.internal() {
+			Internal::Function(index) => *index,
+			_ => panic!("Core_version is a function"),
+		})
+		.unwrap();
+	let signature = module.function_section().unwrap().entries()[(core_version - imported_count) as usize].type_ref();
+
+	let encoded = version.encode();
+	let mut body = vec![
+		Instruction::I32Const(encoded.len() as i32),
+		Instruction::Call(malloc),
+		Instruction::SetLocal(2),
+	];
+	for (offset, byte) in encoded.iter().enumerate() {
+		body.push(Instruction::GetLocal(2));
+		body.push(Instruction::I32Const(*byte as i32));
+		body.push(Instruction::I32Store8(0, offset as u32));
+	}
+	body.extend(vec![
+		Instruction::I64Const(encoded.len() as i64),
+		Instruction::I64Const(32),
+		Instruction::I64Shl,
+		Instruction::GetLocal(2),
+		Instruction::I64ExtendUI32,
+		Instruction::I64Or,
+		Instruction::End,
+	]);
+
+	let functions = module.function_section_mut().unwrap().entries_mut();
+	let patched = imported_count + functions.len() as u32;
+	functions.push(Func::new(signature));
+	module.code_section_mut().unwrap().bodies_mut()
+		.push(FuncBody::new(vec![Local::new(1, ValueType::I32)], Instructions::new(body)));
+	for entry in module.export_section_mut().unwrap().entries_mut() {
+		if entry.field() == "Core_version" {
+			*entry.internal_mut() = Internal::Function(patched);
+		}
+	}
+
+	elements::serialize(module).unwrap()
+}
+
+/// The dev chain spec with a kitty of the sudo key for sale in genesis.
+fn chain_spec_with_kitty(path: &Path) -> Value {
+	let output = Command::new(cargo_bin("substrate-kitties")).args(&["build-spec", "--chain", "dev"]).output().unwrap();
+	assert!(output.status.success());
+
+	let mut spec: Value = serde_json::from_slice(&output.stdout).unwrap();
+	let owner = spec["genesis"]["runtime"]["sudo"]["key"].clone();
+	spec["genesis"]["runtime"]["kitties"]["kitties"] = json!([[owner, [7; 16], 5]]);
+	fs::write(path, spec.to_string()).unwrap();
+
+	owner
+}
+
+#[test]
+fn upgrade_runtime_keeps_kitties_state() {
+	let base_path = tempfile::tempdir().unwrap();
+	let spec_path = base_path.path().join("spec.json");
+	let owner = chain_spec_with_kitty(&spec_path);
+
+	let _node = Node(Command::new(cargo_bin("substrate-kitties"))
+		.args(&["--chain", spec_path.to_str().unwrap(), "--alice", "--validator"])
+		.arg("--base-path").arg(base_path.path().join("node"))
+		.args(&["--rpc-port", &RPC_PORT.to_string(), "--ws-port", "19944", "--port", "30555"])
+		.spawn()
+		.unwrap());
+
+	wait_for("the first block", || {
+		let header = rpc("chain_getHeader", json!([])).ok()?;
+		let number = u64::from_str_radix(header["number"].as_str()?.trim_start_matches("0x"), 16).ok()?;
+		if number > 0 { Some(()) } else { None }
+	});
+	let kitty = rpc("kitties_kitty", json!([0])).unwrap();
+	assert_eq!(kitty["owner"], owner);
+
+	let mut next = runtime_version().unwrap();
+	next.spec_version += 1;
+	let wasm_path = base_path.path().join("next.wasm");
+	fs::write(&wasm_path, with_version(WASM_BINARY, &next)).unwrap();
+
+	let status = Command::new(cargo_bin("substrate-kitties"))
+		.args(&["upgrade-runtime", "--url", &format!("http://127.0.0.1:{}", RPC_PORT)])
+		.arg("--wasm").arg(&wasm_path)
+		.status()
+		.unwrap();
+	assert!(status.success());
+
+	wait_for("the upgraded runtime", || runtime_version().filter(|version| version.spec_version == next.spec_version));
+
+	// the kitty of the genesis block is kept
+	assert_eq!(rpc("kitties_kitty", json!([0])).unwrap(), kitty);
+	assert_eq!(kitty["price"], json!(5));
+	assert_eq!(kitty["dna"], json!(format!("0x{}", hex::encode([7u8; 16]))));
+}